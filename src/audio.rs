@@ -0,0 +1,141 @@
+#[cfg(test)]
+#[path = "./audio_test.rs"]
+mod audio_test;
+
+use crate::chip8::{DigitizedSound, FRAME_RATE};
+use std::fs::File;
+use std::io::BufWriter;
+
+pub const SAMPLE_RATE: u32 = 44100;
+pub const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE as u64 / FRAME_RATE) as usize;
+/// Pitch of the square wave played while the sound timer runs.
+const BUZZER_FREQUENCY: u32 = 440;
+const VOLUME: i32 = 8192;
+/// Center of the unsigned 8-bit MEGA-CHIP samples.
+const SAMPLE_CENTER: i32 = 128;
+
+/// Turns the buzzer and the digitized MEGA-CHIP sound into 16-bit mono
+/// samples, one frame at a time.
+pub struct Synthesizer {
+    /// Buzzer samples played so far, which keeps the wave continuous.
+    buzzer_position: u64,
+    /// The sound being played and the position in its samples.
+    sound: Option<(DigitizedSound, f64)>,
+}
+
+impl Synthesizer {
+    pub fn new() -> Self {
+        Synthesizer {
+            buzzer_position: 0,
+            sound: None,
+        }
+    }
+
+    /// Returns the samples of a frame. A sound starts over when it differs
+    /// from the one of the previous frame.
+    pub fn frame(&mut self, beep: bool, sound: Option<&DigitizedSound>) -> Vec<i16> {
+        match (sound, &self.sound) {
+            (Some(sound), Some((playing, _))) if sound == playing => {}
+            (Some(sound), _) => self.sound = Some((sound.clone(), 0.0)),
+            (None, _) => self.sound = None,
+        }
+
+        (0..SAMPLES_PER_FRAME)
+            .map(|_| {
+                let mut sample = 0;
+
+                if beep {
+                    sample += self.buzzer_sample();
+                }
+
+                sample += self.sound_sample();
+                sample.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+            })
+            .collect()
+    }
+
+    fn buzzer_sample(&mut self) -> i32 {
+        let half_period =
+            self.buzzer_position * u64::from(BUZZER_FREQUENCY) * 2 / u64::from(SAMPLE_RATE);
+        self.buzzer_position += 1;
+
+        if half_period.is_multiple_of(2) {
+            VOLUME
+        } else {
+            -VOLUME
+        }
+    }
+
+    /// Returns the next sample of the digitized sound, resampled to
+    /// `SAMPLE_RATE`. Sounds that don't loop stay silent once they ended.
+    fn sound_sample(&mut self) -> i32 {
+        let (sound, position) = match &mut self.sound {
+            Some((sound, position)) if !sound.samples.is_empty() => (sound, position),
+            _ => return 0,
+        };
+
+        if *position as usize >= sound.samples.len() {
+            if !sound.looping {
+                return 0;
+            }
+
+            *position %= sound.samples.len() as f64;
+        }
+
+        let sample = sound.samples[*position as usize] as i32;
+        *position += f64::from(sound.sample_rate) / f64::from(SAMPLE_RATE);
+        (sample - SAMPLE_CENTER) * VOLUME / SAMPLE_CENTER
+    }
+}
+
+impl Default for Synthesizer {
+    fn default() -> Self {
+        Synthesizer::new()
+    }
+}
+
+/// Writes the sound of a session to a 16-bit mono WAV file.
+pub struct WavRecorder {
+    path: String,
+    writer: hound::WavWriter<BufWriter<File>>,
+}
+
+impl WavRecorder {
+    pub fn create(path: &str) -> Result<Self, String> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec)
+            .map_err(|err| format!("failed to write '{}': {}", path, err))?;
+
+        Ok(WavRecorder {
+            path: path.to_string(),
+            writer,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn record(&mut self, samples: &[i16]) -> Result<(), String> {
+        for &sample in samples {
+            self.writer
+                .write_sample(sample)
+                .map_err(|err| format!("failed to write '{}': {}", self.path, err))?;
+        }
+
+        Ok(())
+    }
+
+    /// Updates the WAV header with the length and closes the file.
+    pub fn finish(self) -> Result<(), String> {
+        let path = self.path;
+        self.writer
+            .finalize()
+            .map_err(|err| format!("failed to write '{}': {}", path, err))
+    }
+}
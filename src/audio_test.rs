@@ -0,0 +1,94 @@
+use super::*;
+use crate::chip8::Chip8;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use std::env;
+
+fn create_sound(samples: Vec<u8>, looping: bool) -> DigitizedSound {
+    DigitizedSound {
+        sample_rate: SAMPLE_RATE as u16 / 2,
+        samples,
+        looping,
+    }
+}
+
+#[test]
+fn test_silence() {
+    let samples = Synthesizer::new().frame(false, None);
+    assert_eq!(samples, vec![0; SAMPLES_PER_FRAME]);
+}
+
+#[test]
+fn test_buzzer() {
+    let mut synthesizer = Synthesizer::new();
+    let mut samples = synthesizer.frame(true, None);
+    samples.extend(synthesizer.frame(true, None));
+    assert_eq!(samples[0], VOLUME as i16);
+    assert!(samples.iter().all(|&sample| sample.abs() == VOLUME as i16));
+
+    // 440 Hz over two frames, i.e. 1/30 of a second.
+    let changes = samples.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    assert_eq!(changes, 29);
+}
+
+#[test]
+fn test_digitized_sound() {
+    let mut synthesizer = Synthesizer::new();
+    let sound = create_sound(vec![0xc0, 0x40], false);
+    let samples = synthesizer.frame(false, Some(&sound));
+    assert_eq!(&samples[..5], [4096, 4096, -4096, -4096, 0]);
+    assert_eq!(
+        synthesizer.frame(false, Some(&sound)),
+        vec![0; SAMPLES_PER_FRAME]
+    );
+
+    // A new sound starts from the beginning.
+    let sound = create_sound(vec![0xc0, 0x40], true);
+    let samples = synthesizer.frame(true, Some(&sound));
+    assert_eq!(&samples[..6], [12288, 12288, 4096, 4096, 12288, 12288]);
+}
+
+#[test]
+fn test_wav_recorder() {
+    let path = env::temp_dir().join("chip8-audio.wav");
+    let path = path.to_str().unwrap();
+    let mut recorder = WavRecorder::create(path).unwrap();
+    recorder.record(&[0, 100, -100]).unwrap();
+    recorder.record(&[i16::MAX]).unwrap();
+    recorder.finish().unwrap();
+
+    let mut reader = hound::WavReader::open(path).unwrap();
+    assert_eq!(reader.spec().sample_rate, SAMPLE_RATE);
+    assert_eq!(reader.spec().channels, 1);
+    let samples: Vec<i16> = reader.samples().map(Result::unwrap).collect();
+    assert_eq!(samples, vec![0, 100, -100, i16::MAX]);
+    assert!(WavRecorder::create("missing-directory/out.wav").is_err());
+}
+
+#[test]
+fn test_buzzer_timing() {
+    for platform in [
+        Platform::Chip8,
+        Platform::SuperChip,
+        Platform::TwoPage,
+        Platform::Chip8X,
+        Platform::MegaChip,
+    ] {
+        let mut chip8 = Chip8::new(platform, Quirks::new(platform));
+        let [high, low] = (0x1000 | (platform.start_address() + 4)).to_be_bytes();
+        // ST := 60, then loop.
+        chip8.load(&[0x60, 60, 0xf0, 0x18, high, low]);
+
+        let mut synthesizer = Synthesizer::new();
+        let ticks = platform.cpu_frequency() / FRAME_RATE;
+        let samples = (0..90)
+            .flat_map(|_| {
+                let beep = chip8.run_frame([false; 16], ticks).beep;
+                synthesizer.frame(beep, None)
+            })
+            .filter(|&sample| sample != 0)
+            .count();
+
+        assert_eq!(samples, 60 * SAMPLES_PER_FRAME, "{}", platform.name());
+    }
+}
@@ -0,0 +1,215 @@
+#[cfg(test)]
+#[path = "./capture_test.rs"]
+mod capture_test;
+
+use crate::chip8::FRAME_RATE;
+use crate::framebuffer::{Framebuffer, BYTES_PER_PIXEL};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// GIF frame delays are given in hundredths of a second.
+const GIF_TIME_UNITS: u64 = 100;
+/// Colors of a GIF frame palette.
+const GIF_MAX_COLORS: usize = 256;
+/// Quantization speed for frames with more colors, from 1 (best) to 30.
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+/// Saves the framebuffer as a PNG image, `scale` times its size.
+pub fn write_png(framebuffer: &Framebuffer, scale: u32, path: &str) -> Result<(), String> {
+    let error = |err: &dyn std::fmt::Display| format!("failed to write '{}': {}", path, err);
+    let width = framebuffer.width() as u32 * scale;
+    let height = framebuffer.height() as u32 * scale;
+    let file = File::create(path).map_err(|err| error(&err))?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| {
+            writer.write_image_data(&framebuffer.scaled(width as usize, height as usize))
+        })
+        .map_err(|err| error(&err))
+}
+
+/// Returns the first `PREFIX-N.EXTENSION` file name that is not taken yet.
+pub fn next_file_name(prefix: &str, extension: &str) -> String {
+    (1..)
+        .map(|number| format!("{}-{}.{}", prefix, number, extension))
+        .find(|name| !Path::new(name).exists())
+        .unwrap()
+}
+
+enum Output {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        /// The last frame and the frame number it was first shown at. It is
+        /// written once it changes, so still images take a single frame.
+        pending: Option<(Vec<u8>, u64)>,
+    },
+    /// Frames of RGB pixels, one after another.
+    Raw(BufWriter<File>),
+}
+
+/// Records the framebuffer at 60 frames per second, either as an animated
+/// GIF or as raw RGB frames, e.g. for
+/// `ffmpeg -f rawvideo -pixel_format rgb24 -video_size WxH -framerate 60`.
+/// Every frame is scaled to the size the recording started with.
+pub struct Recorder {
+    path: String,
+    output: Output,
+    width: usize,
+    height: usize,
+    frames: u64,
+}
+
+impl Recorder {
+    /// Creates a GIF recording for paths ending in `.gif` and a raw one for
+    /// others.
+    pub fn create(path: &str, width: usize, height: usize) -> Result<Self, String> {
+        let error = |err: &dyn std::fmt::Display| format!("failed to write '{}': {}", path, err);
+        let file = BufWriter::new(File::create(path).map_err(|err| error(&err))?);
+        let is_gif = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+
+        let output = if is_gif {
+            let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])
+                .map_err(|err| error(&err))?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(|err| error(&err))?;
+
+            Output::Gif {
+                encoder,
+                pending: None,
+            }
+        } else {
+            Output::Raw(file)
+        };
+
+        Ok(Recorder {
+            path: path.to_string(),
+            output,
+            width,
+            height,
+            frames: 0,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Adds the framebuffer as the next frame.
+    pub fn record(&mut self, framebuffer: &Framebuffer) -> Result<(), String> {
+        let pixels = framebuffer.scaled(self.width, self.height);
+        let frame = self.frames;
+        self.frames += 1;
+
+        match &mut self.output {
+            Output::Gif { pending, .. } => match pending {
+                Some((previous, _)) if *previous == pixels => Ok(()),
+                _ => {
+                    let previous = pending.replace((pixels, frame));
+                    self.write_gif_frame(previous, frame)
+                }
+            },
+            Output::Raw(file) => file
+                .write_all(&pixels)
+                .map_err(|err| format!("failed to write '{}': {}", self.path, err)),
+        }
+    }
+
+    /// Writes the remaining frames and closes the file.
+    pub fn finish(mut self) -> Result<(), String> {
+        if let Output::Gif { pending, .. } = &mut self.output {
+            let last = pending.take();
+            self.write_gif_frame(last, self.frames)?;
+        }
+
+        let Recorder { path, output, .. } = self;
+        let result = match output {
+            Output::Gif { encoder, .. } => encoder
+                .into_inner()
+                .map_err(|err| err.to_string())
+                .and_then(|mut file| file.flush().map_err(|err| err.to_string())),
+            Output::Raw(mut file) => file.flush().map_err(|err| err.to_string()),
+        };
+
+        result.map_err(|err| format!("failed to write '{}': {}", path, err))
+    }
+
+    /// Writes a GIF frame shown from its first frame number until `end`.
+    /// Frames shown longer than a GIF delay allows are repeated.
+    fn write_gif_frame(&mut self, frame: Option<(Vec<u8>, u64)>, end: u64) -> Result<(), String> {
+        let (pixels, start) = match frame {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+
+        let mut frame = gif_frame(self.width as u16, self.height as u16, &pixels);
+        let mut delay = centiseconds(end) - centiseconds(start);
+
+        while delay > 0 {
+            frame.delay = delay.min(u64::from(u16::MAX)) as u16;
+            delay -= u64::from(frame.delay);
+
+            if let Output::Gif { encoder, .. } = &mut self.output {
+                encoder
+                    .write_frame(&frame)
+                    .map_err(|err| format!("failed to write '{}': {}", self.path, err))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Time of a frame in GIF time units, rounded so that the delays add up to
+/// 60 frames per second.
+fn centiseconds(frame: u64) -> u64 {
+    (frame * GIF_TIME_UNITS + FRAME_RATE / 2) / FRAME_RATE
+}
+
+/// Builds a frame with a palette of the colors it uses. Frames with more
+/// colors than a GIF palette holds, e.g. from the filters or MEGA-CHIP, are
+/// quantized.
+fn gif_frame(width: u16, height: u16, pixels: &[u8]) -> gif::Frame<'static> {
+    let mut colors: HashMap<&[u8], u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(pixels.len() / BYTES_PER_PIXEL);
+
+    for color in pixels.chunks(BYTES_PER_PIXEL) {
+        let index = match colors.get(color) {
+            Some(&index) => index,
+            None if colors.len() < GIF_MAX_COLORS => {
+                let index = colors.len() as u8;
+                colors.insert(color, index);
+                palette.extend_from_slice(color);
+                index
+            }
+            None => {
+                return gif::Frame::from_rgb_speed(width, height, pixels, GIF_QUANTIZATION_SPEED)
+            }
+        };
+
+        indices.push(index);
+    }
+
+    gif::Frame::from_palette_pixels(width, height, indices, palette, None)
+}
@@ -0,0 +1,137 @@
+use super::*;
+use crate::framebuffer::Filter;
+use crate::memory::Memory;
+use crate::palette::Palette;
+use crate::platform::Platform;
+use crate::window::{HEIGHT_LO_RES, WIDTH_LO_RES};
+use std::env;
+use std::fs;
+
+fn temp_path(name: &str) -> String {
+    let directory = env::temp_dir().join("chip8-capture");
+    fs::create_dir_all(&directory).unwrap();
+    directory.join(name).to_str().unwrap().to_string()
+}
+
+fn render(framebuffer: &mut Framebuffer, lit: &[(usize, usize)]) {
+    let mut memory = Memory::new(Platform::Chip8);
+
+    for &(x, y) in lit {
+        memory.write_vram(x, y, 1);
+    }
+
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+}
+
+#[test]
+fn test_write_png() {
+    let path = temp_path("screenshot.png");
+    let mut framebuffer = Framebuffer::new(Palette::parse("amber").unwrap(), Filter::None);
+    render(&mut framebuffer, &[(0, 0)]);
+    write_png(&framebuffer, 2, &path).unwrap();
+
+    let decoder = png::Decoder::new(std::io::BufReader::new(fs::File::open(&path).unwrap()));
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (128, 64));
+    assert_eq!(&pixels[..3], [0xff, 0xb0, 0x00]);
+    assert_eq!(&pixels[3..6], [0xff, 0xb0, 0x00]);
+    assert_eq!(&pixels[6..9], [0x1a, 0x0f, 0x00]);
+
+    assert!(write_png(&framebuffer, 1, "missing/screenshot.png").is_err());
+}
+
+#[test]
+fn test_record_gif() {
+    let path = temp_path("recording.gif");
+    let mut framebuffer = Framebuffer::new(Palette::default(), Filter::None);
+    let mut recorder = Recorder::create(&path, WIDTH_LO_RES, HEIGHT_LO_RES).unwrap();
+
+    render(&mut framebuffer, &[]);
+    for _ in 0..3 {
+        recorder.record(&framebuffer).unwrap();
+    }
+
+    render(&mut framebuffer, &[(1, 1)]);
+    recorder.record(&framebuffer).unwrap();
+    assert_eq!(recorder.frames(), 4);
+    recorder.finish().unwrap();
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(fs::File::open(&path).unwrap()).unwrap();
+    let mut delays = Vec::new();
+    let mut last = Vec::new();
+
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delays.push(frame.delay);
+        last = frame.buffer.to_vec();
+    }
+
+    assert_eq!(delays, vec![5, 2]);
+    let offset = (1 + WIDTH_LO_RES) * 4;
+    assert_eq!(&last[offset..offset + 4], [255, 255, 255, 255]);
+}
+
+#[test]
+fn test_record_long_gif_frame() {
+    let path = temp_path("long.gif");
+    let mut framebuffer = Framebuffer::new(Palette::default(), Filter::None);
+    let mut recorder = Recorder::create(&path, WIDTH_LO_RES, HEIGHT_LO_RES).unwrap();
+    render(&mut framebuffer, &[]);
+    let pixels = framebuffer.scaled(WIDTH_LO_RES, HEIGHT_LO_RES);
+    // 1000 s, more than the 655.35 s of a single GIF frame.
+    recorder
+        .write_gif_frame(Some((pixels, 0)), 1000 * FRAME_RATE)
+        .unwrap();
+    recorder.finish().unwrap();
+
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(fs::File::open(&path).unwrap())
+        .unwrap();
+    let mut delays = Vec::new();
+
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delays.push(frame.delay);
+    }
+
+    assert_eq!(delays, vec![u16::MAX, 34465]);
+}
+
+#[test]
+fn test_record_raw() {
+    let path = temp_path("recording.rgb");
+    let mut framebuffer = Framebuffer::new(Palette::default(), Filter::None);
+    let mut recorder = Recorder::create(&path, WIDTH_LO_RES * 2, HEIGHT_LO_RES * 2).unwrap();
+
+    render(&mut framebuffer, &[(0, 0)]);
+    recorder.record(&framebuffer).unwrap();
+    recorder.record(&framebuffer).unwrap();
+    recorder.finish().unwrap();
+
+    let frame_size = WIDTH_LO_RES * HEIGHT_LO_RES * 4 * BYTES_PER_PIXEL;
+    let data = fs::read(&path).unwrap();
+    assert_eq!(data.len(), frame_size * 2);
+    assert_eq!(&data[frame_size..frame_size + 6], [255; 6]);
+}
+
+#[test]
+fn test_centiseconds() {
+    let delays: Vec<u64> = (0..6)
+        .map(|frame| centiseconds(frame + 1) - centiseconds(frame))
+        .collect();
+    assert_eq!(delays, vec![2, 1, 2, 2, 1, 2]);
+    assert_eq!(centiseconds(60), 100);
+}
+
+#[test]
+fn test_next_file_name() {
+    let prefix = temp_path("shot");
+    let _ = fs::remove_file(format!("{}-1.png", prefix));
+    let _ = fs::remove_file(format!("{}-2.png", prefix));
+    assert_eq!(next_file_name(&prefix, "png"), format!("{}-1.png", prefix));
+
+    fs::write(format!("{}-1.png", prefix), b"").unwrap();
+    assert_eq!(next_file_name(&prefix, "png"), format!("{}-2.png", prefix));
+}
@@ -1,15 +1,243 @@
+#[cfg(test)]
+#[path = "./cartridge_test.rs"]
+mod cartridge_test;
+
+use crate::octo::{self, OctoOptions};
+use crate::symbols::{Symbols, SYMBOL_EXTENSION};
 use std::fs;
 use std::io::prelude::*;
+use std::io::Cursor;
+use std::path::Path;
+use std::time::SystemTime;
+use zip::ZipArchive;
+
+/// Extensions of ROM files, including Octo cartridges.
+pub const ROM_EXTENSIONS: &[&str] = &["ch8", "c8", "sc8", "c8x", "mc8", "rom", "bin", "gif"];
+pub const ARCHIVE_EXTENSION: &str = "zip";
+/// Metadata that macOS adds to zip archives.
+const ARCHIVE_METADATA: &str = "__MACOSX/";
 
+#[derive(Default)]
 pub struct Cartridge {
-    pub rom: [u8; 3584],
+    pub rom: Vec<u8>,
+    pub symbols: Symbols,
+    /// Options embedded in an Octo cartridge.
+    pub options: Option<OctoOptions>,
+    path: String,
+    /// ROM in a zip archive.
+    entry: Option<String>,
+    symbol_path: Option<String>,
+    modified: Option<SystemTime>,
 }
 
 impl Cartridge {
-    pub fn new(file: &str) -> Self {
-        let mut f = fs::File::open(file).expect("file not found");
-        let mut buffer = [0u8; 3584];
-        let _ = f.read(&mut buffer);
-        Cartridge { rom: buffer }
+    /// Reads a ROM, an Octo cartridge or a zip archive with a single ROM.
+    pub fn load(file: &str) -> Result<Self, String> {
+        Cartridge::open(file, None)
+    }
+
+    /// Reads the ROM `entry` of a zip archive.
+    pub fn load_entry(file: &str, entry: &str) -> Result<Self, String> {
+        Cartridge::open(file, Some(entry))
+    }
+
+    fn open(file: &str, entry: Option<&str>) -> Result<Self, String> {
+        let mut cartridge = Cartridge {
+            path: file.to_string(),
+            entry: entry.map(str::to_string),
+            modified: modified(file),
+            ..Cartridge::default()
+        };
+
+        cartridge.read()?;
+        Ok(cartridge)
+    }
+
+    /// Reads the ROMs in a zip archive with their raw data, opening it only
+    /// once. Octo cartridges are not assembled.
+    pub fn read_entries(file: &str) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let data = fs::read(file).map_err(|err| format!("failed to read '{}': {}", file, err))?;
+
+        if !is_archive(&data) {
+            return Ok(Vec::new());
+        }
+
+        let mut archive = open_archive(file, data)?;
+        rom_entries(&archive)
+            .into_iter()
+            .map(|entry| {
+                let data = read_entry(&mut archive, &entry).map_err(|err| {
+                    format!("failed to read '{}' from '{}': {}", entry, file, err)
+                })?;
+                Ok((entry, data))
+            })
+            .collect()
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Names the ROM, with its entry for one in an archive.
+    pub fn name(&self) -> String {
+        match &self.entry {
+            Some(entry) => format!("{}:{}", self.path, entry),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Returns the symbol file next to the ROM `file`, if there is one.
+    pub fn symbol_file(file: &str) -> Option<String> {
+        let path = Path::new(file).with_extension(SYMBOL_EXTENSION);
+
+        if path.is_file() {
+            path.to_str().map(str::to_string)
+        } else {
+            None
+        }
+    }
+
+    pub fn load_symbols(&mut self, path: &str) -> Result<(), String> {
+        self.symbols = Symbols::load(path)?;
+        self.symbol_path = Some(path.to_string());
+        Ok(())
+    }
+
+    /// Reads the ROM and its symbols from disk again. The cartridge stays
+    /// unchanged if either can't be read.
+    pub fn reload(&mut self) -> Result<(), String> {
+        let symbols = match &self.symbol_path {
+            Some(path) => Some(Symbols::load(path)?),
+            None => None,
+        };
+
+        self.read()?;
+
+        if let Some(symbols) = symbols {
+            self.symbols = symbols;
+        }
+
+        self.modified = modified(&self.path);
+        Ok(())
+    }
+
+    /// Whether the ROM file was modified since it was read.
+    pub fn changed(&self) -> bool {
+        let current = modified(&self.path);
+        current.is_some() && current != self.modified
+    }
+
+    /// Reads the ROM from the file, extracting it from a zip archive and
+    /// assembling Octo cartridges, whose labels become the symbols.
+    fn read(&mut self) -> Result<(), String> {
+        let file = &self.path;
+        let mut data =
+            fs::read(file).map_err(|err| format!("failed to read '{}': {}", file, err))?;
+
+        if is_archive(&data) {
+            let mut archive = open_archive(file, data)?;
+            let entry = match &self.entry {
+                Some(entry) => entry.clone(),
+                None => match rom_entries(&archive).as_slice() {
+                    [entry] => entry.clone(),
+                    [] => return Err(format!("'{}' contains no ROMs", file)),
+                    entries => return Err(format!("'{}' contains {} ROMs", file, entries.len())),
+                },
+            };
+
+            data = read_entry(&mut archive, &entry)
+                .map_err(|err| format!("failed to read '{}' from '{}': {}", entry, file, err))?;
+            self.entry = Some(entry);
+        }
+
+        if octo::is_cartridge(&data) {
+            let (program, options) = octo::read_cartridge(&data)
+                .map_err(|err| format!("failed to load cartridge '{}': {}", file, err))?;
+
+            self.rom = program.rom;
+            self.symbols = program.symbols;
+            self.options = Some(options);
+        } else {
+            self.rom = data;
+            self.symbols = Symbols::new();
+            self.options = None;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a ROM, or the `entry` of a zip archive, with the given symbol
+/// file or the one next to it.
+pub fn open_cartridge(
+    path: &str,
+    entry: Option<&str>,
+    symbols: Option<&str>,
+) -> Result<Cartridge, String> {
+    let mut cartridge = match entry {
+        Some(entry) => Cartridge::load_entry(path, entry)?,
+        None => Cartridge::load(path)?,
+    };
+    let symbol_file = symbols
+        .map(str::to_string)
+        .or_else(|| Cartridge::symbol_file(path));
+
+    if let Some(symbol_file) = symbol_file {
+        cartridge.load_symbols(&symbol_file)?;
     }
+
+    Ok(cartridge)
+}
+
+/// Whether `data` starts like a zip archive.
+fn is_archive(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04")
+}
+
+fn open_archive(file: &str, data: Vec<u8>) -> Result<ZipArchive<Cursor<Vec<u8>>>, String> {
+    ZipArchive::new(Cursor::new(data))
+        .map_err(|err| format!("failed to open archive '{}': {}", file, err))
+}
+
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    entry: &str,
+) -> zip::result::ZipResult<Vec<u8>> {
+    let mut data = Vec::new();
+    archive.by_name(entry)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn rom_entries(archive: &ZipArchive<Cursor<Vec<u8>>>) -> Vec<String> {
+    let mut entries: Vec<String> = archive
+        .file_names()
+        .filter(|name| is_rom_file(name) && !name.starts_with(ARCHIVE_METADATA))
+        .map(str::to_string)
+        .collect();
+
+    entries.sort();
+    entries
+}
+
+/// Whether the file name has the extension of a ROM.
+pub fn is_rom_file(name: &str) -> bool {
+    has_extension(name, ROM_EXTENSIONS)
+}
+
+/// Whether the file name has the extension of a zip archive.
+pub fn is_archive_file(name: &str) -> bool {
+    has_extension(name, &[ARCHIVE_EXTENSION])
+}
+
+fn has_extension(name: &str, extensions: &[&str]) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.contains(&extension.to_lowercase().as_str()))
+}
+
+fn modified(file: &str) -> Option<SystemTime> {
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
 }
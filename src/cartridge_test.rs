@@ -0,0 +1,137 @@
+use super::*;
+use std::env;
+use std::io::Cursor;
+use std::time::Duration;
+
+/// Writes a file to a directory of its own in the temporary directory.
+fn create_file(test: &str, name: &str, contents: &[u8]) -> String {
+    let directory = env::temp_dir().join(format!("chip8-cartridge-{}", test));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_load() {
+    let path = create_file("load", "game.ch8", &[0x12, 0x00]);
+    let cartridge = Cartridge::load(&path).unwrap();
+    assert_eq!(cartridge.rom, vec![0x12, 0x00]);
+    assert_eq!(cartridge.path(), path);
+    assert!(Cartridge::load("missing.ch8").is_err());
+}
+
+#[test]
+fn test_symbol_file() {
+    let path = create_file("symbol-file", "game.ch8", &[0x12, 0x00]);
+    // Left over from an earlier run.
+    let _ = fs::remove_file(Path::new(&path).with_extension(SYMBOL_EXTENSION));
+    assert_eq!(Cartridge::symbol_file(&path), None);
+
+    let symbols = create_file("symbol-file", "game.sym", b"0x200 main\n");
+    assert_eq!(Cartridge::symbol_file(&path), Some(symbols));
+}
+
+#[test]
+fn test_reload() {
+    let path = create_file("reload", "game.ch8", &[0x12, 0x00]);
+    let symbols = create_file("reload", "game.sym", b"0x200 main\n");
+    let mut cartridge = Cartridge::load(&path).unwrap();
+    cartridge.load_symbols(&symbols).unwrap();
+    assert!(!cartridge.changed());
+
+    // File times can be too coarse to tell quick writes apart.
+    fs::write(&path, [0x00, 0xe0, 0x12, 0x02]).unwrap();
+    let file = fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    fs::write(&symbols, b"0x200 start\n").unwrap();
+    assert!(cartridge.changed());
+
+    cartridge.reload().unwrap();
+    assert_eq!(cartridge.rom, vec![0x00, 0xe0, 0x12, 0x02]);
+    assert_eq!(cartridge.symbols.name(0x200), Some("start"));
+    assert!(!cartridge.changed());
+}
+
+fn create_archive(test: &str, files: &[(&str, &[u8])]) -> String {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+    for (name, contents) in files {
+        writer
+            .start_file(*name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(contents).unwrap();
+    }
+
+    let archive = writer.finish().unwrap().into_inner();
+    create_file(test, "roms.zip", &archive)
+}
+
+#[test]
+fn test_load_archive() {
+    let path = create_archive(
+        "archive",
+        &[
+            ("readme.txt", b"Pong"),
+            ("__MACOSX/._pong.ch8", b"metadata"),
+            ("pong.ch8", &[0x12, 0x00]),
+        ],
+    );
+    assert_eq!(
+        Cartridge::read_entries(&path).unwrap(),
+        vec![("pong.ch8".to_string(), vec![0x12, 0x00])]
+    );
+
+    let cartridge = Cartridge::load(&path).unwrap();
+    assert_eq!(cartridge.rom, vec![0x12, 0x00]);
+    assert_eq!(cartridge.name(), format!("{}:pong.ch8", path));
+}
+
+#[test]
+fn test_load_archive_entry() {
+    let path = create_archive(
+        "archive-entry",
+        &[("pong.ch8", &[0x12, 0x00]), ("tetris.ch8", &[0x00, 0xe0])],
+    );
+    assert!(Cartridge::load(&path)
+        .err()
+        .unwrap()
+        .contains("contains 2 ROMs"));
+
+    assert_eq!(
+        Cartridge::read_entries(&path).unwrap(),
+        vec![
+            ("pong.ch8".to_string(), vec![0x12, 0x00]),
+            ("tetris.ch8".to_string(), vec![0x00, 0xe0]),
+        ]
+    );
+
+    let mut cartridge = Cartridge::load_entry(&path, "tetris.ch8").unwrap();
+    assert_eq!(cartridge.rom, vec![0x00, 0xe0]);
+    cartridge.reload().unwrap();
+    assert_eq!(cartridge.rom, vec![0x00, 0xe0]);
+    assert!(Cartridge::load_entry(&path, "missing.ch8").is_err());
+}
+
+#[test]
+fn test_entries_of_rom() {
+    let path = create_file("entries", "game.ch8", &[0x12, 0x00]);
+    assert!(Cartridge::read_entries(&path).unwrap().is_empty());
+    assert!(Cartridge::load(&path).unwrap().options.is_none());
+}
+
+#[test]
+fn test_load_invalid_cartridge() {
+    let path = create_file("invalid-cartridge", "game.gif", b"GIF89a");
+    let err = Cartridge::load(&path).err().unwrap();
+    assert!(err.starts_with("failed to load cartridge"));
+}
+
+#[test]
+fn test_is_rom_file() {
+    assert!(is_rom_file("games/PONG.CH8"));
+    assert!(is_rom_file("cartridge.gif"));
+    assert!(!is_rom_file("readme.txt"));
+    assert!(is_archive_file("roms.ZIP"));
+}
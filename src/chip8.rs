@@ -2,18 +2,80 @@
 #[path = "./chip8_test.rs"]
 mod chip8_test;
 
-use crate::memory::Memory;
-use crate::window::{HEIGHT_HI_RES, HEIGHT_LO_RES, WIDTH_HI_RES, WIDTH_LO_RES};
+use crate::memory::{BlendMode, Memory, COLOR_ZONE_WIDTH};
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::trace::TraceEntry;
+use crate::window::{HEIGHT_HI_RES, HEIGHT_MEGA_CHIP, WIDTH_HI_RES, WIDTH_MEGA_CHIP};
 use rand::prelude::*;
+use sdl2::pixels::Color;
+use std::collections::{HashMap, HashSet};
 
-const TIMER_EVERY_X_TICKS: usize = 8;
+/// Rate of the display and of the delay and sound timers, independent of
+/// the instructions per frame.
+pub const FRAME_RATE: u64 = 60;
 const OPCODE_SIZE: u16 = 2;
+/// Sprites below this address are font characters, which MEGA-CHIP draws
+/// in monochrome.
+const FONT_END: u32 = 0x100;
+const SOUND_HEADER_SIZE: usize = 6;
 
 pub struct OutputState<'a> {
     pub memory: &'a mut Memory,
     pub draw_flag: bool,
-    pub _beep: bool,
-    pub hi_res: bool,
+    /// The sound timer was running at some point during the frame.
+    pub beep: bool,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// How 0NNN calls to RCA 1802 machine code routines are handled when no
+/// native routine is registered for the address.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MachineCodePolicy {
+    Ignore,
+    /// Prints a warning the first time each address is called.
+    Warn,
+    /// Stops the interpreter with an error.
+    Halt,
+}
+
+impl MachineCodePolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "ignore" => Ok(MachineCodePolicy::Ignore),
+            "warn" => Ok(MachineCodePolicy::Warn),
+            "halt" => Ok(MachineCodePolicy::Halt),
+            _ => Err(format!("unknown machine code policy '{}'", value)),
+        }
+    }
+}
+
+/// 8-bit sample played by the MEGA-CHIP 060N instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DigitizedSound {
+    pub sample_rate: u16,
+    pub samples: Vec<u8>,
+    pub looping: bool,
+}
+
+/// Native replacement for an 1802 machine code routine, called by 0NNN.
+pub type MachineRoutine = Box<dyn FnMut(&mut Chip8)>;
+
+/// Called after every executed instruction. Returning an error halts the
+/// interpreter.
+pub type TraceHook = Box<dyn FnMut(&TraceEntry) -> Result<(), String>>;
+
+/// Snapshot of the CPU registers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CpuState {
+    pub pc: u16,
+    pub i: u32,
+    pub v: [u8; 16],
+    pub sp: u16,
+    pub stack: [u16; 16],
+    pub dt: u8,
+    pub st: u8,
 }
 
 enum ProgramCounter {
@@ -37,95 +99,406 @@ pub struct Chip8 {
     draw_flag: bool,
     stack: [u16; 16],
     v: [u8; 16],
-    i: u16,
+    i: u32,
     pc: u16,
     sp: u16,
     dt: u8,
     st: u8,
+    beep: bool,
     keypad: [bool; 16],
+    second_keypad: [bool; 16],
+    output_port: u8,
+    input_port: Option<u8>,
+    /// Whether a device feeds the input port. FXFB only waits for input
+    /// with one connected.
+    input_connected: bool,
     keypad_waiting: bool,
     keypad_register: usize,
-    ticks: usize,
+    platform: Platform,
     super_chip: bool,
+    quirks: Quirks,
     hi_res: bool,
+    vblank_waiting: bool,
+    machine_code_policy: MachineCodePolicy,
+    machine_routines: HashMap<u16, MachineRoutine>,
+    machine_code_warnings: HashSet<u16>,
+    /// Warnings not yet taken by `take_warnings`.
+    warnings: Vec<String>,
+    error: Option<String>,
+    sprite_width: usize,
+    sprite_height: usize,
+    blend_mode: BlendMode,
+    collision_color: u8,
+    sound: Option<DigitizedSound>,
+    /// Seed of `rng` if the random numbers have to be reproducible.
+    seed: Option<u64>,
+    rng: StdRng,
+    cycles: u64,
+    trace_hooks: Vec<TraceHook>,
 }
 
 impl Chip8 {
-    pub fn new(super_chip: bool) -> Self {
+    pub fn new(platform: Platform, quirks: Quirks) -> Self {
         Chip8 {
-            memory: Memory::new(super_chip),
+            memory: Memory::new(platform),
             draw_flag: false,
             stack: [0; 16],
             v: [0; 16],
             i: 0,
-            pc: 0x200,
+            pc: platform.start_address(),
             sp: 0,
             dt: 0,
             st: 0,
+            beep: false,
             keypad: [false; 16],
+            second_keypad: [false; 16],
+            output_port: 0,
+            input_port: None,
+            input_connected: false,
             keypad_waiting: false,
             keypad_register: 0,
-            ticks: 0,
-            super_chip,
+            platform,
+            super_chip: platform.is_super_chip(),
+            quirks,
             hi_res: false,
+            vblank_waiting: false,
+            machine_code_policy: MachineCodePolicy::Warn,
+            machine_routines: HashMap::new(),
+            machine_code_warnings: HashSet::new(),
+            warnings: Vec::new(),
+            error: None,
+            sprite_width: 0,
+            sprite_height: 0,
+            blend_mode: BlendMode::Normal,
+            collision_color: 0,
+            sound: None,
+            seed: None,
+            rng: rand::make_rng(),
+            cycles: 0,
+            trace_hooks: Vec::new(),
+        }
+    }
+
+    /// Puts the interpreter back into its power-on state. The machine code
+    /// policy, the registered routines, the trace hooks, the seed and
+    /// whether the input port is connected are kept; the ROM has to be
+    /// loaded again.
+    pub fn reset(&mut self) {
+        let mut chip8 = Chip8::new(self.platform, self.quirks);
+
+        if let Some(seed) = self.seed {
+            chip8.set_seed(seed);
         }
+
+        chip8.machine_code_policy = self.machine_code_policy;
+        chip8.input_connected = self.input_connected;
+        chip8.machine_routines = std::mem::take(&mut self.machine_routines);
+        chip8.trace_hooks = std::mem::take(&mut self.trace_hooks);
+        chip8.memory.set_write_log(!chip8.trace_hooks.is_empty());
+        *self = chip8;
+    }
+
+    /// Switches to another platform and resets the interpreter.
+    pub fn set_platform(&mut self, platform: Platform, quirks: Quirks) {
+        self.platform = platform;
+        self.quirks = quirks;
+        self.reset();
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Makes the random numbers of CXKK the same in every run, e.g. to
+    /// verify a trace.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn set_machine_code_policy(&mut self, policy: MachineCodePolicy) {
+        self.machine_code_policy = policy;
+    }
+
+    /// Registers a native routine that is run instead of the 1802 machine
+    /// code at `address` whenever the program calls it with 0NNN.
+    pub fn register_machine_routine(&mut self, address: u16, routine: MachineRoutine) {
+        self.machine_routines.insert(address, routine);
+    }
+
+    /// Stops the interpreter. Further ticks do nothing.
+    pub fn halt(&mut self, message: String) {
+        self.error = Some(message);
+    }
+
+    /// Returns the warnings since the last call, e.g. about ignored machine
+    /// code calls.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Adds a hook that receives a trace entry for every executed
+    /// instruction. Memory writes are only recorded while a hook is set.
+    pub fn add_trace_hook(&mut self, hook: TraceHook) {
+        self.memory.set_write_log(true);
+        self.trace_hooks.push(hook);
+    }
+
+    /// Removes all trace hooks, which drops whatever they own.
+    pub fn clear_trace_hooks(&mut self) {
+        self.memory.set_write_log(false);
+        self.trace_hooks.clear();
+    }
+
+    /// Number of instructions executed since the start.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            pc: self.pc,
+            i: self.i,
+            v: self.v,
+            sp: self.sp,
+            stack: self.stack,
+            dt: self.dt,
+            st: self.st,
+        }
+    }
+
+    /// Sets the keys of the second CHIP-8X keypad, read by EXF2 and EXF5.
+    pub fn set_second_keypad(&mut self, keypad: [bool; 16]) {
+        self.second_keypad = keypad;
+    }
+
+    /// Last value written to the CHIP-8X output port by FXF8, which sets
+    /// the tone of the sound board.
+    pub fn output_port(&self) -> u8 {
+        self.output_port
+    }
+
+    /// Connects a device to the CHIP-8X input port, so FXFB waits until
+    /// it provides a value with `set_input_port`.
+    pub fn connect_input_port(&mut self) {
+        self.input_connected = true;
+    }
+
+    /// Provides a value for the CHIP-8X input port, read by FXFB.
+    pub fn set_input_port(&mut self, value: u8) {
+        self.input_connected = true;
+        self.input_port = Some(value);
+    }
+
+    pub fn v(&self, x: usize) -> u8 {
+        self.v[x]
+    }
+
+    pub fn set_v(&mut self, x: usize, value: u8) {
+        self.v[x] = value;
+    }
+
+    pub fn i(&self) -> u32 {
+        self.i
+    }
+
+    pub fn set_i(&mut self, value: u32) {
+        self.i = value;
+    }
+
+    pub fn set_pc(&mut self, value: u16) {
+        self.pc = value;
+    }
+
+    /// Whether PC can point to `address`, with the whole opcode there
+    /// inside of memory.
+    pub fn is_valid_pc(&self, address: u32) -> bool {
+        address <= u16::MAX as u32 && (address as usize) + 1 < self.memory.size()
+    }
+
+    /// Whether I can point to `address`.
+    pub fn is_valid_i(&self, address: u32) -> bool {
+        (address as usize) < self.memory.size()
+    }
+
+    pub fn set_sp(&mut self, value: u16) {
+        self.sp = value;
+    }
+
+    pub fn set_dt(&mut self, value: u8) {
+        self.dt = value;
+    }
+
+    pub fn set_st(&mut self, value: u8) {
+        self.st = value;
+    }
+
+    pub fn memory(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Digitized sound started by the MEGA-CHIP 060N instruction, if any is
+    /// playing.
+    pub fn sound(&self) -> Option<&DigitizedSound> {
+        self.sound.as_ref()
     }
 
     pub fn load(&mut self, data: &[u8]) {
+        let start = self.platform.load_address(data);
+        let end = if self.platform == Platform::MegaChip {
+            self.memory.size()
+        } else {
+            4096
+        };
+
         for (i, &byte) in data.iter().enumerate() {
-            let addr = 0x200 + i;
+            let addr = start + i;
 
-            if addr < 4096 {
-                self.memory.write_byte(0x200 + i, byte);
+            if addr < end {
+                self.memory.write_byte(addr, byte);
             } else {
                 break;
             }
         }
     }
 
-    pub fn tick(&mut self, keypad: [bool; 16]) -> OutputState {
+    pub fn tick(&mut self, keypad: [bool; 16]) -> OutputState<'_> {
         self.keypad = keypad;
         self.draw_flag = false;
 
+        if self.error.is_some() {
+            return self.output_state();
+        }
+
         if self.keypad_waiting {
-            for i in 0..keypad.len() {
-                if keypad[i] {
+            for (i, &pressed) in keypad.iter().enumerate() {
+                if pressed {
                     self.keypad_waiting = false;
                     self.v[self.keypad_register] = i as u8;
                     break;
                 }
             }
         } else {
-            if self.ticks == TIMER_EVERY_X_TICKS - 1 {
-                self.ticks = 0;
+            if !self.vblank_waiting {
+                let opcode = self.get_opcode();
+                self.execute(opcode);
+            }
 
-                if self.dt > 0 {
-                    self.dt -= 1
-                }
+            self.beep |= self.st > 0;
+        }
 
-                if self.st > 0 {
-                    self.st -= 1
-                }
-            } else {
-                self.ticks += 1;
-            }
+        self.output_state()
+    }
 
-            let opcode = self.get_opcode();
-            self.run_opcode(opcode);
+    /// Runs the instructions of one display frame. The draw flag is set if
+    /// any of them changed the display.
+    pub fn run_frame(&mut self, keypad: [bool; 16], ticks: u64) -> OutputState<'_> {
+        let mut draw_flag = false;
+        self.start_frame();
+
+        for _ in 0..ticks {
+            draw_flag |= self.tick(keypad).draw_flag;
         }
 
+        self.draw_flag = draw_flag;
+        self.output_state()
+    }
+
+    /// Counts the timers down and ends the wait for the vertical blank.
+    /// Called at the start of every frame by `run_frame`, and by debuggers
+    /// that run ticks themselves.
+    pub fn start_frame(&mut self) {
+        self.dt = self.dt.saturating_sub(1);
+        self.st = self.st.saturating_sub(1);
+        self.beep = false;
+        self.end_vblank_wait();
+    }
+
+    /// Lets a program waiting for the vertical blank continue without
+    /// starting a new frame, e.g. when stepping in a debugger.
+    pub fn end_vblank_wait(&mut self) {
+        self.vblank_waiting = false;
+    }
+
+    /// Returns the display without running any instructions, e.g. while
+    /// paused.
+    pub fn output_state(&mut self) -> OutputState<'_> {
+        let (width, height) = if self.is_mega_chip_mode() {
+            (WIDTH_MEGA_CHIP, HEIGHT_MEGA_CHIP)
+        } else if self.quirks.double_lo_res {
+            (WIDTH_HI_RES, HEIGHT_HI_RES)
+        } else {
+            self.get_display_size()
+        };
+
         OutputState {
             memory: &mut self.memory,
             draw_flag: self.draw_flag,
-            _beep: self.st > 0,
-            hi_res: self.hi_res,
+            beep: self.beep || self.st > 0,
+            width,
+            height,
+        }
+    }
+
+    /// Size of the display that sprites are drawn on in the current mode.
+    fn get_display_size(&self) -> (usize, usize) {
+        if self.is_mega_chip_mode() {
+            (WIDTH_MEGA_CHIP, HEIGHT_MEGA_CHIP)
+        } else if self.hi_res {
+            (WIDTH_HI_RES, HEIGHT_HI_RES)
+        } else {
+            self.platform.lo_res_size()
         }
     }
 
+    fn is_mega_chip_mode(&self) -> bool {
+        self.memory
+            .mega_chip()
+            .is_some_and(|screen| screen.enabled())
+    }
+
     fn get_opcode(&mut self) -> u16 {
         self.memory.read_word(self.pc as usize)
     }
 
+    fn execute(&mut self, opcode: u16) {
+        if self.trace_hooks.is_empty() {
+            self.run_opcode(opcode);
+            self.cycles += 1;
+            return;
+        }
+
+        let before = self.state();
+        self.run_opcode(opcode);
+
+        let entry = TraceEntry {
+            cycle: self.cycles,
+            opcode,
+            before,
+            after: self.state(),
+            writes: self.memory.take_writes(),
+        };
+
+        self.cycles += 1;
+
+        let result = self
+            .trace_hooks
+            .iter_mut()
+            .try_for_each(|hook| hook(&entry));
+
+        if let Err(error) = result {
+            self.halt(error);
+        }
+    }
+
     fn run_opcode(&mut self, opcode: u16) {
         let nibbles = (
             (opcode & 0xF000) >> 12u8,
@@ -157,11 +530,35 @@ impl Chip8 {
                     ProgramCounter::Next
                 }
             }
+            (0x00, 0x00, 0x0c, _) if self.super_chip => self.op_00cn(n),
+            (0x00, 0x00, 0x0f, 0x0b) if self.super_chip => self.op_00fb(),
+            (0x00, 0x00, 0x0f, 0x0c) if self.super_chip => self.op_00fc(),
+            (0x00, 0x00, 0x0f, 0x0d) if self.super_chip => self.op_00fd(),
+            // Empty memory rather than a machine code call.
+            (0x00, 0x00, 0x00, 0x00) if self.super_chip => ProgramCounter::Next,
+            (0x00, 0x02, 0x0a, 0x00) if self.platform == Platform::Chip8X => self.op_02a0(),
+            (0x00, _, _, _) if self.platform == Platform::MegaChip => match nibbles {
+                (_, 0x00, 0x01, 0x00) => self.op_0010(),
+                (_, 0x00, 0x01, 0x01) => self.op_0011(),
+                (_, 0x00, 0x0b, _) => self.op_00bn(n),
+                (_, 0x01, _, _) => self.op_01nn(kk),
+                (_, 0x02, _, _) => self.op_02nn(kk),
+                (_, 0x03, _, _) => self.op_03nn(kk),
+                (_, 0x04, _, _) => self.op_04nn(kk),
+                (_, 0x05, _, _) => self.op_05nn(kk),
+                (_, 0x06, 0x00, _) => self.op_060n(n),
+                (_, 0x07, 0x00, 0x00) => self.op_0700(),
+                (_, 0x08, 0x00, _) => self.op_080n(n),
+                (_, 0x09, _, _) => self.op_09nn(kk),
+                _ => self.op_0nnn(nnn),
+            },
+            (0x00, _, _, _) => self.op_0nnn(nnn),
             (0x01, _, _, _) => self.op_1nnn(nnn),
             (0x02, _, _, _) => self.op_2nnn(nnn),
             (0x03, _, _, _) => self.op_3xkk(x, kk),
             (0x04, _, _, _) => self.op_4xkk(x, kk),
             (0x05, _, _, 0x00) => self.op_5xy0(x, y),
+            (0x05, _, _, 0x01) if self.platform == Platform::Chip8X => self.op_5xy1(x, y),
             (0x06, _, _, _) => self.op_6xkk(x, kk),
             (0x07, _, _, _) => self.op_7xkk(x, kk),
             (0x08, _, _, 0x00) => self.op_8xy0(x, y),
@@ -175,6 +572,8 @@ impl Chip8 {
             (0x08, _, _, 0x0e) => self.op_8xye(x, y),
             (0x09, _, _, 0x00) => self.op_9xy0(x, y),
             (0x0a, _, _, _) => self.op_annn(nnn),
+            (0x0b, _, _, 0x00) if self.platform == Platform::Chip8X => self.op_bxy0(x, y),
+            (0x0b, _, _, _) if self.platform == Platform::Chip8X => self.op_bxyn(x, y, n),
             (0x0b, _, _, _) => {
                 if self.super_chip {
                     self.op_bxnn(x, nnn)
@@ -185,8 +584,12 @@ impl Chip8 {
             (0x0c, _, _, _) => self.op_cxkk(x, kk),
             (0x0d, _, _, _) => self.op_dxyn(x, y, n),
             (0x0e, _, 0x09, 0x0e) => self.op_ex9e(x),
+            (0x0e, _, 0x0f, 0x02) if self.platform == Platform::Chip8X => self.op_exf2(x),
+            (0x0e, _, 0x0f, 0x05) if self.platform == Platform::Chip8X => self.op_exf5(x),
             (0x0e, _, 0x0a, 0x01) => self.op_exa1(x),
             (0x0f, _, 0x00, 0x07) => self.op_fx07(x),
+            (0x0f, _, 0x0f, 0x08) if self.platform == Platform::Chip8X => self.op_fxf8(x),
+            (0x0f, _, 0x0f, 0x0b) if self.platform == Platform::Chip8X => self.op_fxfb(x),
             (0x0f, _, 0x00, 0x0a) => self.op_fx0a(x),
             (0x0f, _, 0x01, 0x05) => self.op_fx15(x),
             (0x0f, _, 0x01, 0x08) => self.op_fx18(x),
@@ -213,21 +616,210 @@ impl Chip8 {
         (value & 0b10000000) >> 7
     }
 
-    fn op_00e0(&mut self) -> ProgramCounter {
-        if self.super_chip && self.hi_res {
-            for y in 0..HEIGHT_HI_RES {
-                for x in 0..WIDTH_HI_RES {
-                    self.memory.write_vram(x, y, 0);
+    fn op_0nnn(&mut self, nnn: u16) -> ProgramCounter {
+        if let Some(mut routine) = self.machine_routines.remove(&nnn) {
+            routine(self);
+            self.machine_routines.insert(nnn, routine);
+            return ProgramCounter::Next;
+        }
+
+        // The clear routine of the platform is emulated natively.
+        if self.platform.clear_routine() == Some(nnn) {
+            self.memory.clear_vram();
+            return ProgramCounter::Next;
+        }
+
+        match self.machine_code_policy {
+            MachineCodePolicy::Ignore => ProgramCounter::Next,
+            MachineCodePolicy::Warn => {
+                if self.machine_code_warnings.insert(nnn) {
+                    self.warnings.push(format!(
+                        "ignoring machine code call to 0x{:03X} at 0x{:03X}",
+                        nnn, self.pc
+                    ));
                 }
+
+                ProgramCounter::Next
+            }
+            MachineCodePolicy::Halt => {
+                self.halt(format!(
+                    "machine code call to 0x{:03X} at 0x{:03X}",
+                    nnn, self.pc
+                ));
+                ProgramCounter::Jump(self.pc)
             }
+        }
+    }
+
+    fn op_02a0(&mut self) -> ProgramCounter {
+        if let Some(colors) = self.memory.colors_mut() {
+            colors.set_background((colors.background() + 1) % 4);
+        }
+
+        self.draw_flag = true;
+        ProgramCounter::Next
+    }
+
+    fn op_0010(&mut self) -> ProgramCounter {
+        self.set_mega_chip_mode(false);
+        ProgramCounter::Next
+    }
+
+    fn op_0011(&mut self) -> ProgramCounter {
+        self.set_mega_chip_mode(true);
+        ProgramCounter::Next
+    }
+
+    fn set_mega_chip_mode(&mut self, enabled: bool) {
+        if let Some(screen) = self.memory.mega_chip_mut() {
+            screen.set_enabled(enabled);
+        }
+
+        self.memory.clear_vram();
+        self.draw_flag = true;
+    }
+
+    fn op_00bn(&mut self, n: u8) -> ProgramCounter {
+        self.scroll(0, -(n as isize))
+    }
+
+    fn op_00cn(&mut self, n: u8) -> ProgramCounter {
+        self.scroll(0, n as isize)
+    }
+
+    fn op_00fb(&mut self) -> ProgramCounter {
+        self.scroll(4, 0)
+    }
+
+    fn op_00fc(&mut self) -> ProgramCounter {
+        self.scroll(-4, 0)
+    }
+
+    /// Exits the interpreter on the HP-48. The program stays at the
+    /// instruction, keeping the display as it is.
+    fn op_00fd(&mut self) -> ProgramCounter {
+        ProgramCounter::Jump(self.pc)
+    }
+
+    /// Scrolls the visible display, which is the whole hi-res one when
+    /// lo-res pixels are doubled, by hi-res pixels as on the HP-48.
+    fn scroll(&mut self, dx: isize, dy: isize) -> ProgramCounter {
+        let (width, height) = if self.is_lo_res_doubled() {
+            (WIDTH_HI_RES, HEIGHT_HI_RES)
         } else {
-            for y in 0..HEIGHT_LO_RES {
-                for x in 0..WIDTH_LO_RES {
-                    self.memory.write_vram(x, y, 0);
-                }
+            self.get_display_size()
+        };
+        self.memory.scroll_vram(dx, dy, width, height);
+        self.draw_flag = true;
+        ProgramCounter::Next
+    }
+
+    /// Loads a 24-bit address into I. The low 16 bits are stored in the
+    /// word following the instruction.
+    fn op_01nn(&mut self, kk: u8) -> ProgramCounter {
+        let low = self
+            .memory
+            .read_word(self.pc as usize + OPCODE_SIZE as usize);
+        self.i = (kk as u32) << 16 | low as u32;
+        ProgramCounter::Skip
+    }
+
+    /// Loads NN ARGB colors from I into the palette, starting at index 1.
+    fn op_02nn(&mut self, kk: u8) -> ProgramCounter {
+        let address = self.i as usize;
+
+        if address + kk as usize * 4 > self.memory.size() {
+            self.halt(format!(
+                "palette at 0x{:06X} ends outside of memory at 0x{:03X}",
+                address, self.pc
+            ));
+            return ProgramCounter::Jump(self.pc);
+        }
+
+        let colors: Vec<(u8, u8, u8, u8)> = (0..kk as usize)
+            .map(|i| {
+                let color = address + i * 4;
+                (
+                    self.memory.read_byte(color),
+                    self.memory.read_byte(color + 1),
+                    self.memory.read_byte(color + 2),
+                    self.memory.read_byte(color + 3),
+                )
+            })
+            .collect();
+
+        if let Some(screen) = self.memory.mega_chip_mut() {
+            for (i, (a, r, g, b)) in colors.into_iter().enumerate() {
+                screen.set_palette_color(i as u8 + 1, Color::RGBA(r, g, b, a));
             }
         }
 
+        ProgramCounter::Next
+    }
+
+    fn op_03nn(&mut self, kk: u8) -> ProgramCounter {
+        self.sprite_width = if kk == 0 { 256 } else { kk as usize };
+        ProgramCounter::Next
+    }
+
+    fn op_04nn(&mut self, kk: u8) -> ProgramCounter {
+        self.sprite_height = if kk == 0 { 256 } else { kk as usize };
+        ProgramCounter::Next
+    }
+
+    fn op_05nn(&mut self, kk: u8) -> ProgramCounter {
+        if let Some(screen) = self.memory.mega_chip_mut() {
+            screen.set_alpha(kk);
+        }
+
+        ProgramCounter::Next
+    }
+
+    /// Plays the sound at I, which starts with a header of the 16-bit sample
+    /// rate and the 24-bit sample count. N = 0 loops the sound.
+    fn op_060n(&mut self, n: u8) -> ProgramCounter {
+        let address = self.i as usize;
+
+        if address + SOUND_HEADER_SIZE > self.memory.size() {
+            self.halt(format!(
+                "sound header at 0x{:06X} ends outside of memory at 0x{:03X}",
+                address, self.pc
+            ));
+            return ProgramCounter::Jump(self.pc);
+        }
+
+        let sample_rate = self.memory.read_word(address);
+        let length = (self.memory.read_byte(address + 2) as usize) << 16
+            | (self.memory.read_word(address + 3) as usize);
+        let start = address + SOUND_HEADER_SIZE;
+        let end = (start + length).min(self.memory.size());
+
+        self.sound = Some(DigitizedSound {
+            sample_rate,
+            samples: (start..end).map(|i| self.memory.read_byte(i)).collect(),
+            looping: n == 0,
+        });
+
+        ProgramCounter::Next
+    }
+
+    fn op_0700(&mut self) -> ProgramCounter {
+        self.sound = None;
+        ProgramCounter::Next
+    }
+
+    fn op_080n(&mut self, n: u8) -> ProgramCounter {
+        self.blend_mode = BlendMode::from_index(n);
+        ProgramCounter::Next
+    }
+
+    fn op_09nn(&mut self, kk: u8) -> ProgramCounter {
+        self.collision_color = kk;
+        ProgramCounter::Next
+    }
+
+    fn op_00e0(&mut self) -> ProgramCounter {
+        self.memory.clear_vram();
         self.draw_flag = false;
         ProgramCounter::Next
     }
@@ -238,15 +830,53 @@ impl Chip8 {
     }
 
     fn op_00fe(&mut self) -> ProgramCounter {
-        self.hi_res = false;
+        self.set_resolution(false);
         ProgramCounter::Next
     }
 
     fn op_00ff(&mut self) -> ProgramCounter {
-        self.hi_res = true;
+        self.set_resolution(true);
         ProgramCounter::Next
     }
 
+    fn set_resolution(&mut self, hi_res: bool) {
+        self.hi_res = hi_res;
+
+        if self.quirks.resolution_clear {
+            self.memory.clear_vram();
+            self.draw_flag = true;
+        }
+    }
+
+    /// Whether lo-res pixels are drawn as 2x2 blocks on the hi-res display.
+    fn is_lo_res_doubled(&self) -> bool {
+        !self.hi_res && self.quirks.double_lo_res && !self.is_mega_chip_mode()
+    }
+
+    /// Toggles a sprite pixel and returns whether it was already set.
+    fn plot(&mut self, x: usize, y: usize) -> bool {
+        if !self.is_lo_res_doubled() {
+            let collision = self.memory.read_vram(x, y) != 0;
+            self.memory.xor_vram(x, y, 1);
+
+            if let Some(screen) = self.memory.mega_chip_mut() {
+                let index = if collision { 0 } else { 0xff };
+                screen.blend_pixel(x, y, index, BlendMode::Normal);
+            }
+
+            return collision;
+        }
+
+        let mut collision = false;
+
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            collision |= self.memory.read_vram(2 * x + dx, 2 * y + dy) != 0;
+            self.memory.xor_vram(2 * x + dx, 2 * y + dy, 1);
+        }
+
+        collision
+    }
+
     fn op_1nnn(&mut self, nnn: u16) -> ProgramCounter {
         ProgramCounter::Jump(nnn)
     }
@@ -269,6 +899,12 @@ impl Chip8 {
         ProgramCounter::skip_if(self.v[x] == self.v[y])
     }
 
+    fn op_5xy1(&mut self, x: usize, y: usize) -> ProgramCounter {
+        // Both nibbles are added separately and wrap around at 8.
+        self.v[x] = ((self.v[x] & 0x77) + (self.v[y] & 0x77)) & 0x77;
+        ProgramCounter::Next
+    }
+
     fn op_6xkk(&mut self, x: usize, kk: u8) -> ProgramCounter {
         self.v[x] = kk;
         ProgramCounter::Next
@@ -286,17 +922,17 @@ impl Chip8 {
     }
 
     fn op_8xy1(&mut self, x: usize, y: usize) -> ProgramCounter {
-        self.v[x] = self.v[x] | self.v[y];
+        self.v[x] |= self.v[y];
         ProgramCounter::Next
     }
 
     fn op_8xy2(&mut self, x: usize, y: usize) -> ProgramCounter {
-        self.v[x] = self.v[x] & self.v[y];
+        self.v[x] &= self.v[y];
         ProgramCounter::Next
     }
 
     fn op_8xy3(&mut self, x: usize, y: usize) -> ProgramCounter {
-        self.v[x] = self.v[x] ^ self.v[y];
+        self.v[x] ^= self.v[y];
         ProgramCounter::Next
     }
 
@@ -316,7 +952,7 @@ impl Chip8 {
     fn op_8xy6(&mut self, x: usize, y: usize) -> ProgramCounter {
         if self.super_chip {
             self.v[0xf] = self.get_lsb(self.v[x]);
-            self.v[x] = self.v[x] >> 1;
+            self.v[x] >>= 1;
         } else {
             self.v[0xf] = self.get_lsb(self.v[y]);
             self.v[x] = self.v[y] >> 1;
@@ -334,7 +970,7 @@ impl Chip8 {
     fn op_8xye(&mut self, x: usize, y: usize) -> ProgramCounter {
         if self.super_chip {
             self.v[0xf] = self.get_msb(self.v[x]);
-            self.v[x] = self.v[x] << 1;
+            self.v[x] <<= 1;
         } else {
             self.v[0xf] = self.get_msb(self.v[y]);
             self.v[x] = self.v[y] << 1;
@@ -348,7 +984,7 @@ impl Chip8 {
     }
 
     fn op_annn(&mut self, nnn: u16) -> ProgramCounter {
-        self.i = nnn;
+        self.i = nnn as u32;
         ProgramCounter::Next
     }
 
@@ -360,39 +996,136 @@ impl Chip8 {
         ProgramCounter::Jump(self.v[x] as u16 + nnn)
     }
 
+    /// Colors a rectangle of 8x4 pixel zones. The low nibbles of VX and VX+1
+    /// are the left column and top row, the high nibbles the number of
+    /// additional columns and rows.
+    fn op_bxy0(&mut self, x: usize, y: usize) -> ProgramCounter {
+        let horizontal = self.v[x];
+        let vertical = self.v[(x + 1) % 16];
+        let color = self.v[y] & 0x07;
+
+        if let Some(colors) = self.memory.colors_mut() {
+            let left = (horizontal & 0x0f) as usize;
+            let top = (vertical & 0x0f) as usize;
+
+            for zone_row in top..=top + (vertical >> 4) as usize {
+                for column in left..=left + (horizontal >> 4) as usize {
+                    for row in zone_row * 4..zone_row * 4 + 4 {
+                        colors.set_foreground(column, row, color);
+                    }
+                }
+            }
+        }
+
+        self.draw_flag = true;
+        ProgramCounter::Next
+    }
+
+    /// Colors N pixel rows of the 8 pixel wide column containing VX,
+    /// starting at row VX+1.
+    fn op_bxyn(&mut self, x: usize, y: usize, n: u8) -> ProgramCounter {
+        let column = self.v[x] as usize / COLOR_ZONE_WIDTH;
+        let top = self.v[(x + 1) % 16] as usize;
+        let color = self.v[y] & 0x07;
+
+        if let Some(colors) = self.memory.colors_mut() {
+            for row in top..top + n as usize {
+                colors.set_foreground(column, row, color);
+            }
+        }
+
+        self.draw_flag = true;
+        ProgramCounter::Next
+    }
+
     fn op_cxkk(&mut self, x: usize, kk: u8) -> ProgramCounter {
-        let mut rng = rand::rng();
-        self.v[x] = rng.random::<u8>() & kk;
+        self.v[x] = self.rng.random::<u8>() & kk;
         ProgramCounter::Next
     }
 
     fn op_dxyn(&mut self, x: usize, y: usize, n: u8) -> ProgramCounter {
         self.v[0x0f] = 0;
 
-        let width = if self.hi_res {
-            WIDTH_HI_RES
-        } else {
-            WIDTH_LO_RES
-        };
+        if self.is_mega_chip_mode() && self.i >= FONT_END {
+            return self.draw_mega_chip_sprite(x, y);
+        }
 
-        let height = if self.hi_res {
-            HEIGHT_HI_RES
-        } else {
-            HEIGHT_LO_RES
-        };
+        let (width, height) = self.get_display_size();
+
+        // Only the starting coordinate always wraps; the rest of the sprite
+        // is clipped at the screen edges unless the wrap quirk is enabled.
+        let origin_x = self.v[x] as usize % width;
+        let origin_y = self.v[y] as usize % height;
 
         for byte in 0..(n as usize) {
-            let y = (self.v[y] as usize + byte) % height;
+            let y = origin_y + byte;
+
+            if y >= height && !self.quirks.wrap {
+                break;
+            }
+
+            let y = y % height;
 
             for bit in 0..8 {
-                let x = (self.v[x] as usize + bit) % width;
+                let x = origin_x + bit;
+
+                if x >= width && !self.quirks.wrap {
+                    break;
+                }
+
+                let x = x % width;
                 let color = (self.memory.read_byte(self.i as usize + byte) >> (7 - bit)) & 1;
-                self.v[0x0f] |= color & self.memory.read_vram(x, y);
-                self.memory.xor_vram(x, y, color);
+
+                if color == 1 && self.plot(x, y) {
+                    self.v[0x0f] = 1;
+                }
+            }
+        }
+
+        self.draw_flag = true;
+        self.vblank_waiting = self.quirks.display_wait;
+        ProgramCounter::Next
+    }
+
+    /// Draws a sprite of palette indices with the size set by 03NN and
+    /// 04NN. Index 0 is transparent; drawing over a lit pixel of the
+    /// collision color sets VF.
+    fn draw_mega_chip_sprite(&mut self, x: usize, y: usize) -> ProgramCounter {
+        let origin_x = self.v[x] as usize;
+        let origin_y = self.v[y] as usize;
+
+        for row in 0..self.sprite_height {
+            let y = origin_y + row;
+
+            if y >= HEIGHT_MEGA_CHIP {
+                break;
+            }
+
+            for column in 0..self.sprite_width.min(WIDTH_MEGA_CHIP - origin_x) {
+                let x = origin_x + column;
+                let address = self.i as usize + row * self.sprite_width + column;
+                let index = self.memory.read_byte(address % self.memory.size());
+
+                if index == 0 {
+                    continue;
+                }
+
+                let existing = self.memory.read_vram(x, y);
+
+                if existing != 0 && existing == self.collision_color {
+                    self.v[0x0f] = 1;
+                }
+
+                self.memory.write_vram(x, y, index);
+
+                if let Some(screen) = self.memory.mega_chip_mut() {
+                    screen.blend_pixel(x, y, index, self.blend_mode);
+                }
             }
         }
 
         self.draw_flag = true;
+        self.vblank_waiting = self.quirks.display_wait;
         ProgramCounter::Next
     }
 
@@ -404,6 +1137,14 @@ impl Chip8 {
         ProgramCounter::skip_if(!self.keypad[self.v[x] as usize])
     }
 
+    fn op_exf2(&mut self, x: usize) -> ProgramCounter {
+        ProgramCounter::skip_if(self.second_keypad[self.v[x] as usize & 0x0f])
+    }
+
+    fn op_exf5(&mut self, x: usize) -> ProgramCounter {
+        ProgramCounter::skip_if(!self.second_keypad[self.v[x] as usize & 0x0f])
+    }
+
     fn op_fx07(&mut self, x: usize) -> ProgramCounter {
         self.v[x] = self.dt;
         ProgramCounter::Next
@@ -415,6 +1156,27 @@ impl Chip8 {
         ProgramCounter::Next
     }
 
+    fn op_fxf8(&mut self, x: usize) -> ProgramCounter {
+        self.output_port = self.v[x];
+        ProgramCounter::Next
+    }
+
+    /// Reads the input port, waiting for a value if a device is connected.
+    /// Without one, the port reads 0.
+    fn op_fxfb(&mut self, x: usize) -> ProgramCounter {
+        match self.input_port.take() {
+            Some(value) => {
+                self.v[x] = value;
+                ProgramCounter::Next
+            }
+            None if !self.input_connected => {
+                self.v[x] = 0;
+                ProgramCounter::Next
+            }
+            None => ProgramCounter::Jump(self.pc),
+        }
+    }
+
     fn op_fx15(&mut self, x: usize) -> ProgramCounter {
         self.dt = self.v[x];
         ProgramCounter::Next
@@ -426,12 +1188,12 @@ impl Chip8 {
     }
 
     fn op_fx1e(&mut self, x: usize) -> ProgramCounter {
-        self.i = self.v[x] as u16 + self.i;
+        self.i += self.v[x] as u32;
         ProgramCounter::Next
     }
 
     fn op_fx29(&mut self, x: usize) -> ProgramCounter {
-        self.i = (self.v[x] as u16) * 5;
+        self.i = (self.v[x] as u32) * 5;
         ProgramCounter::Next
     }
 
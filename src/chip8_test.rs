@@ -1,11 +1,20 @@
 use super::*;
+use crate::window::{HEIGHT_LO_RES, HEIGHT_MEGA_CHIP, WIDTH_LO_RES, WIDTH_MEGA_CHIP};
+use std::cell::RefCell;
+use std::rc::Rc;
 
 const START_PC: u16 = 0xF00;
 const NEXT_PC: u16 = START_PC + OPCODE_SIZE;
 const SKIPPED_PC: u16 = START_PC + (2 * OPCODE_SIZE);
 
 fn create_cpu(super_chip: bool) -> Chip8 {
-    let mut cpu = Chip8::new(super_chip);
+    let platform = if super_chip {
+        Platform::SuperChip
+    } else {
+        Platform::Chip8
+    };
+
+    let mut cpu = Chip8::new(platform, Quirks::new(platform));
     cpu.pc = START_PC;
     cpu.v = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7];
     cpu
@@ -43,6 +52,60 @@ fn test_op_00e0() {
     assert_eq!(cpu.pc, NEXT_PC);
 }
 
+#[test]
+fn test_op_00e0_hi_res() {
+    let mut cpu = create_cpu(true);
+    cpu.hi_res = true;
+    cpu.memory
+        .write_vram(WIDTH_HI_RES - 1, HEIGHT_HI_RES - 1, 1);
+    cpu.run_opcode(0x00e0);
+    assert_eq!(cpu.memory.read_vram(WIDTH_HI_RES - 1, HEIGHT_HI_RES - 1), 0);
+}
+
+#[test]
+fn test_op_00e0_clears_hi_res_area_in_lo_res() {
+    let mut cpu = create_cpu(true);
+    cpu.memory
+        .write_vram(WIDTH_HI_RES - 1, HEIGHT_HI_RES - 1, 1);
+    cpu.run_opcode(0x00e0);
+    assert_eq!(cpu.memory.read_vram(WIDTH_HI_RES - 1, HEIGHT_HI_RES - 1), 0);
+}
+
+#[test]
+fn test_op_00fe() {
+    let mut cpu = create_cpu(true);
+    cpu.hi_res = true;
+    cpu.memory.write_vram(0, 0, 1);
+    cpu.run_opcode(0x00fe);
+    assert!(!cpu.hi_res);
+    assert_eq!(cpu.memory.read_vram(0, 0), 1);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_op_00ff() {
+    let mut cpu = create_cpu(true);
+    cpu.memory.write_vram(0, 0, 1);
+    cpu.run_opcode(0x00ff);
+    assert!(cpu.hi_res);
+    assert_eq!(cpu.memory.read_vram(0, 0), 1);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_op_00ff_resolution_clear() {
+    let mut cpu = create_cpu(true);
+    cpu.quirks.resolution_clear = true;
+    cpu.memory.write_vram(0, 0, 1);
+    cpu.run_opcode(0x00ff);
+    assert!(cpu.hi_res);
+    assert_eq!(cpu.memory.read_vram(0, 0), 0);
+    cpu.memory.write_vram(0, 0, 1);
+    cpu.run_opcode(0x00fe);
+    assert!(!cpu.hi_res);
+    assert_eq!(cpu.memory.read_vram(0, 0), 0);
+}
+
 #[test]
 fn test_op_00ee() {
     let mut cpu = create_cpu(false);
@@ -53,6 +116,107 @@ fn test_op_00ee() {
     assert_eq!(cpu.pc, 0x6666);
 }
 
+#[test]
+fn test_op_0nnn_ignore() {
+    let mut cpu = create_cpu(false);
+    cpu.set_machine_code_policy(MachineCodePolicy::Ignore);
+    cpu.run_opcode(0x0123);
+    assert_eq!(cpu.pc, NEXT_PC);
+    assert!(cpu.error().is_none());
+}
+
+#[test]
+fn test_op_0nnn_warn() {
+    let mut cpu = create_cpu(false);
+    cpu.run_opcode(0x0123);
+    cpu.run_opcode(0x0123);
+    assert!(cpu.machine_code_warnings.contains(&0x0123));
+    assert_eq!(
+        cpu.take_warnings(),
+        vec![format!(
+            "ignoring machine code call to 0x123 at 0x{:03X}",
+            START_PC
+        )]
+    );
+    assert!(cpu.take_warnings().is_empty());
+    assert_eq!(cpu.pc, NEXT_PC + OPCODE_SIZE);
+    assert!(cpu.error().is_none());
+}
+
+#[test]
+fn test_op_0nnn_halt() {
+    let mut cpu = create_cpu(false);
+    cpu.set_machine_code_policy(MachineCodePolicy::Halt);
+    cpu.run_opcode(0x0123);
+    assert_eq!(cpu.pc, START_PC);
+    assert!(cpu.error().is_some());
+    cpu.v[0] = 0;
+    load_program(&mut cpu, &[0x6005]);
+    cpu.tick([false; 16]);
+    assert_eq!(cpu.v[0], 0);
+    assert_eq!(cpu.pc, START_PC);
+}
+
+#[test]
+fn test_op_0nnn_machine_routine() {
+    let mut cpu = create_cpu(false);
+    cpu.set_machine_code_policy(MachineCodePolicy::Halt);
+    cpu.register_machine_routine(
+        0x0123,
+        Box::new(|cpu: &mut Chip8| cpu.set_v(0, cpu.v(0) + 1)),
+    );
+    cpu.v[0] = 5;
+    cpu.run_opcode(0x0123);
+    cpu.run_opcode(0x0123);
+    assert_eq!(cpu.v[0], 7);
+    assert_eq!(cpu.pc, NEXT_PC + OPCODE_SIZE);
+    assert!(cpu.error().is_none());
+}
+
+#[test]
+fn test_super_chip_system_opcodes() {
+    let mut cpu = create_cpu(true);
+    cpu.set_machine_code_policy(MachineCodePolicy::Halt);
+    cpu.memory.write_vram(0, 0, 1);
+
+    for opcode in [0x0000, 0x00c1, 0x00fb, 0x00fc] {
+        cpu.run_opcode(opcode);
+    }
+
+    assert_eq!(cpu.error(), None);
+    assert_eq!(cpu.memory.read_vram(0, 1), 1);
+    assert_eq!(cpu.pc, START_PC + 4 * OPCODE_SIZE);
+
+    // 00FD stays at the instruction.
+    cpu.run_opcode(0x00fd);
+    assert_eq!(cpu.pc, START_PC + 4 * OPCODE_SIZE);
+    assert_eq!(cpu.error(), None);
+
+    let mut cpu = create_cpu(false);
+    cpu.set_machine_code_policy(MachineCodePolicy::Halt);
+    cpu.run_opcode(0x00fb);
+    assert!(cpu.error().is_some());
+}
+
+#[test]
+fn test_scroll_doubled_lo_res() {
+    let mut cpu = create_cpu(true);
+    assert!(cpu.quirks.double_lo_res);
+    cpu.memory.write_vram(100, 50, 1);
+    cpu.memory.write_vram(WIDTH_HI_RES - 1, 0, 1);
+
+    cpu.run_opcode(0x00c2);
+    assert_eq!(cpu.memory.read_vram(100, 52), 1);
+    assert_eq!(cpu.memory.read_vram(100, 50), 0);
+
+    cpu.run_opcode(0x00fc);
+    assert_eq!(cpu.memory.read_vram(96, 52), 1);
+    assert_eq!(cpu.memory.read_vram(WIDTH_HI_RES - 5, 2), 1);
+
+    cpu.run_opcode(0x00fb);
+    assert_eq!(cpu.memory.read_vram(100, 52), 1);
+}
+
 #[test]
 fn test_op_1nnn() {
     let mut cpu = create_cpu(false);
@@ -300,6 +464,7 @@ fn test_op_dxyn() {
 #[test]
 fn test_op_dxyn_wrap_horizontal() {
     let mut cpu = create_cpu(false);
+    cpu.quirks.wrap = true;
     let x = WIDTH_LO_RES - 4;
     cpu.i = 0;
     cpu.memory.write_byte(0, 0b11111111);
@@ -322,6 +487,7 @@ fn test_op_dxyn_wrap_horizontal() {
 #[test]
 fn test_op_dxyn_wrap_vertical() {
     let mut cpu = create_cpu(false);
+    cpu.quirks.wrap = true;
     let y = HEIGHT_LO_RES - 1;
     cpu.i = 0;
     cpu.memory.write_byte(0, 0b11111111);
@@ -334,6 +500,173 @@ fn test_op_dxyn_wrap_vertical() {
     assert_eq!(cpu.v[0x0f], 0);
 }
 
+fn create_sprite_cpu(hi_res: bool, x: usize, y: usize) -> Chip8 {
+    let mut cpu = create_cpu(hi_res);
+    cpu.hi_res = hi_res;
+    cpu.i = 0x300;
+    cpu.memory.write_byte(0x300, 0b11111111);
+    cpu.memory.write_byte(0x301, 0b11111111);
+    cpu.memory.write_byte(0x302, 0b11111111);
+    cpu.memory.write_byte(0x303, 0b11111111);
+    cpu.v[0] = x as u8;
+    cpu.v[1] = y as u8;
+    cpu.run_opcode(0xd014);
+    cpu
+}
+
+fn count_pixels(cpu: &mut Chip8) -> usize {
+    let mut count = 0;
+
+    for y in 0..HEIGHT_HI_RES {
+        for x in 0..WIDTH_HI_RES {
+            count += cpu.memory.read_vram(x, y) as usize;
+        }
+    }
+
+    count
+}
+
+fn test_clip_right(hi_res: bool, width: usize) {
+    let mut cpu = create_sprite_cpu(hi_res, width - 3, 0);
+
+    for y in 0..4 {
+        assert_eq!(cpu.memory.read_vram(width - 4, y), 0);
+        assert_eq!(cpu.memory.read_vram(width - 3, y), 1);
+        assert_eq!(cpu.memory.read_vram(width - 1, y), 1);
+        assert_eq!(cpu.memory.read_vram(0, y), 0);
+    }
+
+    assert_eq!(count_pixels(&mut cpu), 3 * 4);
+}
+
+fn test_clip_bottom(hi_res: bool, height: usize) {
+    let mut cpu = create_sprite_cpu(hi_res, 0, height - 1);
+
+    for x in 0..8 {
+        assert_eq!(cpu.memory.read_vram(x, height - 1), 1);
+        assert_eq!(cpu.memory.read_vram(x, 0), 0);
+    }
+
+    assert_eq!(count_pixels(&mut cpu), 8);
+}
+
+fn test_clip_corner(hi_res: bool, width: usize, height: usize) {
+    let mut cpu = create_sprite_cpu(hi_res, width - 2, height - 2);
+    assert_eq!(cpu.memory.read_vram(width - 1, height - 1), 1);
+    assert_eq!(cpu.memory.read_vram(0, 0), 0);
+    assert_eq!(count_pixels(&mut cpu), 2 * 2);
+}
+
+fn test_wrap_origin(hi_res: bool, width: usize, height: usize) {
+    let mut cpu = create_sprite_cpu(hi_res, width + 2, height + 1);
+
+    for x in 2..10 {
+        assert_eq!(cpu.memory.read_vram(x, 1), 1);
+        assert_eq!(cpu.memory.read_vram(x, 4), 1);
+    }
+
+    assert_eq!(count_pixels(&mut cpu), 8 * 4);
+}
+
+#[test]
+fn test_op_dxyn_clip_right() {
+    test_clip_right(false, WIDTH_LO_RES);
+}
+
+#[test]
+fn test_op_dxyn_clip_right_hi_res() {
+    test_clip_right(true, WIDTH_HI_RES);
+}
+
+#[test]
+fn test_op_dxyn_clip_bottom() {
+    test_clip_bottom(false, HEIGHT_LO_RES);
+}
+
+#[test]
+fn test_op_dxyn_clip_bottom_hi_res() {
+    test_clip_bottom(true, HEIGHT_HI_RES);
+}
+
+#[test]
+fn test_op_dxyn_clip_corner() {
+    test_clip_corner(false, WIDTH_LO_RES, HEIGHT_LO_RES);
+}
+
+#[test]
+fn test_op_dxyn_clip_corner_hi_res() {
+    test_clip_corner(true, WIDTH_HI_RES, HEIGHT_HI_RES);
+}
+
+#[test]
+fn test_op_dxyn_wrap_origin() {
+    test_wrap_origin(false, WIDTH_LO_RES, HEIGHT_LO_RES);
+}
+
+#[test]
+fn test_op_dxyn_wrap_origin_hi_res() {
+    test_wrap_origin(true, WIDTH_HI_RES, HEIGHT_HI_RES);
+}
+
+#[test]
+fn test_op_dxyn_wrap_corner_hi_res() {
+    let mut cpu = create_cpu(true);
+    cpu.quirks.wrap = true;
+    cpu.hi_res = true;
+    cpu.i = 0x300;
+    cpu.memory.write_byte(0x300, 0b11000000);
+    cpu.memory.write_byte(0x301, 0b11000000);
+    cpu.v[0] = (WIDTH_HI_RES - 1) as u8;
+    cpu.v[1] = (HEIGHT_HI_RES - 1) as u8;
+    cpu.run_opcode(0xd012);
+    assert_eq!(cpu.memory.read_vram(WIDTH_HI_RES - 1, HEIGHT_HI_RES - 1), 1);
+    assert_eq!(cpu.memory.read_vram(0, HEIGHT_HI_RES - 1), 1);
+    assert_eq!(cpu.memory.read_vram(WIDTH_HI_RES - 1, 0), 1);
+    assert_eq!(cpu.memory.read_vram(0, 0), 1);
+}
+
+#[test]
+fn test_op_dxyn_double_lo_res() {
+    let mut cpu = create_cpu(true);
+    cpu.i = 0x300;
+    cpu.memory.write_byte(0x300, 0b10000000);
+    cpu.v[0] = 3;
+    cpu.v[1] = 2;
+    cpu.run_opcode(0xd011);
+    assert_eq!(cpu.memory.read_vram(6, 4), 1);
+    assert_eq!(cpu.memory.read_vram(7, 4), 1);
+    assert_eq!(cpu.memory.read_vram(6, 5), 1);
+    assert_eq!(cpu.memory.read_vram(7, 5), 1);
+    assert_eq!(cpu.memory.read_vram(3, 2), 0);
+    assert_eq!(count_pixels(&mut cpu), 4);
+    assert_eq!(cpu.v[0x0f], 0);
+    cpu.run_opcode(0xd011);
+    assert_eq!(count_pixels(&mut cpu), 0);
+    assert_eq!(cpu.v[0x0f], 1);
+}
+
+#[test]
+fn test_op_dxyn_double_lo_res_clip() {
+    let mut cpu = create_cpu(true);
+    cpu.i = 0x300;
+    cpu.memory.write_byte(0x300, 0b11111111);
+    cpu.v[0] = (WIDTH_LO_RES - 1) as u8;
+    cpu.v[1] = 0;
+    cpu.run_opcode(0xd011);
+    assert_eq!(cpu.memory.read_vram(WIDTH_HI_RES - 1, 1), 1);
+    assert_eq!(count_pixels(&mut cpu), 4);
+}
+
+#[test]
+fn test_double_lo_res_output() {
+    let mut cpu = create_cpu(true);
+    let output = cpu.tick([false; 16]);
+    assert_eq!((output.width, output.height), (WIDTH_HI_RES, HEIGHT_HI_RES));
+    let mut cpu = create_cpu(false);
+    let output = cpu.tick([false; 16]);
+    assert_eq!((output.width, output.height), (WIDTH_LO_RES, HEIGHT_LO_RES));
+}
+
 #[test]
 fn test_op_ex9e() {
     let mut cpu = create_cpu(false);
@@ -373,15 +706,15 @@ fn test_op_fx07() {
 fn test_op_fx0a() {
     let mut cpu = create_cpu(false);
     cpu.run_opcode(0xf50a);
-    assert_eq!(cpu.keypad_waiting, true);
+    assert!(cpu.keypad_waiting);
     assert_eq!(cpu.keypad_register, 5);
     assert_eq!(cpu.pc, NEXT_PC);
     cpu.tick([false; 16]);
-    assert_eq!(cpu.keypad_waiting, true);
+    assert!(cpu.keypad_waiting);
     assert_eq!(cpu.keypad_register, 5);
     assert_eq!(cpu.pc, NEXT_PC);
     cpu.tick([true; 16]);
-    assert_eq!(cpu.keypad_waiting, false);
+    assert!(!cpu.keypad_waiting);
     assert_eq!(cpu.v[5], 0);
     assert_eq!(cpu.pc, NEXT_PC);
 }
@@ -476,6 +809,495 @@ fn test_timers() {
         cpu.tick([false; 16]);
     }
 
+    assert_eq!(cpu.dt, 200);
+    assert_eq!(cpu.st, 100);
+
+    // The timers count down once per frame, however many instructions it
+    // runs.
+    cpu.run_frame([false; 16], 66);
     assert_eq!(cpu.dt, 199);
     assert_eq!(cpu.st, 99);
 }
+
+#[test]
+fn test_beep() {
+    let mut cpu = create_cpu(false);
+    cpu.v[0] = 2;
+    load_program(&mut cpu, &[0xf018, 0x1000 | (START_PC + OPCODE_SIZE)]);
+    assert!(cpu.run_frame([false; 16], 16).beep);
+    assert!(cpu.run_frame([false; 16], 16).beep);
+    assert_eq!(cpu.st, 1);
+    assert!(!cpu.run_frame([false; 16], 16).beep);
+}
+
+#[test]
+fn test_run_frame() {
+    let mut cpu = create_cpu(false);
+    cpu.memory.write_byte(START_PC as usize, 0xd0);
+    cpu.memory.write_byte(START_PC as usize + 1, 0x01);
+    let output = cpu.run_frame([false; 16], 3);
+    assert!(output.draw_flag);
+    assert_eq!(cpu.pc, START_PC + 3 * OPCODE_SIZE);
+    assert_eq!(cpu.cycles(), 3);
+}
+
+fn load_program(cpu: &mut Chip8, program: &[u16]) {
+    for (i, opcode) in program.iter().enumerate() {
+        let address = START_PC as usize + i * OPCODE_SIZE as usize;
+        cpu.memory.write_byte(address, (opcode >> 8) as u8);
+        cpu.memory.write_byte(address + 1, *opcode as u8);
+    }
+}
+
+#[test]
+fn test_display_wait() {
+    let mut cpu = create_cpu(false);
+    cpu.quirks.display_wait = true;
+    cpu.run_opcode(0xd001);
+    assert!(cpu.vblank_waiting);
+    assert_eq!(cpu.pc, NEXT_PC);
+    cpu.tick([false; 16]);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_display_wait_disabled() {
+    let mut cpu = create_cpu(false);
+    cpu.run_opcode(0xd001);
+    assert!(!cpu.vblank_waiting);
+}
+
+#[test]
+fn test_display_wait_one_draw_per_frame() {
+    // Draws the same pixel over and over, like the display wait check of the
+    // Timendus quirks test: each frame may only run a single DXYN.
+    let mut cpu = create_cpu(false);
+    cpu.quirks.display_wait = true;
+    cpu.i = 0x300;
+    cpu.memory.write_byte(0x300, 0b10000000);
+    cpu.v[1] = 0;
+    load_program(&mut cpu, &[0xd101, 0x7101, 0x1000 | START_PC]);
+
+    for frame in 1..=5 {
+        cpu.run_frame([false; 16], 8);
+        assert_eq!(cpu.v[1], frame - 1);
+        assert_eq!(cpu.pc, NEXT_PC);
+    }
+
+    for x in 0..5 {
+        assert_eq!(cpu.memory.read_vram(x, 0), 1);
+    }
+
+    assert_eq!(cpu.memory.read_vram(5, 0), 0);
+}
+
+#[test]
+fn test_display_wait_keeps_timers_running() {
+    let mut cpu = create_cpu(false);
+    cpu.quirks.display_wait = true;
+    cpu.dt = 10;
+    load_program(&mut cpu, &[0xd011, 0x1000 | START_PC]);
+    cpu.run_frame([false; 16], 8);
+    assert_eq!(cpu.dt, 9);
+}
+
+fn create_two_page_cpu() -> Chip8 {
+    Chip8::new(Platform::TwoPage, Quirks::new(Platform::TwoPage))
+}
+
+#[test]
+fn test_two_page_load_with_header() {
+    let mut cpu = create_two_page_cpu();
+    cpu.load(&[0x12, 0x60, 0xaa]);
+    assert_eq!(cpu.memory.read_word(0x200), 0x1260);
+    assert_eq!(cpu.memory.read_byte(0x202), 0xaa);
+    assert_eq!(cpu.pc, 0x260);
+}
+
+#[test]
+fn test_two_page_load_without_header() {
+    let mut cpu = create_two_page_cpu();
+    cpu.load(&[0x00, 0xe0]);
+    assert_eq!(cpu.memory.read_word(0x260), 0x00e0);
+    assert_eq!(cpu.memory.read_word(0x200), 0);
+    assert_eq!(cpu.pc, 0x260);
+}
+
+#[test]
+fn test_two_page_display() {
+    let mut cpu = create_two_page_cpu();
+    cpu.i = 0x300;
+    cpu.memory.write_byte(0x300, 0b10000000);
+    cpu.v[0] = 0;
+    cpu.v[1] = 40;
+    cpu.run_opcode(0xd011);
+    assert_eq!(cpu.memory.read_vram(0, 40), 1);
+    let output = cpu.tick([false; 16]);
+    assert_eq!((output.width, output.height), (WIDTH_LO_RES, 64));
+}
+
+#[test]
+fn test_two_page_clear_routine() {
+    let mut cpu = create_two_page_cpu();
+    cpu.set_machine_code_policy(MachineCodePolicy::Halt);
+    cpu.memory.write_vram(63, 63, 1);
+    cpu.run_opcode(0x0230);
+    assert_eq!(cpu.memory.read_vram(63, 63), 0);
+    assert!(cpu.error().is_none());
+}
+
+#[test]
+fn test_set_platform_clear_routine() {
+    let mut cpu = create_cpu(false);
+    cpu.set_machine_code_policy(MachineCodePolicy::Halt);
+    cpu.set_platform(Platform::TwoPage, Quirks::new(Platform::TwoPage));
+    cpu.memory.write_vram(63, 63, 1);
+    cpu.run_opcode(0x0230);
+    assert_eq!(cpu.memory.read_vram(63, 63), 0);
+    assert!(cpu.error().is_none());
+
+    // Other platforms don't know the routine.
+    cpu.set_platform(Platform::Chip8, Quirks::new(Platform::Chip8));
+    cpu.run_opcode(0x0230);
+    assert!(cpu.error().is_some());
+}
+
+#[test]
+fn test_set_platform_keeps_registered_routines() {
+    let mut cpu = create_two_page_cpu();
+    cpu.set_machine_code_policy(MachineCodePolicy::Halt);
+    cpu.register_machine_routine(0x300, Box::new(|chip8: &mut Chip8| chip8.set_i(0x123)));
+    cpu.set_platform(Platform::Chip8, Quirks::new(Platform::Chip8));
+    cpu.run_opcode(0x0300);
+    assert_eq!(cpu.i, 0x123);
+    assert!(cpu.error().is_none());
+}
+
+fn create_chip8x_cpu() -> Chip8 {
+    let mut cpu = Chip8::new(Platform::Chip8X, Quirks::new(Platform::Chip8X));
+    cpu.pc = START_PC;
+    cpu
+}
+
+#[test]
+fn test_chip8x_load() {
+    let mut cpu = Chip8::new(Platform::Chip8X, Quirks::new(Platform::Chip8X));
+    cpu.load(&[0x12, 0x34]);
+    assert_eq!(cpu.memory.read_word(0x300), 0x1234);
+    assert_eq!(cpu.pc, 0x300);
+}
+
+#[test]
+fn test_op_02a0() {
+    let mut cpu = create_chip8x_cpu();
+
+    for expected in [1, 2, 3, 0] {
+        cpu.run_opcode(0x02a0);
+        assert_eq!(cpu.memory.colors().unwrap().background(), expected);
+    }
+
+    assert_eq!(cpu.pc, START_PC + 4 * OPCODE_SIZE);
+}
+
+#[test]
+fn test_op_5xy1() {
+    let mut cpu = create_chip8x_cpu();
+    cpu.v[0] = 0x35;
+    cpu.v[1] = 0x64;
+    cpu.run_opcode(0x5011);
+    assert_eq!(cpu.v[0], 0x11);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_op_5xy1_chip8() {
+    let mut cpu = create_cpu(false);
+    cpu.v[0] = 0x35;
+    cpu.run_opcode(0x5011);
+    assert_eq!(cpu.v[0], 0x35);
+}
+
+#[test]
+fn test_op_bxy0() {
+    let mut cpu = create_chip8x_cpu();
+    cpu.v[0] = 0x12;
+    cpu.v[1] = 0x01;
+    cpu.v[2] = 5;
+    cpu.run_opcode(0xb020);
+    let colors = cpu.memory.colors().unwrap();
+    assert_eq!(colors.foreground(2 * 8, 4), 5);
+    assert_eq!(colors.foreground(3 * 8 + 7, 7), 5);
+    assert_eq!(colors.foreground(8, 4), 1);
+    assert_eq!(colors.foreground(4 * 8, 4), 1);
+    assert_eq!(colors.foreground(2 * 8, 3), 1);
+    assert_eq!(colors.foreground(2 * 8, 8), 1);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_op_bxyn() {
+    let mut cpu = create_chip8x_cpu();
+    cpu.v[0] = 20;
+    cpu.v[1] = 3;
+    cpu.v[2] = 6;
+    cpu.run_opcode(0xb022);
+    let colors = cpu.memory.colors().unwrap();
+    assert_eq!(colors.foreground(16, 3), 6);
+    assert_eq!(colors.foreground(23, 4), 6);
+    assert_eq!(colors.foreground(16, 5), 1);
+    assert_eq!(colors.foreground(16, 2), 1);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_op_exf2() {
+    let mut cpu = create_chip8x_cpu();
+    cpu.second_keypad[9] = true;
+    cpu.v[5] = 9;
+    cpu.run_opcode(0xe5f2);
+    assert_eq!(cpu.pc, SKIPPED_PC);
+    let mut cpu = create_chip8x_cpu();
+    cpu.keypad[9] = true;
+    cpu.v[5] = 9;
+    cpu.run_opcode(0xe5f2);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_op_exf5() {
+    let mut cpu = create_chip8x_cpu();
+    cpu.second_keypad[9] = true;
+    cpu.v[5] = 9;
+    cpu.run_opcode(0xe5f5);
+    assert_eq!(cpu.pc, NEXT_PC);
+    let mut cpu = create_chip8x_cpu();
+    cpu.v[5] = 9;
+    cpu.run_opcode(0xe5f5);
+    assert_eq!(cpu.pc, SKIPPED_PC);
+}
+
+#[test]
+fn test_op_fxf8() {
+    let mut cpu = create_chip8x_cpu();
+    cpu.v[5] = 0x42;
+    cpu.run_opcode(0xf5f8);
+    assert_eq!(cpu.output_port(), 0x42);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_op_fxfb() {
+    let mut cpu = create_chip8x_cpu();
+    cpu.connect_input_port();
+    cpu.run_opcode(0xf5fb);
+    assert_eq!(cpu.pc, START_PC);
+    cpu.set_input_port(0x42);
+    cpu.run_opcode(0xf5fb);
+    assert_eq!(cpu.v[5], 0x42);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_op_fxfb_without_input() {
+    let mut cpu = create_chip8x_cpu();
+    cpu.v[5] = 0x42;
+    cpu.run_opcode(0xf5fb);
+    assert_eq!(cpu.v[5], 0);
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+fn create_mega_chip_cpu() -> Chip8 {
+    let mut cpu = Chip8::new(Platform::MegaChip, Quirks::new(Platform::MegaChip));
+    cpu.pc = START_PC;
+    cpu.run_opcode(0x0011);
+    cpu.pc = START_PC;
+    cpu
+}
+
+#[test]
+fn test_mega_chip_mode() {
+    let mut cpu = create_mega_chip_cpu();
+    assert!(cpu.memory.mega_chip().unwrap().enabled());
+    assert_eq!(cpu.get_display_size(), (WIDTH_MEGA_CHIP, HEIGHT_MEGA_CHIP));
+    cpu.run_opcode(0x0010);
+    assert!(!cpu.memory.mega_chip().unwrap().enabled());
+    assert_eq!(cpu.get_display_size(), (WIDTH_LO_RES, HEIGHT_LO_RES));
+}
+
+#[test]
+fn test_mega_chip_load_above_4k() {
+    let mut cpu = Chip8::new(Platform::MegaChip, Quirks::new(Platform::MegaChip));
+    let mut rom = vec![0; 0x1000];
+    rom.push(0x42);
+    cpu.load(&rom);
+    assert_eq!(cpu.memory.read_byte(0x1200), 0x42);
+}
+
+#[test]
+fn test_op_01nn() {
+    let mut cpu = create_mega_chip_cpu();
+    load_program(&mut cpu, &[0x0112, 0x3456]);
+    cpu.run_opcode(0x0112);
+    assert_eq!(cpu.i, 0x123456);
+    assert_eq!(cpu.pc, SKIPPED_PC);
+}
+
+#[test]
+fn test_op_02nn() {
+    let mut cpu = create_mega_chip_cpu();
+    cpu.i = 0x400;
+    cpu.memory.write_byte(0x400, 0xff);
+    cpu.memory.write_byte(0x401, 0x10);
+    cpu.memory.write_byte(0x402, 0x20);
+    cpu.memory.write_byte(0x403, 0x30);
+    cpu.run_opcode(0x0201);
+    let color = cpu.memory.mega_chip().unwrap().palette_color(1);
+    assert_eq!(color, Color::RGBA(0x10, 0x20, 0x30, 0xff));
+}
+
+#[test]
+fn test_op_02nn_outside_memory() {
+    let mut cpu = create_mega_chip_cpu();
+    cpu.i = 0xfffffc;
+    cpu.run_opcode(0x0202);
+    assert!(cpu.error().unwrap().starts_with("palette at 0xFFFFFC"));
+    assert_eq!(cpu.pc, START_PC);
+}
+
+#[test]
+fn test_op_03nn_04nn() {
+    let mut cpu = create_mega_chip_cpu();
+    cpu.run_opcode(0x0310);
+    cpu.run_opcode(0x0400);
+    assert_eq!(cpu.sprite_width, 0x10);
+    assert_eq!(cpu.sprite_height, 256);
+}
+
+#[test]
+fn test_op_060n_0700() {
+    let mut cpu = create_mega_chip_cpu();
+    cpu.i = 0x400;
+    let header = [0x1f, 0x40, 0x00, 0x00, 0x02, 0x00, 0x80, 0x81];
+
+    for (offset, &byte) in header.iter().enumerate() {
+        cpu.memory.write_byte(0x400 + offset, byte);
+    }
+
+    cpu.run_opcode(0x0601);
+    let sound = cpu.sound().unwrap();
+    assert_eq!(sound.sample_rate, 8000);
+    assert_eq!(sound.samples, vec![0x80, 0x81]);
+    assert!(!sound.looping);
+    cpu.run_opcode(0x0700);
+    assert!(cpu.sound().is_none());
+}
+
+#[test]
+fn test_op_060n_outside_memory() {
+    let mut cpu = create_mega_chip_cpu();
+    load_program(&mut cpu, &[0x01ff, 0xffff, 0x0600]);
+    cpu.tick([false; 16]);
+    cpu.tick([false; 16]);
+    assert!(cpu.error().unwrap().starts_with("sound header at 0xFFFFFF"));
+    assert!(cpu.sound().is_none());
+
+    // The samples are cut off at the end of memory.
+    let mut cpu = create_mega_chip_cpu();
+    cpu.i = 0xfffff8;
+    cpu.memory.write_byte(0xfffffc, 0xff);
+    cpu.run_opcode(0x0600);
+    assert_eq!(cpu.sound().unwrap().samples.len(), 2);
+}
+
+#[test]
+fn test_mega_chip_sprite() {
+    let mut cpu = create_mega_chip_cpu();
+    cpu.i = 0x400;
+    cpu.memory.write_byte(0x400, 0x00);
+    cpu.memory.write_byte(0x401, 0x05);
+    cpu.run_opcode(0x0302);
+    cpu.run_opcode(0x0401);
+    cpu.v[0] = 10;
+    cpu.v[1] = 20;
+    cpu.run_opcode(0xd011);
+    assert_eq!(cpu.memory.read_vram(10, 20), 0);
+    assert_eq!(cpu.memory.read_vram(11, 20), 5);
+    assert_eq!(cpu.v[0x0f], 0);
+    cpu.run_opcode(0x0905);
+    cpu.run_opcode(0xd011);
+    assert_eq!(cpu.v[0x0f], 1);
+}
+
+#[test]
+fn test_mega_chip_sprite_clips() {
+    let mut cpu = create_mega_chip_cpu();
+    cpu.i = 0x400;
+    cpu.memory.write_byte(0x400, 0x01);
+    cpu.memory.write_byte(0x401, 0x02);
+    cpu.run_opcode(0x0302);
+    cpu.run_opcode(0x0401);
+    cpu.v[0] = 255;
+    cpu.v[1] = 0;
+    cpu.run_opcode(0xd011);
+    assert_eq!(cpu.memory.read_vram(255, 0), 1);
+    assert_eq!(cpu.memory.read_vram(0, 0), 0);
+}
+
+#[test]
+fn test_trace_hook() {
+    let entries = Rc::new(RefCell::new(Vec::new()));
+    let recorded = Rc::clone(&entries);
+    let mut cpu = create_cpu(false);
+    load_program(&mut cpu, &[0x6a7b, 0xfa33]);
+    cpu.i = 0x300;
+    cpu.add_trace_hook(Box::new(move |entry: &TraceEntry| {
+        recorded.borrow_mut().push(entry.clone());
+        Ok(())
+    }));
+    cpu.tick([false; 16]);
+    cpu.tick([false; 16]);
+
+    let entries = entries.borrow();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].cycle, 0);
+    assert_eq!(entries[0].before.pc, START_PC);
+    assert_eq!(entries[0].after.v[0x0a], 0x7b);
+    assert_eq!(entries[1].opcode, 0xfa33);
+    assert_eq!(entries[1].writes, vec![(0x300, 1), (0x301, 2), (0x302, 3)]);
+    assert_eq!(cpu.cycles(), 2);
+}
+
+#[test]
+fn test_trace_hook_error_halts() {
+    let mut cpu = create_cpu(false);
+    load_program(&mut cpu, &[0x6a7b, 0x6b7b]);
+    cpu.add_trace_hook(Box::new(|_: &TraceEntry| Err("disk full".to_string())));
+    cpu.tick([false; 16]);
+    cpu.tick([false; 16]);
+    assert_eq!(cpu.error(), Some("disk full"));
+    assert_eq!(cpu.pc, NEXT_PC);
+}
+
+#[test]
+fn test_reset() {
+    let mut cpu = create_cpu(false);
+    cpu.set_machine_code_policy(MachineCodePolicy::Halt);
+    cpu.memory.write_byte(0x300, 1);
+    cpu.memory.write_vram(0, 0, 1);
+    cpu.halt("error".to_string());
+    cpu.reset();
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.v, [0; 16]);
+    assert_eq!(cpu.memory.read_byte(0x300), 0);
+    assert_eq!(cpu.memory.read_vram(0, 0), 0);
+    assert!(cpu.error().is_none());
+    assert_eq!(cpu.machine_code_policy, MachineCodePolicy::Halt);
+}
+
+#[test]
+fn test_set_platform() {
+    let mut cpu = create_cpu(false);
+    cpu.set_platform(Platform::Chip8X, Quirks::new(Platform::Chip8X));
+    assert_eq!(cpu.platform(), Platform::Chip8X);
+    assert_eq!(cpu.pc, 0x300);
+}
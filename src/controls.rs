@@ -0,0 +1,123 @@
+#[cfg(test)]
+#[path = "./controls_test.rs"]
+mod controls_test;
+
+use crate::keypad::Action;
+use std::time::Duration;
+
+/// Factor the frames are slowed down by in slow motion.
+const SLOW_MOTION_FACTOR: u32 = 4;
+const MAX_TICKS_PER_FRAME: u64 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    Normal,
+    /// Runs the frames as fast as possible.
+    FastForward,
+    SlowMotion,
+}
+
+/// Runtime controls of the emulation: pause, frame advance, speed and the
+/// instructions per frame.
+pub struct Controls {
+    paused: bool,
+    advance: bool,
+    speed: Speed,
+    ticks_per_frame: u64,
+}
+
+impl Controls {
+    pub fn new(ticks_per_frame: u64) -> Self {
+        Controls {
+            paused: false,
+            advance: false,
+            speed: Speed::Normal,
+            ticks_per_frame,
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn ticks_per_frame(&self) -> u64 {
+        self.ticks_per_frame
+    }
+
+    pub fn set_ticks_per_frame(&mut self, ticks_per_frame: u64) {
+        self.ticks_per_frame = ticks_per_frame.clamp(1, MAX_TICKS_PER_FRAME);
+    }
+
+    /// Applies a runtime control and returns the notification for it.
+    /// Other actions are left to the caller.
+    pub fn apply(&mut self, action: Action) -> Option<String> {
+        match action {
+            Action::TogglePause => {
+                self.paused = !self.paused;
+                Some(if self.paused { "Paused" } else { "Resumed" }.to_string())
+            }
+            Action::AdvanceFrame => {
+                self.paused = true;
+                self.advance = true;
+                Some("Frame advance".to_string())
+            }
+            Action::ToggleFastForward => Some(self.toggle_speed(Speed::FastForward)),
+            Action::ToggleSlowMotion => Some(self.toggle_speed(Speed::SlowMotion)),
+            Action::IncreaseSpeed => {
+                let step = (self.ticks_per_frame / 4).max(1);
+                self.ticks_per_frame = (self.ticks_per_frame + step).min(MAX_TICKS_PER_FRAME);
+                Some(self.ticks_message())
+            }
+            Action::DecreaseSpeed => {
+                let step = (self.ticks_per_frame / 5).max(1);
+                self.ticks_per_frame = self.ticks_per_frame.saturating_sub(step).max(1);
+                Some(self.ticks_message())
+            }
+            _ => None,
+        }
+    }
+
+    /// Tells whether the next frame runs. A frame advance lets a single
+    /// frame run while paused.
+    pub fn next_frame(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+
+        let advance = self.advance;
+        self.advance = false;
+        advance
+    }
+
+    /// Returns the time a frame takes at the current speed, or `None` when
+    /// fast-forwarding without a limit.
+    pub fn frame_duration(&self, duration: Duration) -> Option<Duration> {
+        match self.speed {
+            Speed::Normal => Some(duration),
+            Speed::FastForward => None,
+            Speed::SlowMotion => Some(duration * SLOW_MOTION_FACTOR),
+        }
+    }
+
+    fn toggle_speed(&mut self, speed: Speed) -> String {
+        self.speed = if self.speed == speed {
+            Speed::Normal
+        } else {
+            speed
+        };
+
+        match self.speed {
+            Speed::Normal => "Normal speed".to_string(),
+            Speed::FastForward => "Fast forward".to_string(),
+            Speed::SlowMotion => format!("Slow motion 1/{}", SLOW_MOTION_FACTOR),
+        }
+    }
+
+    fn ticks_message(&self) -> String {
+        format!("{} instructions per frame", self.ticks_per_frame)
+    }
+}
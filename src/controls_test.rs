@@ -0,0 +1,114 @@
+use super::*;
+use crate::chip8::{Chip8, FRAME_RATE};
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+
+#[test]
+fn test_pause() {
+    let mut controls = Controls::new(8);
+    assert!(controls.next_frame());
+    assert_eq!(
+        controls.apply(Action::TogglePause),
+        Some("Paused".to_string())
+    );
+    assert!(!controls.next_frame());
+    assert_eq!(
+        controls.apply(Action::TogglePause),
+        Some("Resumed".to_string())
+    );
+    assert!(controls.next_frame());
+}
+
+#[test]
+fn test_advance_frame() {
+    let mut controls = Controls::new(8);
+    controls.apply(Action::AdvanceFrame);
+    assert!(controls.paused());
+    assert!(controls.next_frame());
+    assert!(!controls.next_frame());
+}
+
+#[test]
+fn test_speed() {
+    let mut controls = Controls::new(8);
+    let frame = Duration::from_millis(16);
+    assert_eq!(
+        controls.apply(Action::ToggleFastForward),
+        Some("Fast forward".to_string())
+    );
+    assert_eq!(controls.frame_duration(frame), None);
+    controls.apply(Action::ToggleSlowMotion);
+    assert_eq!(controls.speed(), Speed::SlowMotion);
+    assert_eq!(
+        controls.frame_duration(frame),
+        Some(Duration::from_millis(64))
+    );
+    assert_eq!(
+        controls.apply(Action::ToggleSlowMotion),
+        Some("Normal speed".to_string())
+    );
+    assert_eq!(controls.frame_duration(frame), Some(frame));
+}
+
+#[test]
+fn test_ticks_per_frame() {
+    let mut controls = Controls::new(8);
+    assert_eq!(
+        controls.apply(Action::IncreaseSpeed),
+        Some("10 instructions per frame".to_string())
+    );
+    controls.apply(Action::DecreaseSpeed);
+    assert_eq!(controls.ticks_per_frame(), 8);
+
+    let mut controls = Controls::new(1);
+    controls.apply(Action::DecreaseSpeed);
+    assert_eq!(controls.ticks_per_frame(), 1);
+
+    controls.set_ticks_per_frame(30);
+    assert_eq!(controls.ticks_per_frame(), 30);
+    controls.set_ticks_per_frame(0);
+    assert_eq!(controls.ticks_per_frame(), 1);
+}
+
+/// Frames until a delay timer set to one second runs out.
+fn timer_frames(controls: &Controls) -> u64 {
+    let mut chip8 = Chip8::new(Platform::Chip8, Quirks::new(Platform::Chip8));
+    chip8.load(&[0x12, 0x00]);
+    chip8.set_dt(FRAME_RATE as u8);
+    let mut frames = 0;
+
+    loop {
+        chip8.run_frame([false; 16], controls.ticks_per_frame());
+        frames += 1;
+
+        if chip8.state().dt == 0 {
+            return frames;
+        }
+    }
+}
+
+#[test]
+fn test_speed_keeps_timer_rate() {
+    let mut controls = Controls::new(8);
+    assert_eq!(timer_frames(&controls), FRAME_RATE);
+
+    for _ in 0..10 {
+        controls.apply(Action::IncreaseSpeed);
+    }
+
+    assert_eq!(timer_frames(&controls), FRAME_RATE);
+
+    for _ in 0..20 {
+        controls.apply(Action::DecreaseSpeed);
+    }
+
+    assert_eq!(controls.ticks_per_frame(), 1);
+    assert_eq!(timer_frames(&controls), FRAME_RATE);
+}
+
+#[test]
+fn test_other_actions() {
+    let mut controls = Controls::new(8);
+    assert_eq!(controls.apply(Action::Reset), None);
+    assert_eq!(controls.apply(Action::ToggleFullscreen), None);
+}
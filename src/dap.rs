@@ -0,0 +1,852 @@
+#[cfg(test)]
+#[path = "./dap_test.rs"]
+mod dap_test;
+
+use crate::cartridge::{open_cartridge, Cartridge};
+use crate::chip8::Chip8;
+use crate::debugger::{DebugServer, Debugger, StopReason};
+use crate::disassembler::disassemble_with_symbols;
+use crate::symbols::Symbols;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+const THREAD_ID: u64 = 1;
+const REGISTERS_REFERENCE: u64 = 1;
+const STACK_REFERENCE: u64 = 2;
+const HEADER_END: &[u8] = b"\r\n\r\n";
+const CONTENT_LENGTH: &str = "Content-Length";
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Splits the received bytes into messages. Incomplete messages stay in
+/// the buffer; invalid ones are dropped from it and returned as errors.
+pub fn parse_messages(buffer: &mut Vec<u8>) -> Vec<Result<Value, String>> {
+    let mut messages = Vec::new();
+
+    while let Some(header_end) = find(buffer, HEADER_END) {
+        let header = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+        let start = header_end + HEADER_END.len();
+        let length = header
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(CONTENT_LENGTH))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok());
+
+        let length = match length {
+            Some(length) => length,
+            None => {
+                // Without a length, the body ends where the next header
+                // starts.
+                buffer.drain(..start);
+                let next = find(buffer, CONTENT_LENGTH.as_bytes()).unwrap_or(buffer.len());
+                buffer.drain(..next);
+                messages.push(Err(format!("missing Content-Length in '{}'", header)));
+                continue;
+            }
+        };
+
+        if buffer.len() < start + length {
+            break;
+        }
+
+        messages.push(
+            serde_json::from_slice(&buffer[start..start + length])
+                .map_err(|err| format!("invalid message: {}", err)),
+        );
+        buffer.drain(..start + length);
+    }
+
+    messages
+}
+
+fn find(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len())
+        .position(|window| window == pattern)
+}
+
+pub fn format_message(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    let mut data = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    data.extend_from_slice(body.as_bytes());
+    data
+}
+
+/// Maps assembler source lines to addresses. The map is read from a text
+/// file with one `ADDRESS LINE` pair per line, with the address in hex.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineMap {
+    addresses: BTreeMap<u32, u16>,
+    lines: BTreeMap<u16, u32>,
+}
+
+impl LineMap {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut map = LineMap::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let error = || {
+                format!(
+                    "line {}: expected 'ADDRESS LINE', got '{}'",
+                    number + 1,
+                    line
+                )
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            let (address, source_line) = match fields.as_slice() {
+                [] => continue,
+                [address, source_line] => (address, source_line),
+                _ => return Err(error()),
+            };
+
+            let address = parse_address(address).ok_or_else(error)?;
+            let source_line = source_line.parse().map_err(|_| error())?;
+            map.addresses.entry(source_line).or_insert(address);
+            map.lines.entry(address).or_insert(source_line);
+        }
+
+        Ok(map)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read line map '{}': {}", path, err))?;
+
+        LineMap::parse(&text)
+    }
+
+    pub fn address(&self, line: u32) -> Option<u16> {
+        self.addresses.get(&line).copied()
+    }
+
+    pub fn line(&self, address: u16) -> Option<u32> {
+        self.lines.get(&address).copied()
+    }
+}
+
+/// Body of a response and the events sent after it.
+type Reply = (Value, Vec<(&'static str, Value)>);
+
+/// Debug Adapter Protocol server on a local TCP port, for editors that
+/// connect to a running debug adapter.
+pub struct DapServer {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    buffer: Vec<u8>,
+    seq: u64,
+    source: Option<String>,
+    line_map: Option<LineMap>,
+    source_breakpoints: Vec<u16>,
+    instruction_breakpoints: Vec<u16>,
+    function_breakpoints: Vec<u16>,
+    symbols: Symbols,
+    stop_on_entry: bool,
+    /// ROM of the `launch` request, run by the interpreter.
+    program: Option<Cartridge>,
+}
+
+impl DapServer {
+    pub fn bind(port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|err| format!("failed to listen on port {}: {}", port, err))?;
+
+        Ok(DapServer {
+            listener,
+            client: None,
+            buffer: Vec::new(),
+            seq: 0,
+            source: None,
+            line_map: None,
+            source_breakpoints: Vec::new(),
+            instruction_breakpoints: Vec::new(),
+            function_breakpoints: Vec::new(),
+            symbols: Symbols::new(),
+            stop_on_entry: false,
+            program: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answers a request with a response followed by the events it causes.
+    pub fn handle(
+        &mut self,
+        request: &Value,
+        debugger: &mut Debugger,
+        chip8: &mut Chip8,
+    ) -> Vec<Value> {
+        let command = request["command"].as_str().unwrap_or_default();
+        let arguments = &request["arguments"];
+
+        let result = match command {
+            "initialize" => Ok((capabilities(), vec![("initialized", Value::Null)])),
+            "launch" | "attach" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments, debugger),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(arguments, debugger),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(arguments, debugger),
+            "setExceptionBreakpoints" => Ok((json!({ "breakpoints": [] }), Vec::new())),
+            "configurationDone" => Ok(self.configuration_done(debugger)),
+            "threads" => Ok((
+                json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] }),
+                Vec::new(),
+            )),
+            "stackTrace" => Ok((self.stack_trace(chip8), Vec::new())),
+            "scopes" => Ok((scopes(), Vec::new())),
+            "variables" => Ok((variables(arguments, chip8), Vec::new())),
+            "setVariable" => set_variable(arguments, chip8).map(|body| (body, Vec::new())),
+            "continue" => {
+                debugger.resume();
+                Ok((json!({ "allThreadsContinued": true }), Vec::new()))
+            }
+            "next" => Ok(step_over(debugger, chip8)),
+            "stepIn" => Ok(step(debugger, chip8)),
+            "stepOut" => Ok(step_out(debugger, chip8)),
+            "pause" => {
+                debugger.stop();
+                Ok((Value::Null, vec![("stopped", stopped_body("pause", None))]))
+            }
+            "readMemory" => read_memory(arguments, chip8).map(|body| (body, Vec::new())),
+            "writeMemory" => write_memory(arguments, chip8).map(|body| (body, Vec::new())),
+            "disassemble" => self
+                .disassemble(arguments, chip8)
+                .map(|body| (body, Vec::new())),
+            "disconnect" => {
+                debugger.clear_breakpoints();
+                debugger.resume();
+                Ok((Value::Null, Vec::new()))
+            }
+            _ => Err(format!("unsupported request '{}'", command)),
+        };
+
+        let mut messages = vec![self.response(request, result.as_ref().map(|(body, _)| body))];
+
+        if let Ok((_, events)) = result {
+            for (event, body) in events {
+                messages.push(self.event(event, body));
+            }
+        }
+
+        messages
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn response(&mut self, request: &Value, result: Result<&Value, &String>) -> Value {
+        let mut response = json!({
+            "seq": self.next_seq(),
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+
+        match result {
+            Ok(body) if !body.is_null() => response["body"] = body.clone(),
+            Ok(_) => {}
+            Err(message) => response["message"] = json!(message),
+        }
+
+        response
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Value {
+        let mut message = json!({ "seq": self.next_seq(), "type": "event", "event": event });
+
+        if !body.is_null() {
+            message["body"] = body;
+        }
+
+        message
+    }
+
+    /// Optionally loads the ROM given as `program` and the line map of its
+    /// assembler source.
+    fn launch(&mut self, arguments: &Value) -> Result<Reply, String> {
+        if let Some(program) = arguments["program"].as_str() {
+            self.program = Some(open_cartridge(program, None, None)?);
+        }
+
+        if let Some(path) = arguments["lineMap"].as_str() {
+            self.line_map = Some(LineMap::load(path)?);
+        }
+
+        self.source = arguments["source"].as_str().map(str::to_string);
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+        Ok((Value::Null, Vec::new()))
+    }
+
+    fn set_breakpoints(
+        &mut self,
+        arguments: &Value,
+        debugger: &mut Debugger,
+    ) -> Result<Reply, String> {
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let mut breakpoints = Vec::new();
+        self.source_breakpoints.clear();
+
+        for breakpoint in requested {
+            let line = breakpoint["line"].as_u64().unwrap_or(0) as u32;
+            let address = self.line_map.as_ref().and_then(|map| map.address(line));
+
+            if let Some(address) = address {
+                self.source_breakpoints.push(address);
+            }
+
+            breakpoints.push(json!({
+                "verified": address.is_some(),
+                "line": line,
+                "instructionReference": address.map(format_address),
+            }));
+        }
+
+        self.sync_breakpoints(debugger);
+        Ok((json!({ "breakpoints": breakpoints }), Vec::new()))
+    }
+
+    fn set_instruction_breakpoints(
+        &mut self,
+        arguments: &Value,
+        debugger: &mut Debugger,
+    ) -> Result<Reply, String> {
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let mut breakpoints = Vec::new();
+        self.instruction_breakpoints.clear();
+
+        for breakpoint in requested {
+            let reference = breakpoint["instructionReference"]
+                .as_str()
+                .unwrap_or_default();
+            let offset = breakpoint["offset"].as_i64().unwrap_or(0);
+            let address = parse_address(reference).map(|address| (address as i64 + offset) as u16);
+
+            if let Some(address) = address {
+                self.instruction_breakpoints.push(address);
+            }
+
+            breakpoints.push(json!({
+                "verified": address.is_some(),
+                "instructionReference": address.map(format_address),
+            }));
+        }
+
+        self.sync_breakpoints(debugger);
+        Ok((json!({ "breakpoints": breakpoints }), Vec::new()))
+    }
+
+    /// Sets breakpoints by symbol name, optionally with an offset like
+    /// `draw+4`.
+    fn set_function_breakpoints(
+        &mut self,
+        arguments: &Value,
+        debugger: &mut Debugger,
+    ) -> Result<Reply, String> {
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let mut breakpoints = Vec::new();
+        self.function_breakpoints.clear();
+
+        for breakpoint in requested {
+            let name = breakpoint["name"].as_str().unwrap_or_default();
+
+            match self.symbols.resolve(name) {
+                Ok(address) => {
+                    self.function_breakpoints.push(address);
+                    breakpoints.push(json!({
+                        "verified": true,
+                        "instructionReference": format_address(address),
+                    }));
+                }
+                Err(message) => {
+                    breakpoints.push(json!({ "verified": false, "message": message }));
+                }
+            }
+        }
+
+        self.sync_breakpoints(debugger);
+        Ok((json!({ "breakpoints": breakpoints }), Vec::new()))
+    }
+
+    fn sync_breakpoints(&self, debugger: &mut Debugger) {
+        debugger.clear_breakpoints();
+
+        for &address in self
+            .source_breakpoints
+            .iter()
+            .chain(&self.instruction_breakpoints)
+            .chain(&self.function_breakpoints)
+        {
+            debugger.add_breakpoint(address);
+        }
+    }
+
+    fn configuration_done(&mut self, debugger: &mut Debugger) -> Reply {
+        if self.stop_on_entry {
+            return (Value::Null, vec![("stopped", stopped_body("entry", None))]);
+        }
+
+        debugger.resume();
+        (Value::Null, Vec::new())
+    }
+
+    /// Derives the frames from the return addresses on the stack. Outer
+    /// frames point at the call instruction.
+    fn stack_trace(&self, chip8: &Chip8) -> Value {
+        let state = chip8.state();
+        let mut addresses = vec![state.pc];
+
+        for depth in (0..state.sp as usize).rev() {
+            addresses.push(state.stack[depth].wrapping_sub(2));
+        }
+
+        let frames: Vec<Value> = addresses
+            .iter()
+            .enumerate()
+            .map(|(id, &address)| self.frame(id, address))
+            .collect();
+
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn frame(&self, id: usize, address: u16) -> Value {
+        let mut frame = json!({
+            "id": id,
+            "name": self.symbols.locate(address).unwrap_or_else(|| format_address(address)),
+            "instructionPointerReference": format_address(address),
+            "line": 0,
+            "column": 0,
+        });
+
+        let line = self.line_map.as_ref().and_then(|map| map.line(address));
+
+        if let (Some(line), Some(source)) = (line, &self.source) {
+            frame["line"] = json!(line);
+            frame["column"] = json!(1);
+            frame["source"] = json!({ "path": source });
+        }
+
+        frame
+    }
+
+    fn disassemble(&self, arguments: &Value, chip8: &mut Chip8) -> Result<Value, String> {
+        let address = memory_reference(arguments)? as i64;
+        let first = address + arguments["instructionOffset"].as_i64().unwrap_or(0) * 2;
+        let count = arguments["instructionCount"].as_u64().unwrap_or(0) as i64;
+        let size = chip8.memory().size() as i64;
+        let mut instructions = Vec::new();
+
+        for index in 0..count {
+            let address = first + index * 2;
+
+            if address < 0 || address + 1 >= size {
+                instructions.push(json!({
+                    "address": format!("0x{:04X}", address.max(0)),
+                    "instruction": "",
+                    "presentationHint": "invalid",
+                }));
+                continue;
+            }
+
+            let opcode = chip8.memory().read_word(address as usize);
+            let mut instruction = json!({
+                "address": format_address(address as u16),
+                "instructionBytes": format!("{:04X}", opcode),
+                "instruction": disassemble_with_symbols(opcode, chip8.platform(), &self.symbols),
+            });
+
+            if let Some(name) = self.symbols.name(address as u16) {
+                instruction["symbol"] = json!(name);
+            }
+
+            if let (Some(line), Some(source)) = (
+                self.line_map
+                    .as_ref()
+                    .and_then(|map| map.line(address as u16)),
+                &self.source,
+            ) {
+                instruction["line"] = json!(line);
+                instruction["location"] = json!({ "path": source });
+            }
+
+            instructions.push(instruction);
+        }
+
+        Ok(json!({ "instructions": instructions }))
+    }
+
+    fn send(&mut self, message: &Value) -> Result<(), String> {
+        if let Some(client) = &mut self.client {
+            if let Err(err) = client.write_all(&format_message(message)) {
+                self.client = None;
+                return Err(format!("failed to write to the debug client: {}", err));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn accept(&mut self, debugger: &mut Debugger) -> Result<(), String> {
+        match self.listener.accept() {
+            Ok((client, _)) => {
+                client
+                    .set_nonblocking(true)
+                    .and_then(|_| client.set_nodelay(true))
+                    .map_err(|err| format!("failed to set up the debug connection: {}", err))?;
+
+                self.client = Some(client);
+                self.buffer.clear();
+                debugger.stop();
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(err) => Err(format!("failed to accept the debug connection: {}", err)),
+        }
+    }
+
+    /// Reads the available bytes. Returns false once the client is gone.
+    fn receive(&mut self) -> bool {
+        let client = match &mut self.client {
+            Some(client) => client,
+            None => return false,
+        };
+
+        let mut data = [0; 4096];
+
+        loop {
+            match client.read(&mut data) {
+                Ok(0) => return false,
+                Ok(count) => self.buffer.extend_from_slice(&data[..count]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+impl DebugServer for DapServer {
+    fn poll(&mut self, debugger: &mut Debugger, chip8: &mut Chip8) -> Result<(), String> {
+        if self.client.is_none() {
+            return self.accept(debugger);
+        }
+
+        if !self.receive() {
+            // The program continues without a debugger attached.
+            self.client = None;
+            debugger.clear_breakpoints();
+            debugger.resume();
+            return Ok(());
+        }
+
+        for request in parse_messages(&mut self.buffer) {
+            let request = match request {
+                Ok(request) => request,
+                Err(err) => {
+                    let response = self.response(&Value::Null, Err(&err));
+                    self.send(&response)?;
+                    continue;
+                }
+            };
+
+            for message in self.handle(&request, debugger, chip8) {
+                self.send(&message)?;
+            }
+
+            if request["command"] == "disconnect" {
+                self.client = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stopped(&mut self, reason: &StopReason, _chip8: &mut Chip8) -> Result<(), String> {
+        let body = match reason {
+            StopReason::Breakpoint(_) => stopped_body("breakpoint", None),
+            StopReason::Step => stopped_body("step", None),
+            StopReason::Interrupt => stopped_body("pause", None),
+            StopReason::Error(error) => stopped_body("exception", Some(error)),
+        };
+
+        let event = self.event("stopped", body);
+        self.send(&event)
+    }
+
+    fn take_program(&mut self) -> Option<Cartridge> {
+        self.program.take()
+    }
+
+    /// Names the frames and disassembly, and resolves function breakpoints.
+    fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsSetVariable": true,
+        "supportsReadMemoryRequest": true,
+        "supportsWriteMemoryRequest": true,
+        "supportsDisassembleRequest": true,
+        "supportsInstructionBreakpoints": true,
+        "supportsFunctionBreakpoints": true,
+        "supportsSteppingGranularity": true,
+    })
+}
+
+fn stopped_body(reason: &str, text: Option<&str>) -> Value {
+    let mut body = json!({
+        "reason": reason,
+        "threadId": THREAD_ID,
+        "allThreadsStopped": true,
+    });
+
+    if let Some(text) = text {
+        body["text"] = json!(text);
+    }
+
+    body
+}
+
+fn step(debugger: &mut Debugger, chip8: &mut Chip8) -> Reply {
+    let reason = match debugger.step(chip8, [false; 16]) {
+        StopReason::Error(error) => stopped_body("exception", Some(&error)),
+        _ => stopped_body("step", None),
+    };
+
+    (Value::Null, vec![("stopped", reason)])
+}
+
+/// Runs a called subroutine to its return, and steps anything else.
+fn step_over(debugger: &mut Debugger, chip8: &mut Chip8) -> Reply {
+    let pc = chip8.state().pc;
+    let opcode = chip8.memory().read_word(pc as usize);
+
+    if opcode & 0xF000 != 0x2000 {
+        return step(debugger, chip8);
+    }
+
+    debugger.run_to(pc + 2);
+    (Value::Null, Vec::new())
+}
+
+fn step_out(debugger: &mut Debugger, chip8: &mut Chip8) -> Reply {
+    let state = chip8.state();
+
+    if state.sp == 0 {
+        return step(debugger, chip8);
+    }
+
+    debugger.run_to(state.stack[state.sp as usize - 1]);
+    (Value::Null, Vec::new())
+}
+
+fn scopes() -> Value {
+    json!({
+        "scopes": [
+            { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+            { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+        ]
+    })
+}
+
+fn variables(arguments: &Value, chip8: &Chip8) -> Value {
+    let state = chip8.state();
+    let variable = |name: String, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+
+    let variables: Vec<Value> = match arguments["variablesReference"].as_u64() {
+        Some(REGISTERS_REFERENCE) => {
+            let mut registers: Vec<Value> = state
+                .v
+                .iter()
+                .enumerate()
+                .map(|(register, value)| {
+                    variable(format!("V{:X}", register), format!("0x{:02X}", value))
+                })
+                .collect();
+
+            registers.push(variable("I".to_string(), format!("0x{:04X}", state.i)));
+            registers.push(variable("PC".to_string(), format!("0x{:04X}", state.pc)));
+            registers.push(variable("SP".to_string(), state.sp.to_string()));
+            registers.push(variable("DT".to_string(), state.dt.to_string()));
+            registers.push(variable("ST".to_string(), state.st.to_string()));
+            registers
+        }
+        Some(STACK_REFERENCE) => (0..state.sp as usize)
+            .map(|depth| {
+                variable(
+                    format!("[{}]", depth),
+                    format!("0x{:04X}", state.stack[depth]),
+                )
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    json!({ "variables": variables })
+}
+
+fn set_variable(arguments: &Value, chip8: &mut Chip8) -> Result<Value, String> {
+    if arguments["variablesReference"].as_u64() != Some(REGISTERS_REFERENCE) {
+        return Err("only registers can be changed".to_string());
+    }
+
+    let name = arguments["name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_uppercase();
+    let text = arguments["value"].as_str().unwrap_or_default();
+    let value = parse_number(text).ok_or(format!("invalid value '{}'", text))?;
+    let too_large = || format!("value {} is too large for {}", text, name);
+    let outside = || format!("address {} is outside of memory", text);
+
+    match name.as_str() {
+        "I" if chip8.is_valid_i(value) => chip8.set_i(value),
+        "PC" if chip8.is_valid_pc(value) => chip8.set_pc(value as u16),
+        "I" | "PC" => return Err(outside()),
+        "DT" if value <= u8::MAX as u32 => chip8.set_dt(value as u8),
+        "ST" if value <= u8::MAX as u32 => chip8.set_st(value as u8),
+        "DT" | "ST" => return Err(too_large()),
+        _ => {
+            let register = name
+                .strip_prefix('V')
+                .filter(|register| register.len() == 1)
+                .and_then(|register| usize::from_str_radix(register, 16).ok())
+                .ok_or(format!("register '{}' can't be changed", name))?;
+
+            if value > u8::MAX as u32 {
+                return Err(too_large());
+            }
+
+            chip8.set_v(register, value as u8);
+        }
+    }
+
+    Ok(json!({ "value": text }))
+}
+
+fn memory_reference(arguments: &Value) -> Result<u32, String> {
+    let reference = arguments["memoryReference"].as_str().unwrap_or_default();
+    let offset = arguments["offset"].as_i64().unwrap_or(0);
+    let address =
+        parse_number(reference).ok_or(format!("invalid memory reference '{}'", reference))?;
+    Ok((address as i64 + offset).max(0) as u32)
+}
+
+fn read_memory(arguments: &Value, chip8: &mut Chip8) -> Result<Value, String> {
+    let address = memory_reference(arguments)? as usize;
+    let count = arguments["count"].as_u64().unwrap_or(0) as usize;
+    let memory = chip8.memory();
+    let end = address
+        .saturating_add(count)
+        .min(memory.size())
+        .max(address);
+    let data: Vec<u8> = (address..end)
+        .map(|address| memory.read_byte(address))
+        .collect();
+
+    Ok(json!({
+        "address": format!("0x{:04X}", address),
+        "data": encode_base64(&data),
+        "unreadableBytes": count - data.len(),
+    }))
+}
+
+fn write_memory(arguments: &Value, chip8: &mut Chip8) -> Result<Value, String> {
+    let address = memory_reference(arguments)? as usize;
+    let data = decode_base64(arguments["data"].as_str().unwrap_or_default())?;
+    let memory = chip8.memory();
+
+    if address + data.len() > memory.size() {
+        return Err(format!("address 0x{:X} is out of range", address));
+    }
+
+    for (offset, &byte) in data.iter().enumerate() {
+        memory.write_byte(address + offset, byte);
+    }
+
+    Ok(json!({ "bytesWritten": data.len() }))
+}
+
+fn format_address(address: u16) -> String {
+    format!("0x{:04X}", address)
+}
+
+fn parse_address(value: &str) -> Option<u16> {
+    u16::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+/// Parses a decimal number or a hex number with a `0x` prefix.
+fn parse_number(value: &str) -> Option<u32> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::new();
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(bits >> (18 - i * 6)) as usize & 0x3F] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let error = || format!("invalid base64 data '{}'", text);
+    let unpadded = text.trim_end_matches('=');
+
+    if !text.len().is_multiple_of(4) || text.len() - unpadded.len() > 2 {
+        return Err(error());
+    }
+
+    let mut data = Vec::new();
+    let mut bits = 0u32;
+    let mut count = 0;
+
+    for character in unpadded.bytes() {
+        let value = BASE64
+            .iter()
+            .position(|&c| c == character)
+            .ok_or_else(error)?;
+        bits = bits << 6 | value as u32;
+        count += 6;
+
+        if count >= 8 {
+            count -= 8;
+            data.push((bits >> count) as u8);
+        }
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,549 @@
+use super::*;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+/// Calls a subroutine adding 5 to V0, then loads 1 into V0 and loops.
+fn create_chip8() -> Chip8 {
+    let mut chip8 = Chip8::new(Platform::Chip8, Quirks::new(Platform::Chip8));
+    chip8.load(&[0x22, 0x06, 0x60, 0x01, 0x12, 0x04, 0x70, 0x05, 0x00, 0xee]);
+    chip8
+}
+
+fn create_server() -> DapServer {
+    DapServer::bind(0).unwrap()
+}
+
+fn create_line_map(server: &mut DapServer) {
+    server.line_map = Some(LineMap::parse("200 1\n202 2\n204 3\n206 5\n208 6\n").unwrap());
+    server.source = Some("game.8o".to_string());
+}
+
+fn request(command: &str, arguments: Value) -> Value {
+    json!({ "seq": 1, "type": "request", "command": command, "arguments": arguments })
+}
+
+#[test]
+fn test_parse_messages() {
+    let mut buffer = format_message(&json!({ "seq": 1 }));
+    buffer.extend_from_slice(b"Content-Length: 20\r\n\r\n{\"seq\"");
+    let messages = parse_messages(&mut buffer);
+    assert_eq!(messages, vec![Ok(json!({ "seq": 1 }))]);
+    assert_eq!(buffer, b"Content-Length: 20\r\n\r\n{\"seq\"".to_vec());
+}
+
+#[test]
+fn test_parse_messages_without_length() {
+    let mut buffer = b"Content-Type: json\r\n\r\n{}".to_vec();
+    buffer.extend(format_message(&json!({ "seq": 2 })));
+    let messages = parse_messages(&mut buffer);
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].is_err());
+    assert_eq!(messages[1], Ok(json!({ "seq": 2 })));
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_parse_messages_invalid_json() {
+    let mut buffer = b"Content-Length: 5\r\n\r\n{seq}".to_vec();
+    buffer.extend(format_message(&json!({ "seq": 2 })));
+    let messages = parse_messages(&mut buffer);
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].is_err());
+    assert_eq!(messages[1], Ok(json!({ "seq": 2 })));
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_format_message() {
+    assert_eq!(
+        format_message(&json!({ "seq": 1 })),
+        b"Content-Length: 9\r\n\r\n{\"seq\":1}".to_vec()
+    );
+}
+
+#[test]
+fn test_line_map() {
+    let map = LineMap::parse("200 3\n\n0x202 4\n").unwrap();
+    assert_eq!(map.address(4), Some(0x202));
+    assert_eq!(map.line(0x200), Some(3));
+    assert_eq!(map.line(0x204), None);
+    assert!(LineMap::parse("200").is_err());
+    assert!(LineMap::parse("zz 1").is_err());
+}
+
+#[test]
+fn test_base64() {
+    assert_eq!(encode_base64(b"CHIP-8"), "Q0hJUC04");
+    assert_eq!(encode_base64(&[0x60, 0x01]), "YAE=");
+    assert_eq!(decode_base64("YAE=").unwrap(), vec![0x60, 0x01]);
+    assert_eq!(decode_base64("Q0hJUC04").unwrap(), b"CHIP-8".to_vec());
+    assert!(decode_base64("Y*E=").is_err());
+}
+
+#[test]
+fn test_base64_padding() {
+    assert_eq!(encode_base64(&[]), "");
+    assert_eq!(encode_base64(&[0x60]), "YA==");
+    assert_eq!(encode_base64(&[0x60, 0x01, 0x70]), "YAFw");
+    assert_eq!(decode_base64("").unwrap(), Vec::<u8>::new());
+    assert_eq!(decode_base64("YA==").unwrap(), vec![0x60]);
+    assert_eq!(decode_base64("YAFw").unwrap(), vec![0x60, 0x01, 0x70]);
+    assert!(decode_base64("YA=").is_err());
+    assert!(decode_base64("YAE").is_err());
+    assert!(decode_base64("Y===").is_err());
+    assert!(decode_base64("YA=A").is_err());
+}
+
+#[test]
+fn test_launch_program() {
+    let directory = env::temp_dir().join("chip8-dap-launch");
+    fs::create_dir_all(&directory).unwrap();
+    let rom = directory.join("game.ch8");
+    fs::write(&rom, [0x12, 0x00]).unwrap();
+    fs::write(directory.join("game.sym"), "0x200 main\n").unwrap();
+
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let launch = request("launch", json!({ "program": rom.to_str().unwrap() }));
+    let messages = server.handle(&launch, &mut debugger, &mut chip8);
+    assert_eq!(messages[0]["success"], true);
+
+    let program = server.take_program().unwrap();
+    assert_eq!(program.rom, vec![0x12, 0x00]);
+    assert_eq!(program.symbols.resolve("main"), Ok(0x200));
+    assert!(server.take_program().is_none());
+
+    let launch = request("launch", json!({ "program": "missing.ch8" }));
+    let messages = server.handle(&launch, &mut debugger, &mut chip8);
+    assert_eq!(messages[0]["success"], false);
+    assert!(server.take_program().is_none());
+}
+
+#[test]
+fn test_initialize() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let messages = server.handle(&request("initialize", json!({})), &mut debugger, &mut chip8);
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0]["success"], true);
+    assert_eq!(messages[0]["body"]["supportsDisassembleRequest"], true);
+    assert_eq!(messages[1]["event"], "initialized");
+    assert_eq!(messages[1]["seq"], 2);
+}
+
+#[test]
+fn test_unsupported_request() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let messages = server.handle(&request("evaluate", json!({})), &mut debugger, &mut chip8);
+    assert_eq!(messages[0]["success"], false);
+    assert_eq!(messages[0]["message"], "unsupported request 'evaluate'");
+}
+
+#[test]
+fn test_configuration_done_stops_on_entry() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let launch = request("launch", json!({ "stopOnEntry": true }));
+    server.handle(&launch, &mut debugger, &mut chip8);
+    let messages = server.handle(
+        &request("configurationDone", json!({})),
+        &mut debugger,
+        &mut chip8,
+    );
+    assert_eq!(messages[1]["body"]["reason"], "entry");
+    assert!(!debugger.running());
+
+    server.handle(&request("launch", json!({})), &mut debugger, &mut chip8);
+    server.handle(
+        &request("configurationDone", json!({})),
+        &mut debugger,
+        &mut chip8,
+    );
+    assert!(debugger.running());
+}
+
+#[test]
+fn test_set_breakpoints() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    create_line_map(&mut server);
+    let arguments = json!({
+        "source": { "path": "game.8o" },
+        "breakpoints": [{ "line": 2 }, { "line": 4 }],
+    });
+    let messages = server.handle(
+        &request("setBreakpoints", arguments),
+        &mut debugger,
+        &mut chip8,
+    );
+    let breakpoints = &messages[0]["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[0]["instructionReference"], "0x0202");
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    let arguments = json!({ "breakpoints": [{ "instructionReference": "0x0206", "offset": 2 }] });
+    server.handle(
+        &request("setInstructionBreakpoints", arguments),
+        &mut debugger,
+        &mut chip8,
+    );
+    assert_eq!(
+        debugger.breakpoints().collect::<Vec<_>>(),
+        vec![0x202, 0x208]
+    );
+}
+
+#[test]
+fn test_stack_trace() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    create_line_map(&mut server);
+    debugger.step(&mut chip8, [false; 16]);
+
+    let messages = server.handle(&request("stackTrace", json!({})), &mut debugger, &mut chip8);
+    let frames = &messages[0]["body"]["stackFrames"];
+    assert_eq!(messages[0]["body"]["totalFrames"], 2);
+    assert_eq!(frames[0]["instructionPointerReference"], "0x0206");
+    assert_eq!(frames[0]["line"], 5);
+    assert_eq!(frames[0]["source"]["path"], "game.8o");
+    assert_eq!(frames[1]["instructionPointerReference"], "0x0200");
+    assert_eq!(frames[1]["line"], 1);
+}
+
+#[test]
+fn test_variables() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    debugger.step(&mut chip8, [false; 16]);
+
+    let arguments = json!({ "variablesReference": REGISTERS_REFERENCE });
+    let messages = server.handle(&request("variables", arguments), &mut debugger, &mut chip8);
+    let variables = &messages[0]["body"]["variables"];
+    assert_eq!(variables[17]["name"], "PC");
+    assert_eq!(variables[17]["value"], "0x0206");
+
+    let arguments = json!({ "variablesReference": STACK_REFERENCE });
+    let messages = server.handle(&request("variables", arguments), &mut debugger, &mut chip8);
+    assert_eq!(
+        messages[0]["body"]["variables"],
+        json!([{ "name": "[0]", "value": "0x0202", "variablesReference": 0 }])
+    );
+}
+
+#[test]
+fn test_set_variable() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let arguments =
+        json!({ "variablesReference": REGISTERS_REFERENCE, "name": "VA", "value": "0x42" });
+    let messages = server.handle(
+        &request("setVariable", arguments),
+        &mut debugger,
+        &mut chip8,
+    );
+    assert_eq!(messages[0]["success"], true);
+    assert_eq!(chip8.v(0xa), 0x42);
+
+    let arguments =
+        json!({ "variablesReference": REGISTERS_REFERENCE, "name": "DT", "value": "300" });
+    let messages = server.handle(
+        &request("setVariable", arguments),
+        &mut debugger,
+        &mut chip8,
+    );
+    assert_eq!(messages[0]["message"], "value 300 is too large for DT");
+
+    for name in ["PC", "I"] {
+        let arguments =
+            json!({ "variablesReference": REGISTERS_REFERENCE, "name": name, "value": "0x8000" });
+        let messages = server.handle(
+            &request("setVariable", arguments),
+            &mut debugger,
+            &mut chip8,
+        );
+        assert_eq!(
+            messages[0]["message"],
+            "address 0x8000 is outside of memory"
+        );
+    }
+
+    assert_eq!(chip8.state().pc, 0x200);
+    assert_eq!(chip8.i(), 0);
+}
+
+#[test]
+fn test_next_steps_over_calls() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let messages = server.handle(&request("next", json!({})), &mut debugger, &mut chip8);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        debugger.run_frame(&mut chip8, [false; 16], 10),
+        Some(StopReason::Step)
+    );
+    assert_eq!(chip8.state().pc, 0x202);
+    assert_eq!(chip8.v(0), 5);
+
+    let messages = server.handle(&request("next", json!({})), &mut debugger, &mut chip8);
+    assert_eq!(messages[1]["body"]["reason"], "step");
+    assert_eq!(chip8.state().pc, 0x204);
+}
+
+#[test]
+fn test_step_out() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    server.handle(&request("stepIn", json!({})), &mut debugger, &mut chip8);
+    assert_eq!(chip8.state().pc, 0x206);
+
+    server.handle(&request("stepOut", json!({})), &mut debugger, &mut chip8);
+    assert_eq!(
+        debugger.run_frame(&mut chip8, [false; 16], 10),
+        Some(StopReason::Step)
+    );
+    assert_eq!(chip8.state().pc, 0x202);
+}
+
+#[test]
+fn test_memory() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let arguments = json!({ "memoryReference": "0x0200", "offset": 2, "count": 2 });
+    let messages = server.handle(&request("readMemory", arguments), &mut debugger, &mut chip8);
+    assert_eq!(messages[0]["body"]["address"], "0x0202");
+    assert_eq!(messages[0]["body"]["data"], "YAE=");
+
+    let arguments = json!({ "memoryReference": "0x7fff", "count": 2 });
+    let messages = server.handle(&request("readMemory", arguments), &mut debugger, &mut chip8);
+    assert_eq!(messages[0]["body"]["unreadableBytes"], 1);
+
+    let arguments = json!({ "memoryReference": "0x0300", "data": "q80=" });
+    let messages = server.handle(
+        &request("writeMemory", arguments),
+        &mut debugger,
+        &mut chip8,
+    );
+    assert_eq!(messages[0]["body"]["bytesWritten"], 2);
+    assert_eq!(chip8.memory().read_byte(0x301), 0xcd);
+}
+
+#[test]
+fn test_disassemble() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let arguments = json!({
+        "memoryReference": "0x0202",
+        "instructionOffset": -1,
+        "instructionCount": 2,
+    });
+    let messages = server.handle(
+        &request("disassemble", arguments),
+        &mut debugger,
+        &mut chip8,
+    );
+    let instructions = &messages[0]["body"]["instructions"];
+    assert_eq!(instructions[0]["address"], "0x0200");
+    assert_eq!(instructions[0]["instruction"], "CALL 0x206");
+    assert_eq!(instructions[1]["instructionBytes"], "6001");
+}
+
+/// Test client that keeps the messages it hasn't looked at yet, since
+/// events can arrive together with the response before them.
+struct Client {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    received: Vec<Value>,
+}
+
+impl Client {
+    fn connect(server: &DapServer) -> Self {
+        let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(1)))
+            .unwrap();
+
+        Client {
+            stream,
+            buffer: Vec::new(),
+            received: Vec::new(),
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        self.stream.write_all(&format_message(&message)).unwrap();
+    }
+
+    /// Polls the server until a response or event named `expected` arrives.
+    fn expect(
+        &mut self,
+        server: &mut DapServer,
+        debugger: &mut Debugger,
+        chip8: &mut Chip8,
+        expected: &str,
+    ) -> Value {
+        for _ in 0..500 {
+            let position = self
+                .received
+                .iter()
+                .position(|message| message["command"] == expected || message["event"] == expected);
+
+            if let Some(position) = position {
+                return self.received.remove(position);
+            }
+
+            server.poll(debugger, chip8).unwrap();
+
+            if let Some(reason) = debugger.run_frame(chip8, [false; 16], 10) {
+                server.stopped(&reason, chip8).unwrap();
+            }
+
+            let mut data = [0; 1024];
+
+            if let Ok(count) = self.stream.read(&mut data) {
+                self.buffer.extend_from_slice(&data[..count]);
+            }
+
+            self.received.extend(
+                parse_messages(&mut self.buffer)
+                    .into_iter()
+                    .map(Result::unwrap),
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        panic!("no '{}' message", expected);
+    }
+}
+
+#[test]
+fn test_scripted_session() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let mut client = Client::connect(&server);
+
+    let mut exchange = |client: &mut Client, message: Option<Value>, expected: &str| {
+        if let Some(message) = message {
+            client.send(message);
+        }
+
+        client.expect(&mut server, &mut debugger, &mut chip8, expected)
+    };
+
+    exchange(
+        &mut client,
+        Some(request("initialize", json!({}))),
+        "initialized",
+    );
+
+    // A bad message is answered with an error and the session goes on.
+    client
+        .stream
+        .write_all(b"Content-Length: 5\r\n\r\n{seq}")
+        .unwrap();
+    let threads = exchange(&mut client, Some(request("threads", json!({}))), "threads");
+    assert_eq!(threads["success"], true);
+    let error = client
+        .received
+        .iter()
+        .find(|message| message["success"] == false)
+        .unwrap();
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("invalid message"));
+
+    exchange(&mut client, Some(request("launch", json!({}))), "launch");
+    let arguments = json!({ "breakpoints": [{ "instructionReference": "0x0208" }] });
+    let request_breakpoints = request("setInstructionBreakpoints", arguments);
+    exchange(
+        &mut client,
+        Some(request_breakpoints),
+        "setInstructionBreakpoints",
+    );
+    exchange(
+        &mut client,
+        Some(request("configurationDone", json!({}))),
+        "configurationDone",
+    );
+
+    let stopped = exchange(&mut client, None, "stopped");
+    assert_eq!(stopped["body"]["reason"], "breakpoint");
+
+    let arguments = json!({ "variablesReference": REGISTERS_REFERENCE });
+    let variables = exchange(
+        &mut client,
+        Some(request("variables", arguments)),
+        "variables",
+    );
+    assert_eq!(variables["body"]["variables"][0]["value"], "0x05");
+
+    let clear_breakpoints = request("setInstructionBreakpoints", json!({}));
+    exchange(
+        &mut client,
+        Some(clear_breakpoints),
+        "setInstructionBreakpoints",
+    );
+    exchange(
+        &mut client,
+        Some(request("continue", json!({}))),
+        "continue",
+    );
+    let stopped = exchange(&mut client, Some(request("pause", json!({}))), "stopped");
+    assert_eq!(stopped["body"]["reason"], "pause");
+    assert_eq!(chip8.v(0), 1);
+}
+
+#[test]
+fn test_symbols() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let mut symbols = Symbols::new();
+    symbols.insert(0x200, "main");
+    symbols.insert(0x206, "add_five");
+    server.set_symbols(symbols);
+
+    let arguments = json!({ "breakpoints": [{ "name": "add_five+2" }, { "name": "missing" }] });
+    let messages = server.handle(
+        &request("setFunctionBreakpoints", arguments),
+        &mut debugger,
+        &mut chip8,
+    );
+    let breakpoints = &messages[0]["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["instructionReference"], "0x0208");
+    assert_eq!(breakpoints[1]["verified"], false);
+    assert_eq!(debugger.breakpoints().collect::<Vec<_>>(), vec![0x208]);
+
+    debugger.step(&mut chip8, [false; 16]);
+    let messages = server.handle(&request("stackTrace", json!({})), &mut debugger, &mut chip8);
+    let frames = &messages[0]["body"]["stackFrames"];
+    assert_eq!(frames[0]["name"], "add_five");
+    assert_eq!(frames[1]["name"], "main");
+
+    let arguments = json!({ "memoryReference": "0x0200", "instructionCount": 1 });
+    let messages = server.handle(
+        &request("disassemble", arguments),
+        &mut debugger,
+        &mut chip8,
+    );
+    let instruction = &messages[0]["body"]["instructions"][0];
+    assert_eq!(instruction["instruction"], "CALL add_five");
+    assert_eq!(instruction["symbol"], "main");
+}
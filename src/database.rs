@@ -0,0 +1,101 @@
+#[cfg(test)]
+#[path = "./database_test.rs"]
+mod database_test;
+
+use crate::platform::Platform;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+
+/// What the database knows about a ROM.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    /// First listed platform the interpreter supports.
+    pub platform: Option<Platform>,
+}
+
+/// ROM titles and platforms by SHA-1 hash, read from the `programs.json`
+/// file of the CHIP-8 community database.
+#[derive(Clone, Debug, Default)]
+pub struct RomDatabase {
+    roms: HashMap<String, RomInfo>,
+}
+
+impl RomDatabase {
+    pub fn new() -> Self {
+        RomDatabase::default()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let programs: Value =
+            serde_json::from_str(text).map_err(|err| format!("invalid ROM database: {}", err))?;
+        let programs = programs
+            .as_array()
+            .ok_or("invalid ROM database: expected a list of programs")?;
+        let mut database = RomDatabase::new();
+
+        for program in programs {
+            let title = program["title"].as_str().unwrap_or_default();
+            let roms = match program["roms"].as_object() {
+                Some(roms) => roms,
+                None => continue,
+            };
+
+            for (hash, rom) in roms {
+                let platform = rom["platforms"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .find_map(parse_platform);
+
+                database.roms.insert(
+                    hash.to_lowercase(),
+                    RomInfo {
+                        title: title.to_string(),
+                        platform,
+                    },
+                );
+            }
+        }
+
+        Ok(database)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read ROM database '{}': {}", path, err))?;
+
+        RomDatabase::parse(&text)
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<&RomInfo> {
+        self.roms.get(&hash(rom))
+    }
+}
+
+/// Returns the SHA-1 hash of `rom` in lowercase hex, as used by the
+/// database.
+pub fn hash(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+/// Maps the platform ids of the database to the supported platforms.
+fn parse_platform(id: &str) -> Option<Platform> {
+    match id {
+        "originalChip8" | "hybridVIP" | "modernChip8" => Some(Platform::Chip8),
+        "chip8x" => Some(Platform::Chip8X),
+        "chip48" | "superchip1" | "superchip" => Some(Platform::SuperChip),
+        "megachip8" => Some(Platform::MegaChip),
+        _ => None,
+    }
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+const PROGRAMS: &str = r#"[
+    {
+        "title": "Pong",
+        "roms": {
+            "A9EF1A5EAE4D9C1AE9F8DAAB0C1A9A59B6E8E1AC": { "platforms": ["originalChip8"] }
+        }
+    },
+    {
+        "title": "Octojam Title",
+        "roms": {
+            "0000000000000000000000000000000000000000": { "platforms": ["xochip", "superchip"] },
+            "1111111111111111111111111111111111111111": { "platforms": ["xochip"] }
+        }
+    },
+    { "title": "Without ROMs" }
+]"#;
+
+#[test]
+fn test_hash() {
+    assert_eq!(hash(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+}
+
+#[test]
+fn test_parse() {
+    let database = RomDatabase::parse(PROGRAMS).unwrap();
+    assert_eq!(database.len(), 3);
+    assert_eq!(
+        database.roms["0000000000000000000000000000000000000000"],
+        RomInfo {
+            title: "Octojam Title".to_string(),
+            platform: Some(Platform::SuperChip),
+        }
+    );
+    assert_eq!(
+        database.roms["1111111111111111111111111111111111111111"].platform,
+        None
+    );
+}
+
+#[test]
+fn test_lookup() {
+    let mut database = RomDatabase::new();
+    let info = RomInfo {
+        title: "ABC".to_string(),
+        platform: Some(Platform::Chip8),
+    };
+    database.roms.insert(hash(b"abc"), info.clone());
+    assert_eq!(database.lookup(b"abc"), Some(&info));
+    assert_eq!(database.lookup(b"abd"), None);
+}
+
+#[test]
+fn test_parse_error() {
+    assert!(RomDatabase::parse("{}").is_err());
+    assert!(RomDatabase::parse("[").is_err());
+}
@@ -0,0 +1,183 @@
+#[cfg(test)]
+#[path = "./debugger_test.rs"]
+mod debugger_test;
+
+use crate::cartridge::Cartridge;
+use crate::chip8::Chip8;
+use crate::symbols::Symbols;
+use std::collections::BTreeSet;
+
+/// Upper bound of ticks spent on a single step, so stepping while the
+/// program waits for a key returns.
+const MAX_STEP_TICKS: u64 = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StopReason {
+    Breakpoint(u16),
+    Step,
+    /// The client asked to stop.
+    Interrupt,
+    /// The interpreter halted with an error.
+    Error(String),
+}
+
+/// Execution control shared by the debug servers: breakpoints, stepping
+/// and running until one of them stops the program.
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    running: bool,
+    /// Address that stops the program once, used to step over calls.
+    target: Option<u16>,
+    /// Address the program last stopped at. Resuming from there runs the
+    /// instruction instead of hitting its breakpoint again.
+    stopped_at: Option<u16>,
+}
+
+impl Debugger {
+    /// Creates a debugger with the program stopped, so a client can set
+    /// breakpoints before it starts.
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            running: false,
+            target: None,
+            stopped_at: None,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.running
+    }
+
+    pub fn resume(&mut self) {
+        self.running = true;
+        self.target = None;
+    }
+
+    /// Resumes until the program reaches `address` or a breakpoint.
+    pub fn run_to(&mut self, address: u16) {
+        self.running = true;
+        self.target = Some(address);
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.target = None;
+        self.stopped_at = None;
+    }
+
+    /// Stops at `pc` because of `reason`.
+    fn stop_at(&mut self, pc: u16, reason: StopReason) -> Option<StopReason> {
+        self.stop();
+        self.stopped_at = Some(pc);
+        Some(reason)
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.remove(&address);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Runs the ticks of one frame while running. A breakpoint at the
+    /// current address stops the program before it runs, e.g. one on the
+    /// entry point, unless the program was resumed from there.
+    pub fn run_frame(
+        &mut self,
+        chip8: &mut Chip8,
+        keypad: [bool; 16],
+        ticks: u64,
+    ) -> Option<StopReason> {
+        if !self.running {
+            return None;
+        }
+
+        let pc = chip8.state().pc;
+
+        if self.breakpoints.contains(&pc) && self.stopped_at != Some(pc) {
+            return self.stop_at(pc, StopReason::Breakpoint(pc));
+        }
+
+        chip8.start_frame();
+
+        for _ in 0..ticks {
+            let cycles = chip8.cycles();
+            chip8.tick(keypad);
+            let pc = chip8.state().pc;
+
+            if let Some(error) = chip8.error() {
+                return self.stop_at(pc, StopReason::Error(error.to_string()));
+            }
+
+            if chip8.cycles() == cycles {
+                continue;
+            }
+
+            if self.breakpoints.contains(&pc) {
+                return self.stop_at(pc, StopReason::Breakpoint(pc));
+            }
+
+            if self.target == Some(pc) {
+                return self.stop_at(pc, StopReason::Step);
+            }
+        }
+
+        None
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self, chip8: &mut Chip8, keypad: [bool; 16]) -> StopReason {
+        let cycles = chip8.cycles();
+        self.stop();
+        chip8.end_vblank_wait();
+
+        for _ in 0..MAX_STEP_TICKS {
+            chip8.tick(keypad);
+
+            if let Some(error) = chip8.error() {
+                self.stopped_at = Some(chip8.state().pc);
+                return StopReason::Error(error.to_string());
+            }
+
+            if chip8.cycles() != cycles {
+                break;
+            }
+        }
+
+        self.stopped_at = Some(chip8.state().pc);
+        StopReason::Step
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Debugger::new()
+    }
+}
+
+/// Protocol front end of the debugger, polled once per frame.
+pub trait DebugServer {
+    /// Handles the pending requests of the client.
+    fn poll(&mut self, debugger: &mut Debugger, chip8: &mut Chip8) -> Result<(), String>;
+
+    /// Tells the client that the program stopped.
+    fn stopped(&mut self, reason: &StopReason, chip8: &mut Chip8) -> Result<(), String>;
+
+    /// Names addresses after the labels of a newly loaded ROM.
+    fn set_symbols(&mut self, _symbols: Symbols) {}
+
+    /// Returns the ROM the client asked to run instead of the current one.
+    fn take_program(&mut self) -> Option<Cartridge> {
+        None
+    }
+}
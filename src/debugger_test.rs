@@ -0,0 +1,98 @@
+use super::*;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+
+/// Loads a program at 0x200: LD V0, 1; ADD V0, 1; JP 0x202.
+fn create_chip8() -> Chip8 {
+    let mut chip8 = Chip8::new(Platform::Chip8, Quirks::new(Platform::Chip8));
+    chip8.load(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+    chip8
+}
+
+#[test]
+fn test_starts_stopped() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.run_frame(&mut chip8, [false; 16], 10), None);
+    assert_eq!(chip8.cycles(), 0);
+}
+
+#[test]
+fn test_step() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.step(&mut chip8, [false; 16]), StopReason::Step);
+    assert_eq!(chip8.state().pc, 0x202);
+    assert_eq!(chip8.v(0), 1);
+    assert_eq!(chip8.cycles(), 1);
+}
+
+#[test]
+fn test_breakpoint() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x204);
+    debugger.resume();
+    let reason = debugger.run_frame(&mut chip8, [false; 16], 10);
+    assert_eq!(reason, Some(StopReason::Breakpoint(0x204)));
+    assert!(!debugger.running());
+    assert_eq!(chip8.v(0), 2);
+
+    // Resuming runs the instruction at the breakpoint.
+    debugger.resume();
+    let reason = debugger.run_frame(&mut chip8, [false; 16], 10);
+    assert_eq!(reason, Some(StopReason::Breakpoint(0x204)));
+    assert_eq!(chip8.v(0), 3);
+    debugger.remove_breakpoint(0x204);
+    debugger.resume();
+    assert_eq!(debugger.run_frame(&mut chip8, [false; 16], 10), None);
+}
+
+#[test]
+fn test_entry_breakpoint() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x200);
+    debugger.resume();
+    let reason = debugger.run_frame(&mut chip8, [false; 16], 10);
+    assert_eq!(reason, Some(StopReason::Breakpoint(0x200)));
+    assert_eq!(chip8.cycles(), 0);
+
+    // Resuming from the entry point runs its instruction.
+    debugger.resume();
+    assert_eq!(debugger.run_frame(&mut chip8, [false; 16], 10), None);
+    assert_eq!(chip8.v(0), 6);
+}
+
+#[test]
+fn test_step_from_breakpoint() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x202);
+    assert_eq!(debugger.step(&mut chip8, [false; 16]), StopReason::Step);
+    debugger.resume();
+    let reason = debugger.run_frame(&mut chip8, [false; 16], 10);
+    assert_eq!(reason, Some(StopReason::Breakpoint(0x202)));
+    assert_eq!(chip8.v(0), 2);
+}
+
+#[test]
+fn test_error_stops() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    chip8.halt("stopped".to_string());
+    debugger.resume();
+    let reason = debugger.run_frame(&mut chip8, [false; 16], 10);
+    assert_eq!(reason, Some(StopReason::Error("stopped".to_string())));
+}
+
+#[test]
+fn test_run_to() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    debugger.run_to(0x204);
+    let reason = debugger.run_frame(&mut chip8, [false; 16], 10);
+    assert_eq!(reason, Some(StopReason::Step));
+    debugger.resume();
+    assert_eq!(debugger.run_frame(&mut chip8, [false; 16], 10), None);
+}
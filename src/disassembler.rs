@@ -0,0 +1,111 @@
+#[cfg(test)]
+#[path = "./disassembler_test.rs"]
+mod disassembler_test;
+
+use crate::platform::Platform;
+use crate::symbols::Symbols;
+
+/// Returns the mnemonic of `opcode` in the notation of Cowgod's technical
+/// reference, e.g. `LD V1, 0x2A`. Unknown opcodes are shown as data.
+pub fn disassemble(opcode: u16, platform: Platform) -> String {
+    let nibbles = (
+        (opcode & 0xF000) >> 12,
+        (opcode & 0x0F00) >> 8,
+        (opcode & 0x00F0) >> 4,
+        opcode & 0x000F,
+    );
+
+    let nnn = opcode & 0x0FFF;
+    let kk = opcode & 0x00FF;
+    let x = nibbles.1;
+    let y = nibbles.2;
+    let n = nibbles.3;
+    let super_chip = platform.is_super_chip();
+    let chip8x = platform == Platform::Chip8X;
+    let mega_chip = platform == Platform::MegaChip;
+
+    match nibbles {
+        (0x0, 0x0, 0xE, 0x0) => "CLS".to_string(),
+        (0x0, 0x0, 0xE, 0xE) => "RET".to_string(),
+        (0x0, 0x0, 0xF, 0xE) if super_chip => "LOW".to_string(),
+        (0x0, 0x0, 0xF, 0xF) if super_chip => "HIGH".to_string(),
+        (0x0, 0x0, 0xC, _) if super_chip => format!("SCD {}", n),
+        (0x0, 0x0, 0xF, 0xB) if super_chip => "SCR".to_string(),
+        (0x0, 0x0, 0xF, 0xC) if super_chip => "SCL".to_string(),
+        (0x0, 0x0, 0xF, 0xD) if super_chip => "EXIT".to_string(),
+        (0x0, 0x2, 0xA, 0x0) if chip8x => "BGC".to_string(),
+        (0x0, 0x0, 0x1, 0x0) if mega_chip => "MEGAOFF".to_string(),
+        (0x0, 0x0, 0x1, 0x1) if mega_chip => "MEGAON".to_string(),
+        (0x0, 0x0, 0xB, _) if mega_chip => format!("SCU {}", n),
+        (0x0, 0x1, _, _) if mega_chip => format!("LDHI I, 0x{:02X}....", kk),
+        (0x0, 0x2, _, _) if mega_chip => format!("LDPAL {}", kk),
+        (0x0, 0x3, _, _) if mega_chip => format!("SPRW {}", kk),
+        (0x0, 0x4, _, _) if mega_chip => format!("SPRH {}", kk),
+        (0x0, 0x5, _, _) if mega_chip => format!("ALPHA 0x{:02X}", kk),
+        (0x0, 0x6, 0x0, _) if mega_chip => format!("DIGISND {}", n),
+        (0x0, 0x7, 0x0, 0x0) if mega_chip => "STOPSND".to_string(),
+        (0x0, 0x8, 0x0, _) if mega_chip => format!("BMODE {}", n),
+        (0x0, 0x9, _, _) if mega_chip => format!("CCOL 0x{:02X}", kk),
+        (0x0, _, _, _) => format!("SYS 0x{:03X}", nnn),
+        (0x1, _, _, _) => format!("JP 0x{:03X}", nnn),
+        (0x2, _, _, _) => format!("CALL 0x{:03X}", nnn),
+        (0x3, _, _, _) => format!("SE V{:X}, 0x{:02X}", x, kk),
+        (0x4, _, _, _) => format!("SNE V{:X}, 0x{:02X}", x, kk),
+        (0x5, _, _, 0x0) => format!("SE V{:X}, V{:X}", x, y),
+        (0x5, _, _, 0x1) if chip8x => format!("ADD V{:X}, V{:X}", x, y),
+        (0x6, _, _, _) => format!("LD V{:X}, 0x{:02X}", x, kk),
+        (0x7, _, _, _) => format!("ADD V{:X}, 0x{:02X}", x, kk),
+        (0x8, _, _, 0x0) => format!("LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("SHR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xE) => format!("SHL V{:X}, V{:X}", x, y),
+        (0x9, _, _, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, _, _, _) => format!("LD I, 0x{:03X}", nnn),
+        (0xB, _, _, 0x0) if chip8x => format!("COL V{:X}, V{:X}", x, y),
+        (0xB, _, _, _) if chip8x => format!("COL V{:X}, V{:X}, {}", x, y, n),
+        (0xB, _, _, _) if super_chip => format!("JP V{:X}, 0x{:03X}", x, nnn),
+        (0xB, _, _, _) => format!("JP V0, 0x{:03X}", nnn),
+        (0xC, _, _, _) => format!("RND V{:X}, 0x{:02X}", x, kk),
+        (0xD, _, _, _) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{:X}", x),
+        (0xE, _, 0xF, 0x2) if chip8x => format!("SKP2 V{:X}", x),
+        (0xE, _, 0xF, 0x5) if chip8x => format!("SKNP2 V{:X}", x),
+        (0xF, _, 0x0, 0x7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0x0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{:X}", x),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{:X}", x),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
+        (0xF, _, 0xF, 0x8) if chip8x => format!("OUT V{:X}", x),
+        (0xF, _, 0xF, 0xB) if chip8x => format!("IN V{:X}", x),
+        _ => format!("DW 0x{:04X}", opcode),
+    }
+}
+
+/// Disassembles like `disassemble`, with the targets of jumps, calls and
+/// I loads that have a symbol shown by name, e.g. `CALL draw`.
+pub fn disassemble_with_symbols(opcode: u16, platform: Platform, symbols: &Symbols) -> String {
+    let mnemonic = disassemble(opcode, platform);
+    let nnn = opcode & 0x0FFF;
+
+    if !matches!(opcode >> 12, 0x1 | 0x2 | 0xA | 0xB) {
+        return mnemonic;
+    }
+
+    match (
+        mnemonic.strip_suffix(&format!("0x{:03X}", nnn)),
+        symbols.name(nnn),
+    ) {
+        (Some(operands), Some(name)) => format!("{}{}", operands, name),
+        _ => mnemonic,
+    }
+}
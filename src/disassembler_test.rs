@@ -0,0 +1,48 @@
+use super::*;
+
+#[test]
+fn test_disassemble() {
+    assert_eq!(disassemble(0x00e0, Platform::Chip8), "CLS");
+    assert_eq!(disassemble(0x6a2b, Platform::Chip8), "LD VA, 0x2B");
+    assert_eq!(disassemble(0x8124, Platform::Chip8), "ADD V1, V2");
+    assert_eq!(disassemble(0xd015, Platform::Chip8), "DRW V0, V1, 5");
+    assert_eq!(disassemble(0xf365, Platform::Chip8), "LD V3, [I]");
+}
+
+#[test]
+fn test_disassemble_platform_opcodes() {
+    assert_eq!(disassemble(0x00ff, Platform::Chip8), "SYS 0x0FF");
+    assert_eq!(disassemble(0x00ff, Platform::SuperChip), "HIGH");
+    assert_eq!(disassemble(0xb123, Platform::SuperChip), "JP V1, 0x123");
+    assert_eq!(disassemble(0xb120, Platform::Chip8X), "COL V1, V2");
+    assert_eq!(disassemble(0x0011, Platform::MegaChip), "MEGAON");
+}
+
+#[test]
+fn test_disassemble_unknown() {
+    assert_eq!(disassemble(0x5121, Platform::Chip8), "DW 0x5121");
+    assert_eq!(disassemble(0xe1ff, Platform::Chip8), "DW 0xE1FF");
+}
+
+#[test]
+fn test_disassemble_with_symbols() {
+    let mut symbols = Symbols::new();
+    symbols.insert(0x206, "draw");
+    symbols.insert(0x300, "sprite");
+    assert_eq!(
+        disassemble_with_symbols(0x2206, Platform::Chip8, &symbols),
+        "CALL draw"
+    );
+    assert_eq!(
+        disassemble_with_symbols(0xa300, Platform::Chip8, &symbols),
+        "LD I, sprite"
+    );
+    assert_eq!(
+        disassemble_with_symbols(0x1208, Platform::Chip8, &symbols),
+        "JP 0x208"
+    );
+    assert_eq!(
+        disassemble_with_symbols(0xb206, Platform::Chip8X, &symbols),
+        "COL V2, V0, 6"
+    );
+}
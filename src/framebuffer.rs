@@ -0,0 +1,237 @@
+#[cfg(test)]
+#[path = "./framebuffer_test.rs"]
+mod framebuffer_test;
+
+use crate::memory::{ColorAttributes, MegaChipScreen, Memory};
+use crate::palette::Palette;
+use crate::window::{HEIGHT_LO_RES, WIDTH_LO_RES};
+use sdl2::pixels::Color;
+
+pub const BYTES_PER_PIXEL: usize = 3;
+const PHOSPHOR_DECAY: f32 = 0.6;
+const PHOSPHOR_CUTOFF: f32 = 0.05;
+
+/// Foreground colors of the CHIP-8X color board.
+const CHIP8X_FOREGROUND: [Color; 8] = [
+    Color::RGB(0x00, 0x00, 0x00),
+    Color::RGB(0xFF, 0x00, 0x00),
+    Color::RGB(0x00, 0x00, 0xFF),
+    Color::RGB(0xFF, 0x00, 0xFF),
+    Color::RGB(0x00, 0xFF, 0x00),
+    Color::RGB(0xFF, 0xFF, 0x00),
+    Color::RGB(0x00, 0xFF, 0xFF),
+    Color::RGB(0xFF, 0xFF, 0xFF),
+];
+
+/// Background colors of the CHIP-8X color board, cycled through by 02A0.
+const CHIP8X_BACKGROUND: [Color; 4] = [
+    Color::RGB(0x00, 0x00, 0x80),
+    Color::RGB(0x00, 0x00, 0x00),
+    Color::RGB(0x00, 0x80, 0x00),
+    Color::RGB(0x80, 0x00, 0x00),
+];
+
+/// Post-processing applied to the display to hide the flicker caused by
+/// sprites being erased and redrawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    None,
+    /// Pixels that are switched off fade out over several frames.
+    Phosphor,
+    /// Every frame is mixed with the previous one.
+    Blend,
+}
+
+impl Filter {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "none" => Ok(Filter::None),
+            "phosphor" => Ok(Filter::Phosphor),
+            "blend" => Ok(Filter::Blend),
+            _ => Err(format!("unknown filter '{}'", value)),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Filter::None => Filter::Phosphor,
+            Filter::Phosphor => Filter::Blend,
+            Filter::Blend => Filter::None,
+        }
+    }
+}
+
+/// RGB image of the current display, rendered from VRAM once per frame.
+pub struct Framebuffer {
+    palette: Palette,
+    filter: Filter,
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    previous: Vec<u8>,
+    intensity: Vec<f32>,
+}
+
+impl Framebuffer {
+    pub fn new(palette: Palette, filter: Filter) -> Self {
+        Framebuffer {
+            palette,
+            filter,
+            width: WIDTH_LO_RES,
+            height: HEIGHT_LO_RES,
+            pixels: vec![0; WIDTH_LO_RES * HEIGHT_LO_RES * BYTES_PER_PIXEL],
+            previous: vec![0; WIDTH_LO_RES * HEIGHT_LO_RES],
+            intensity: vec![0.0; WIDTH_LO_RES * HEIGHT_LO_RES],
+        }
+    }
+
+    /// Renders the top-left `width` x `height` pixels of VRAM.
+    pub fn render(&mut self, memory: &mut Memory, width: usize, height: usize) {
+        if width != self.width || height != self.height {
+            self.resize(width, height);
+        }
+
+        // The MEGA-CHIP screen holds true colors, so it skips the palette and
+        // the filters.
+        if let Some(screen) = memory.mega_chip().filter(|screen| screen.enabled()) {
+            self.render_mega_chip(screen);
+            return;
+        }
+
+        let palette = self.palette;
+        let attributes = memory.colors();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = x + y * self.width;
+                let value = memory.read_vram(x, y);
+
+                // Color board attributes replace the palette on CHIP-8X.
+                let pixel_color = |value: u8| match attributes {
+                    Some(attributes) => get_attribute_color(attributes, x, y, value),
+                    None => palette.color(value),
+                };
+
+                let color = self.filter_pixel(index, value, &pixel_color);
+                let offset = index * BYTES_PER_PIXEL;
+                self.pixels[offset] = color.r;
+                self.pixels[offset + 1] = color.g;
+                self.pixels[offset + 2] = color.b;
+            }
+        }
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.intensity.iter_mut().for_each(|level| *level = 0.0);
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns the RGB image stretched to `width` x `height` pixels without
+    /// smoothing, for captures at the window scale.
+    pub fn scaled(&self, width: usize, height: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(width * height * BYTES_PER_PIXEL);
+
+        for y in 0..height {
+            let source_y = y * self.height / height;
+
+            for x in 0..width {
+                let offset = (x * self.width / width + source_y * self.width) * BYTES_PER_PIXEL;
+                pixels.extend_from_slice(&self.pixels[offset..offset + BYTES_PER_PIXEL]);
+            }
+        }
+
+        pixels
+    }
+
+    pub fn background(&self) -> Color {
+        self.palette.color(0)
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; width * height * BYTES_PER_PIXEL];
+        self.previous = vec![0; width * height];
+        self.intensity = vec![0.0; width * height];
+    }
+
+    fn render_mega_chip(&mut self, screen: &MegaChipScreen) {
+        let black = Color::RGB(0x00, 0x00, 0x00);
+        let alpha = screen.alpha() as f32 / 255.0;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let color = mix(black, screen.pixel(x, y), alpha);
+                let offset = (x + y * self.width) * BYTES_PER_PIXEL;
+                self.pixels[offset] = color.r;
+                self.pixels[offset + 1] = color.g;
+                self.pixels[offset + 2] = color.b;
+            }
+        }
+    }
+
+    fn filter_pixel(&mut self, index: usize, value: u8, color: &dyn Fn(u8) -> Color) -> Color {
+        let background = color(0);
+        let previous = self.previous[index];
+
+        let color = match self.filter {
+            Filter::None => color(value),
+            Filter::Phosphor if value != 0 => {
+                self.intensity[index] = 1.0;
+                color(value)
+            }
+            Filter::Phosphor => {
+                let mut level = self.intensity[index] * PHOSPHOR_DECAY;
+
+                if level < PHOSPHOR_CUTOFF {
+                    level = 0.0;
+                }
+
+                self.intensity[index] = level;
+                mix(background, color(previous), level)
+            }
+            Filter::Blend => mix(color(value), color(previous), 0.5),
+        };
+
+        // The phosphor filter keeps the last lit value so a fading pixel
+        // keeps its color.
+        if value != 0 || self.filter != Filter::Phosphor {
+            self.previous[index] = value;
+        }
+
+        color
+    }
+}
+
+fn get_attribute_color(attributes: &ColorAttributes, x: usize, y: usize, value: u8) -> Color {
+    if value == 0 {
+        CHIP8X_BACKGROUND[attributes.background() as usize % CHIP8X_BACKGROUND.len()]
+    } else {
+        CHIP8X_FOREGROUND[attributes.foreground(x, y) as usize % CHIP8X_FOREGROUND.len()]
+    }
+}
+
+fn mix(a: Color, b: Color, amount: f32) -> Color {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    Color::RGB(channel(a.r, b.r), channel(a.g, b.g), channel(a.b, b.b))
+}
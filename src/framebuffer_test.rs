@@ -0,0 +1,145 @@
+use super::*;
+use crate::memory::BlendMode;
+use crate::platform::Platform;
+use crate::window::{HEIGHT_HI_RES, HEIGHT_MEGA_CHIP, WIDTH_HI_RES, WIDTH_MEGA_CHIP};
+
+const BLACK: Color = Color::RGB(0, 0, 0);
+const WHITE: Color = Color::RGB(255, 255, 255);
+
+fn create_framebuffer(filter: Filter) -> (Framebuffer, Memory) {
+    let palette = Palette::new(&[BLACK, WHITE]);
+    (
+        Framebuffer::new(palette, filter),
+        Memory::new(Platform::Chip8),
+    )
+}
+
+fn pixel(framebuffer: &Framebuffer, x: usize, y: usize) -> Color {
+    let offset = (x + y * framebuffer.width()) * BYTES_PER_PIXEL;
+    let pixels = framebuffer.pixels();
+    Color::RGB(pixels[offset], pixels[offset + 1], pixels[offset + 2])
+}
+
+#[test]
+fn test_render() {
+    let (mut framebuffer, mut memory) = create_framebuffer(Filter::None);
+    memory.write_vram(3, 2, 1);
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    assert_eq!(framebuffer.width(), WIDTH_LO_RES);
+    assert_eq!(framebuffer.height(), HEIGHT_LO_RES);
+    assert_eq!(pixel(&framebuffer, 3, 2), WHITE);
+    assert_eq!(pixel(&framebuffer, 2, 2), BLACK);
+}
+
+#[test]
+fn test_render_hi_res() {
+    let (mut framebuffer, mut memory) = create_framebuffer(Filter::None);
+    memory.write_vram(WIDTH_HI_RES - 1, HEIGHT_HI_RES - 1, 1);
+    framebuffer.render(&mut memory, WIDTH_HI_RES, HEIGHT_HI_RES);
+    assert_eq!(framebuffer.width(), WIDTH_HI_RES);
+    assert_eq!(framebuffer.height(), HEIGHT_HI_RES);
+    assert_eq!(
+        pixel(&framebuffer, WIDTH_HI_RES - 1, HEIGHT_HI_RES - 1),
+        WHITE
+    );
+}
+
+#[test]
+fn test_filter_phosphor() {
+    let (mut framebuffer, mut memory) = create_framebuffer(Filter::Phosphor);
+    memory.write_vram(0, 0, 1);
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    assert_eq!(pixel(&framebuffer, 0, 0), WHITE);
+
+    memory.write_vram(0, 0, 0);
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    let first = pixel(&framebuffer, 0, 0);
+    assert!(first.r > 0 && first.r < 255);
+
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    let second = pixel(&framebuffer, 0, 0);
+    assert!(second.r < first.r);
+
+    for _ in 0..10 {
+        framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    }
+
+    assert_eq!(pixel(&framebuffer, 0, 0), BLACK);
+}
+
+#[test]
+fn test_filter_blend() {
+    let (mut framebuffer, mut memory) = create_framebuffer(Filter::Blend);
+    memory.write_vram(0, 0, 1);
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    assert_eq!(pixel(&framebuffer, 0, 0), WHITE);
+
+    memory.write_vram(0, 0, 0);
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    assert_eq!(pixel(&framebuffer, 0, 0), Color::RGB(128, 128, 128));
+
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    assert_eq!(pixel(&framebuffer, 0, 0), BLACK);
+}
+
+#[test]
+fn test_filter_next() {
+    assert_eq!(Filter::None.next(), Filter::Phosphor);
+    assert_eq!(Filter::Phosphor.next(), Filter::Blend);
+    assert_eq!(Filter::Blend.next(), Filter::None);
+}
+
+#[test]
+fn test_render_color_attributes() {
+    let (mut framebuffer, _) = create_framebuffer(Filter::None);
+    let mut memory = Memory::new(Platform::Chip8X);
+    memory.write_vram(0, 0, 1);
+    memory.write_vram(8, 0, 1);
+    memory.colors_mut().unwrap().set_foreground(1, 0, 4);
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+    assert_eq!(pixel(&framebuffer, 0, 0), CHIP8X_FOREGROUND[1]);
+    assert_eq!(pixel(&framebuffer, 8, 0), CHIP8X_FOREGROUND[4]);
+    assert_eq!(pixel(&framebuffer, 1, 0), CHIP8X_BACKGROUND[0]);
+}
+
+#[test]
+fn test_render_mega_chip() {
+    let (mut framebuffer, _) = create_framebuffer(Filter::Phosphor);
+    let mut memory = Memory::new(Platform::MegaChip);
+    let screen = memory.mega_chip_mut().unwrap();
+    screen.set_enabled(true);
+    screen.set_palette_color(1, Color::RGBA(0x10, 0x20, 0x30, 0xff));
+    screen.blend_pixel(4, 5, 1, BlendMode::Normal);
+    framebuffer.render(&mut memory, WIDTH_MEGA_CHIP, HEIGHT_MEGA_CHIP);
+    assert_eq!(pixel(&framebuffer, 4, 5), Color::RGB(0x10, 0x20, 0x30));
+    memory.mega_chip_mut().unwrap().set_alpha(0);
+    framebuffer.render(&mut memory, WIDTH_MEGA_CHIP, HEIGHT_MEGA_CHIP);
+    assert_eq!(pixel(&framebuffer, 4, 5), BLACK);
+}
+
+#[test]
+fn test_scaled() {
+    let (mut framebuffer, mut memory) = create_framebuffer(Filter::None);
+    memory.write_vram(1, 0, 1);
+    framebuffer.render(&mut memory, WIDTH_LO_RES, HEIGHT_LO_RES);
+
+    let pixels = framebuffer.scaled(WIDTH_LO_RES * 2, HEIGHT_LO_RES * 2);
+    assert_eq!(
+        pixels.len(),
+        WIDTH_LO_RES * HEIGHT_LO_RES * 4 * BYTES_PER_PIXEL
+    );
+    let row = WIDTH_LO_RES * 2 * BYTES_PER_PIXEL;
+    assert_eq!(&pixels[..2 * BYTES_PER_PIXEL], [0; 6]);
+    assert_eq!(&pixels[2 * BYTES_PER_PIXEL..4 * BYTES_PER_PIXEL], [255; 6]);
+    assert_eq!(
+        &pixels[row + 2 * BYTES_PER_PIXEL..row + 4 * BYTES_PER_PIXEL],
+        [255; 6]
+    );
+
+    // Lo-res frames fit the size of a hi-res recording.
+    assert_eq!(
+        framebuffer.scaled(WIDTH_HI_RES, HEIGHT_HI_RES).len(),
+        row * 2 * HEIGHT_LO_RES
+    );
+}
@@ -0,0 +1,498 @@
+#[cfg(test)]
+#[path = "./gdb_test.rs"]
+mod gdb_test;
+
+use crate::chip8::Chip8;
+use crate::debugger::{DebugServer, Debugger, StopReason};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+const INTERRUPT: u8 = 0x03;
+const PACKET_SIZE: usize = 0x4000;
+/// Register numbers after V0-VF.
+const REGISTER_I: usize = 16;
+const REGISTER_PC: usize = 17;
+const REGISTER_SP: usize = 18;
+const REGISTER_DT: usize = 19;
+const REGISTER_ST: usize = 20;
+const STACK_SIZE: u16 = 16;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" regnum="0"/>
+    <reg name="v1" bitsize="8"/>
+    <reg name="v2" bitsize="8"/>
+    <reg name="v3" bitsize="8"/>
+    <reg name="v4" bitsize="8"/>
+    <reg name="v5" bitsize="8"/>
+    <reg name="v6" bitsize="8"/>
+    <reg name="v7" bitsize="8"/>
+    <reg name="v8" bitsize="8"/>
+    <reg name="v9" bitsize="8"/>
+    <reg name="va" bitsize="8"/>
+    <reg name="vb" bitsize="8"/>
+    <reg name="vc" bitsize="8"/>
+    <reg name="vd" bitsize="8"/>
+    <reg name="ve" bitsize="8"/>
+    <reg name="vf" bitsize="8"/>
+    <reg name="i" bitsize="32" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="16"/>
+    <reg name="dt" bitsize="8"/>
+    <reg name="st" bitsize="8"/>
+  </feature>
+</target>
+"#;
+
+/// Sizes in bytes of the registers, in the order of the `g` packet.
+const REGISTER_SIZES: [usize; 21] = [
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 4, 2, 2, 1, 1,
+];
+
+/// Part of the byte stream received from GDB.
+#[derive(Debug, PartialEq)]
+pub enum Received {
+    Packet(String),
+    Interrupt,
+}
+
+/// Splits the received bytes into packets. Incomplete packets stay in the
+/// buffer; acknowledgements and packets with a bad checksum are dropped.
+pub fn parse_packets(buffer: &mut Vec<u8>) -> Vec<Received> {
+    let mut received = Vec::new();
+    let mut start = 0;
+
+    while start < buffer.len() {
+        match buffer[start] {
+            INTERRUPT => {
+                received.push(Received::Interrupt);
+                start += 1;
+            }
+            b'$' => {
+                let end = match buffer[start..].iter().position(|&byte| byte == b'#') {
+                    Some(end) if start + end + 2 < buffer.len() => start + end,
+                    _ => break,
+                };
+
+                let data = &buffer[start + 1..end];
+                let checksum = std::str::from_utf8(&buffer[end + 1..end + 3])
+                    .ok()
+                    .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+
+                if checksum == Some(compute_checksum(data)) {
+                    received.push(Received::Packet(String::from_utf8_lossy(data).into_owned()));
+                }
+
+                start = end + 3;
+            }
+            _ => start += 1,
+        }
+    }
+
+    buffer.drain(..start);
+    received
+}
+
+pub fn format_packet(data: &str) -> String {
+    format!("${}#{:02x}", data, compute_checksum(data.as_bytes()))
+}
+
+fn compute_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// GDB remote serial protocol stub on a local TCP port. V0-VF, I, PC, SP,
+/// DT and ST are exposed as registers and the RAM as the address space.
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    buffer: Vec<u8>,
+}
+
+impl GdbServer {
+    pub fn bind(port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|err| format!("failed to listen on port {}: {}", port, err))?;
+
+        Ok(GdbServer {
+            listener,
+            client: None,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answers a packet, or returns `None` if the reply is sent later, like
+    /// the stop reply of `c`.
+    pub fn handle(
+        &mut self,
+        packet: &str,
+        debugger: &mut Debugger,
+        chip8: &mut Chip8,
+    ) -> Option<String> {
+        // The protocol is ASCII, and slicing other packets could split a
+        // character.
+        if !packet.is_ascii() {
+            return Some("E01".to_string());
+        }
+
+        let (command, arguments) = packet.split_at(packet.len().min(1));
+
+        let reply = match command {
+            "?" => "S05".to_string(),
+            "g" => read_registers(chip8),
+            "G" => write_registers(chip8, arguments),
+            "p" => parse_hex(arguments)
+                .and_then(|register| read_register(chip8, register))
+                .unwrap_or_else(|err| err),
+            "P" => write_register(chip8, arguments),
+            "m" => read_memory(chip8, arguments),
+            "M" => write_memory(chip8, arguments),
+            "c" => {
+                debugger.resume();
+                return None;
+            }
+            "s" => stop_reply(&debugger.step(chip8, [false; 16])),
+            "Z" | "z" => self.set_breakpoint(command == "Z", arguments, debugger),
+            "H" | "T" => "OK".to_string(),
+            "D" => {
+                debugger.clear_breakpoints();
+                debugger.resume();
+                "OK".to_string()
+            }
+            "k" => {
+                debugger.clear_breakpoints();
+                debugger.stop();
+                chip8.halt("killed by the debugger".to_string());
+                self.client = None;
+                return None;
+            }
+            "q" => query(arguments),
+            _ => String::new(),
+        };
+
+        Some(reply)
+    }
+
+    fn set_breakpoint(&self, insert: bool, arguments: &str, debugger: &mut Debugger) -> String {
+        let fields: Vec<&str> = arguments.split(',').collect();
+
+        // Software and hardware breakpoints are the same here, watchpoints
+        // are not supported.
+        let address = match fields.as_slice() {
+            ["0", address, _] | ["1", address, _] => parse_hex(address),
+            _ => return String::new(),
+        };
+
+        match address {
+            Ok(address) if insert => debugger.add_breakpoint(address as u16),
+            Ok(address) => debugger.remove_breakpoint(address as u16),
+            Err(err) => return err,
+        }
+
+        "OK".to_string()
+    }
+
+    fn send(&mut self, reply: &str) -> Result<(), String> {
+        if let Some(client) = &mut self.client {
+            let packet = format_packet(reply);
+
+            if let Err(err) = client.write_all(packet.as_bytes()) {
+                self.client = None;
+                return Err(format!("failed to write to the debugger: {}", err));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn accept(&mut self, debugger: &mut Debugger) -> Result<(), String> {
+        match self.listener.accept() {
+            Ok((client, _)) => {
+                client
+                    .set_nonblocking(true)
+                    .and_then(|_| client.set_nodelay(true))
+                    .map_err(|err| format!("failed to set up the debugger connection: {}", err))?;
+
+                self.client = Some(client);
+                self.buffer.clear();
+                debugger.stop();
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(err) => Err(format!("failed to accept the debugger connection: {}", err)),
+        }
+    }
+
+    /// Reads the available bytes. Returns false once the client is gone.
+    fn receive(&mut self) -> bool {
+        let client = match &mut self.client {
+            Some(client) => client,
+            None => return false,
+        };
+
+        let mut data = [0; 1024];
+
+        loop {
+            match client.read(&mut data) {
+                Ok(0) => return false,
+                Ok(count) => self.buffer.extend_from_slice(&data[..count]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+impl DebugServer for GdbServer {
+    fn poll(&mut self, debugger: &mut Debugger, chip8: &mut Chip8) -> Result<(), String> {
+        if self.client.is_none() {
+            return self.accept(debugger);
+        }
+
+        if !self.receive() {
+            // The program continues without a debugger attached.
+            self.client = None;
+            debugger.clear_breakpoints();
+            debugger.resume();
+            return Ok(());
+        }
+
+        for received in parse_packets(&mut self.buffer) {
+            match received {
+                Received::Interrupt if debugger.running() => {
+                    debugger.stop();
+                    self.stopped(&StopReason::Interrupt, chip8)?;
+                }
+                Received::Interrupt => {}
+                Received::Packet(packet) => {
+                    if let Some(client) = &mut self.client {
+                        let _ = client.write_all(b"+");
+                    }
+
+                    if let Some(reply) = self.handle(&packet, debugger, chip8) {
+                        self.send(&reply)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stopped(&mut self, reason: &StopReason, _chip8: &mut Chip8) -> Result<(), String> {
+        self.send(&stop_reply(reason))
+    }
+}
+
+fn stop_reply(reason: &StopReason) -> String {
+    match reason {
+        StopReason::Breakpoint(_) | StopReason::Step => "S05".to_string(),
+        StopReason::Interrupt => "S02".to_string(),
+        StopReason::Error(_) => "S06".to_string(),
+    }
+}
+
+fn query(arguments: &str) -> String {
+    if arguments.starts_with("Supported") {
+        return format!("PacketSize={:x};qXfer:features:read+", PACKET_SIZE);
+    }
+
+    if let Some(range) = arguments.strip_prefix("Xfer:features:read:target.xml:") {
+        return read_target_xml(range);
+    }
+
+    match arguments {
+        "Attached" => "1".to_string(),
+        "C" => "QC1".to_string(),
+        "fThreadInfo" => "m1".to_string(),
+        "sThreadInfo" => "l".to_string(),
+        _ => String::new(),
+    }
+}
+
+fn read_target_xml(range: &str) -> String {
+    let (offset, length) = match parse_range(range, ',') {
+        Ok(range) => range,
+        Err(err) => return err,
+    };
+
+    let data = TARGET_XML
+        .get(offset.min(TARGET_XML.len())..)
+        .unwrap_or_default();
+
+    if data.len() <= length {
+        format!("l{}", data)
+    } else {
+        format!("m{}", &data[..length])
+    }
+}
+
+fn parse_hex(value: &str) -> Result<usize, String> {
+    usize::from_str_radix(value, 16).map_err(|_| "E01".to_string())
+}
+
+fn parse_range(value: &str, separator: char) -> Result<(usize, usize), String> {
+    let (address, length) = value.split_once(separator).ok_or("E01")?;
+    Ok((parse_hex(address)?, parse_hex(length)?))
+}
+
+fn encode(value: u32, size: usize) -> String {
+    value.to_le_bytes()[..size]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn decode(hex: &str) -> Result<u32, String> {
+    let bytes = decode_bytes(hex)?;
+
+    if bytes.len() > 4 {
+        return Err("E01".to_string());
+    }
+
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| value << 8 | byte as u32))
+}
+
+fn decode_bytes(hex: &str) -> Result<Vec<u8>, String> {
+    if !hex.len().is_multiple_of(2) {
+        return Err("E01".to_string());
+    }
+
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| "E01".to_string())
+        })
+        .collect()
+}
+
+fn register_value(chip8: &Chip8, register: usize) -> Option<u32> {
+    let state = chip8.state();
+
+    match register {
+        0..=15 => Some(state.v[register] as u32),
+        REGISTER_I => Some(state.i),
+        REGISTER_PC => Some(state.pc as u32),
+        REGISTER_SP => Some(state.sp as u32),
+        REGISTER_DT => Some(state.dt as u32),
+        REGISTER_ST => Some(state.st as u32),
+        _ => None,
+    }
+}
+
+fn set_register_value(chip8: &mut Chip8, register: usize, value: u32) -> Result<(), String> {
+    match register {
+        0..=15 => chip8.set_v(register, value as u8),
+        REGISTER_I if chip8.is_valid_i(value) => chip8.set_i(value),
+        REGISTER_PC if chip8.is_valid_pc(value) => chip8.set_pc(value as u16),
+        REGISTER_SP if value < STACK_SIZE as u32 => chip8.set_sp(value as u16),
+        REGISTER_DT => chip8.set_dt(value as u8),
+        REGISTER_ST => chip8.set_st(value as u8),
+        _ => return Err("E01".to_string()),
+    }
+
+    Ok(())
+}
+
+fn read_registers(chip8: &Chip8) -> String {
+    REGISTER_SIZES
+        .iter()
+        .enumerate()
+        .filter_map(|(register, &size)| {
+            register_value(chip8, register).map(|value| encode(value, size))
+        })
+        .collect()
+}
+
+fn write_registers(chip8: &mut Chip8, hex: &str) -> String {
+    let mut offset = 0;
+
+    for (register, &size) in REGISTER_SIZES.iter().enumerate() {
+        let value = hex
+            .get(offset..offset + size * 2)
+            .ok_or_else(|| "E01".to_string())
+            .and_then(decode)
+            .and_then(|value| set_register_value(chip8, register, value));
+
+        if let Err(err) = value {
+            return err;
+        }
+
+        offset += size * 2;
+    }
+
+    "OK".to_string()
+}
+
+fn read_register(chip8: &Chip8, register: usize) -> Result<String, String> {
+    let size = REGISTER_SIZES.get(register).ok_or("E01")?;
+    let value = register_value(chip8, register).ok_or("E01")?;
+    Ok(encode(value, *size))
+}
+
+fn write_register(chip8: &mut Chip8, arguments: &str) -> String {
+    let result = arguments
+        .split_once('=')
+        .ok_or_else(|| "E01".to_string())
+        .and_then(|(register, value)| Ok((parse_hex(register)?, decode(value)?)))
+        .and_then(|(register, value)| set_register_value(chip8, register, value));
+
+    match result {
+        Ok(()) => "OK".to_string(),
+        Err(err) => err,
+    }
+}
+
+fn read_memory(chip8: &mut Chip8, arguments: &str) -> String {
+    let (address, length) = match parse_range(arguments, ',') {
+        Ok(range) => range,
+        Err(err) => return err,
+    };
+
+    let memory = chip8.memory();
+
+    if address.saturating_add(length) > memory.size() {
+        return "E01".to_string();
+    }
+
+    (address..address + length)
+        .map(|address| format!("{:02x}", memory.read_byte(address)))
+        .collect()
+}
+
+fn write_memory(chip8: &mut Chip8, arguments: &str) -> String {
+    let result = arguments
+        .split_once(':')
+        .ok_or_else(|| "E01".to_string())
+        .and_then(|(range, data)| Ok((parse_range(range, ',')?, decode_bytes(data)?)));
+
+    let ((address, length), data) = match result {
+        Ok(result) => result,
+        Err(err) => return err,
+    };
+
+    let memory = chip8.memory();
+
+    if data.len() != length || address.saturating_add(length) > memory.size() {
+        return "E01".to_string();
+    }
+
+    for (offset, &byte) in data.iter().enumerate() {
+        memory.write_byte(address + offset, byte);
+    }
+
+    "OK".to_string()
+}
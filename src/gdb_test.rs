@@ -0,0 +1,333 @@
+use super::*;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use std::thread;
+use std::time::Duration;
+
+fn create_chip8() -> Chip8 {
+    let mut chip8 = Chip8::new(Platform::Chip8, Quirks::new(Platform::Chip8));
+    chip8.load(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+    chip8
+}
+
+fn create_server() -> GdbServer {
+    GdbServer::bind(0).unwrap()
+}
+
+#[test]
+fn test_parse_packets() {
+    let mut buffer = b"+$g#67\x03$m200,2#".to_vec();
+    let received = parse_packets(&mut buffer);
+    assert_eq!(
+        received,
+        vec![Received::Packet("g".to_string()), Received::Interrupt]
+    );
+    assert_eq!(buffer, b"$m200,2#".to_vec());
+}
+
+#[test]
+fn test_parse_packets_bad_checksum() {
+    let mut buffer = b"$g#00".to_vec();
+    assert!(parse_packets(&mut buffer).is_empty());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_format_packet() {
+    assert_eq!(format_packet("OK"), "$OK#9a");
+}
+
+#[test]
+fn test_registers() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    chip8.set_v(0xa, 0x42);
+    chip8.set_i(0x0300);
+
+    let registers = server.handle("g", &mut debugger, &mut chip8).unwrap();
+    assert_eq!(&registers[20..22], "42");
+    assert_eq!(&registers[32..48], "0003000000020000");
+    assert_eq!(
+        server.handle("p11", &mut debugger, &mut chip8).unwrap(),
+        "0002"
+    );
+    assert_eq!(
+        server
+            .handle("P11=0402", &mut debugger, &mut chip8)
+            .unwrap(),
+        "OK"
+    );
+    assert_eq!(chip8.state().pc, 0x204);
+    assert_eq!(
+        server
+            .handle("P12=1100", &mut debugger, &mut chip8)
+            .unwrap(),
+        "E01"
+    );
+    assert_eq!(
+        server.handle("p30", &mut debugger, &mut chip8).unwrap(),
+        "E01"
+    );
+}
+
+#[test]
+fn test_write_registers() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let registers = server.handle("g", &mut debugger, &mut chip8).unwrap();
+    let registers = format!("ff{}", &registers[2..]);
+    assert_eq!(
+        server
+            .handle(&format!("G{}", registers), &mut debugger, &mut chip8)
+            .unwrap(),
+        "OK"
+    );
+    assert_eq!(chip8.v(0), 0xff);
+    assert_eq!(
+        server.handle("G00", &mut debugger, &mut chip8).unwrap(),
+        "E01"
+    );
+}
+
+#[test]
+fn test_write_register_bounds() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let mut write = |packet: &str| server.handle(packet, &mut debugger, &mut chip8).unwrap();
+
+    // Registers are little-endian: PC 0x8000 is past the 32K of memory.
+    assert_eq!(write("P11=0080"), "E01");
+    assert_eq!(write("P11=ff7f"), "E01");
+    assert_eq!(write("P11=fe7f"), "OK");
+    assert_eq!(write("P10=00800000"), "E01");
+    assert_eq!(write("P10=ff7f0000"), "OK");
+    assert_eq!(write("P12=1000"), "E01");
+    assert_eq!(write("P12=0f00"), "OK");
+    assert_eq!(chip8.state().pc, 0x7ffe);
+    assert_eq!(chip8.i(), 0x7fff);
+    assert_eq!(chip8.state().sp, 15);
+}
+
+#[test]
+fn test_memory() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    assert_eq!(
+        server.handle("m200,4", &mut debugger, &mut chip8).unwrap(),
+        "60017001"
+    );
+    assert_eq!(
+        server
+            .handle("M300,2:abcd", &mut debugger, &mut chip8)
+            .unwrap(),
+        "OK"
+    );
+    assert_eq!(chip8.memory().read_byte(0x301), 0xcd);
+    assert_eq!(
+        server
+            .handle("M300,3:abcd", &mut debugger, &mut chip8)
+            .unwrap(),
+        "E01"
+    );
+    assert_eq!(
+        server.handle("m7fff,2", &mut debugger, &mut chip8).unwrap(),
+        "E01"
+    );
+}
+
+#[test]
+fn test_breakpoints_and_continue() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    assert_eq!(
+        server
+            .handle("Z0,204,2", &mut debugger, &mut chip8)
+            .unwrap(),
+        "OK"
+    );
+    assert_eq!(debugger.breakpoints().collect::<Vec<_>>(), vec![0x204]);
+    assert_eq!(server.handle("c", &mut debugger, &mut chip8), None);
+    assert!(debugger.running());
+    assert_eq!(
+        server
+            .handle("z0,204,2", &mut debugger, &mut chip8)
+            .unwrap(),
+        "OK"
+    );
+    assert_eq!(debugger.breakpoints().count(), 0);
+    assert_eq!(
+        server
+            .handle("Z2,300,1", &mut debugger, &mut chip8)
+            .unwrap(),
+        ""
+    );
+}
+
+#[test]
+fn test_step() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    assert_eq!(
+        server.handle("s", &mut debugger, &mut chip8).unwrap(),
+        "S05"
+    );
+    assert_eq!(chip8.state().pc, 0x202);
+}
+
+#[test]
+fn test_non_ascii_packets() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    assert_eq!(
+        server.handle("é", &mut debugger, &mut chip8).unwrap(),
+        "E01"
+    );
+    assert_eq!(
+        server
+            .handle("M300,2:é0a", &mut debugger, &mut chip8)
+            .unwrap(),
+        "E01"
+    );
+    assert_eq!(chip8.memory().read_byte(0x300), 0);
+    assert!(decode_bytes("é0a").is_err());
+}
+
+#[test]
+fn test_kill() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    debugger.add_breakpoint(0x204);
+    debugger.resume();
+    assert_eq!(server.handle("k", &mut debugger, &mut chip8), None);
+    assert!(!debugger.running());
+    assert_eq!(debugger.breakpoints().count(), 0);
+    assert_eq!(chip8.error(), Some("killed by the debugger"));
+}
+
+#[test]
+fn test_target_xml() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let supported = server.handle("qSupported:xmlRegisters=i386", &mut debugger, &mut chip8);
+    assert!(supported.unwrap().contains("qXfer:features:read+"));
+    let start = server.handle(
+        "qXfer:features:read:target.xml:0,10",
+        &mut debugger,
+        &mut chip8,
+    );
+    assert_eq!(start.unwrap(), format!("m{}", &TARGET_XML[..16]));
+    let end = server.handle(
+        "qXfer:features:read:target.xml:10,ffff",
+        &mut debugger,
+        &mut chip8,
+    );
+    assert_eq!(end.unwrap(), format!("l{}", &TARGET_XML[16..]));
+}
+
+/// Sends a packet and polls the server until the reply arrives.
+fn request(
+    client: &mut TcpStream,
+    server: &mut GdbServer,
+    debugger: &mut Debugger,
+    chip8: &mut Chip8,
+    packet: &str,
+) -> String {
+    client.write_all(format_packet(packet).as_bytes()).unwrap();
+    read_reply(client, server, debugger, chip8)
+}
+
+fn read_reply(
+    client: &mut TcpStream,
+    server: &mut GdbServer,
+    debugger: &mut Debugger,
+    chip8: &mut Chip8,
+) -> String {
+    let mut buffer = Vec::new();
+
+    for _ in 0..500 {
+        server.poll(debugger, chip8).unwrap();
+
+        if let Some(reason) = debugger.run_frame(chip8, [false; 16], 10) {
+            server.stopped(&reason, chip8).unwrap();
+        }
+
+        let mut data = [0; 256];
+
+        if let Ok(count) = client.read(&mut data) {
+            buffer.extend_from_slice(&data[..count]);
+        }
+
+        if let Some(Received::Packet(reply)) = parse_packets(&mut buffer).pop() {
+            return reply;
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    panic!("no reply to the request");
+}
+
+#[test]
+fn test_scripted_session() {
+    let mut chip8 = create_chip8();
+    let mut debugger = Debugger::new();
+    let mut server = create_server();
+    let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_millis(1)))
+        .unwrap();
+
+    assert_eq!(
+        request(&mut client, &mut server, &mut debugger, &mut chip8, "?"),
+        "S05"
+    );
+    assert_eq!(
+        request(
+            &mut client,
+            &mut server,
+            &mut debugger,
+            &mut chip8,
+            "Z0,204,2"
+        ),
+        "OK"
+    );
+    assert_eq!(
+        request(&mut client, &mut server, &mut debugger, &mut chip8, "c"),
+        "S05"
+    );
+    assert_eq!(
+        request(&mut client, &mut server, &mut debugger, &mut chip8, "p11"),
+        "0402"
+    );
+    assert_eq!(
+        request(&mut client, &mut server, &mut debugger, &mut chip8, "p0"),
+        "02"
+    );
+    assert_eq!(
+        request(
+            &mut client,
+            &mut server,
+            &mut debugger,
+            &mut chip8,
+            "z0,204,2"
+        ),
+        "OK"
+    );
+
+    client.write_all(format_packet("c").as_bytes()).unwrap();
+    client.write_all(&[INTERRUPT]).unwrap();
+    assert_eq!(
+        read_reply(&mut client, &mut server, &mut debugger, &mut chip8),
+        "S02"
+    );
+    assert!(!debugger.running());
+}
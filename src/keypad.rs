@@ -1,23 +1,77 @@
-use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::controller::{Button, GameController};
+use sdl2::{event::Event, keyboard::Keycode, GameControllerSubsystem};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    ToggleFullscreen,
+    NextFilter,
+    ToggleOverlay,
+    TogglePause,
+    AdvanceFrame,
+    ToggleFastForward,
+    ToggleSlowMotion,
+    Reset,
+    IncreaseSpeed,
+    DecreaseSpeed,
+    Screenshot,
+    ToggleRecording,
+    /// A ROM file was dropped onto the window.
+    OpenRom(String),
+    /// Leaves the running ROM or the menu.
+    Back,
+    Up,
+    Down,
+    Select,
+}
+
+pub struct Input {
+    pub keys: [bool; 16],
+    /// Keys of the second CHIP-8X keypad, mapped to the numeric keypad.
+    pub second_keys: [bool; 16],
+    pub actions: Vec<Action>,
+}
 
 pub struct Keypad {
     event_pump: sdl2::EventPump,
+    controller_subsystem: GameControllerSubsystem,
+    /// Connected gamepads, which only send events while they are open.
+    controllers: Vec<GameController>,
 }
 
 impl Keypad {
     pub fn new(sdl_context: &sdl2::Sdl) -> Result<Self, String> {
         let event_pump = sdl_context.event_pump()?;
-        Ok(Keypad { event_pump })
+        let controller_subsystem = sdl_context.game_controller()?;
+        Ok(Keypad {
+            event_pump,
+            controller_subsystem,
+            controllers: Vec::new(),
+        })
     }
 
-    pub fn poll(&mut self) -> Result<[bool; 16], ()> {
+    /// Returns the pressed keys and hotkey actions, or `None` once the
+    /// window is closed.
+    pub fn poll(&mut self) -> Option<Input> {
+        let mut actions = Vec::new();
+
         for event in self.event_pump.poll_iter() {
             match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
+                Event::Quit { .. } => return None,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
                     ..
-                } => return Err(()),
+                } => actions.extend(hotkey(keycode)),
+                Event::DropFile { filename, .. } => actions.push(Action::OpenRom(filename)),
+                Event::ControllerDeviceAdded { which, .. } => {
+                    if let Ok(controller) = self.controller_subsystem.open(which) {
+                        self.controllers.push(controller);
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => self
+                    .controllers
+                    .retain(|controller| controller.instance_id() != which),
+                Event::ControllerButtonDown { button, .. } => actions.extend(gamepad(button)),
                 _ => {}
             }
         }
@@ -30,8 +84,9 @@ impl Keypad {
             .collect();
 
         let mut chip8_keys = [false; 16];
+        let mut second_keys = [false; 16];
 
-        for key in keys {
+        for &key in &keys {
             let index = match key {
                 Keycode::Num1 => Some(0x1),
                 Keycode::Num2 => Some(0x2),
@@ -55,8 +110,70 @@ impl Keypad {
             if let Some(i) = index {
                 chip8_keys[i] = true;
             }
+
+            let index = match key {
+                Keycode::Kp7 => Some(0x1),
+                Keycode::Kp8 => Some(0x2),
+                Keycode::Kp9 => Some(0x3),
+                Keycode::KpDivide => Some(0xc),
+                Keycode::Kp4 => Some(0x4),
+                Keycode::Kp5 => Some(0x5),
+                Keycode::Kp6 => Some(0x6),
+                Keycode::KpMultiply => Some(0xd),
+                Keycode::Kp1 => Some(0x7),
+                Keycode::Kp2 => Some(0x8),
+                Keycode::Kp3 => Some(0x9),
+                Keycode::KpMinus => Some(0xe),
+                Keycode::Kp0 => Some(0xa),
+                Keycode::KpPeriod => Some(0x0),
+                Keycode::KpEnter => Some(0xb),
+                Keycode::KpPlus => Some(0xf),
+                _ => None,
+            };
+
+            if let Some(i) = index {
+                second_keys[i] = true;
+            }
         }
 
-        Ok(chip8_keys)
+        Some(Input {
+            keys: chip8_keys,
+            second_keys,
+            actions,
+        })
+    }
+}
+
+/// Maps the keys outside the CHIP-8 keypad layout to hotkey actions.
+fn hotkey(keycode: Keycode) -> Option<Action> {
+    match keycode {
+        Keycode::F11 => Some(Action::ToggleFullscreen),
+        Keycode::F1 => Some(Action::ToggleOverlay),
+        Keycode::F2 => Some(Action::NextFilter),
+        Keycode::P => Some(Action::TogglePause),
+        Keycode::N => Some(Action::AdvanceFrame),
+        Keycode::Tab => Some(Action::ToggleFastForward),
+        Keycode::L => Some(Action::ToggleSlowMotion),
+        Keycode::F5 => Some(Action::Reset),
+        Keycode::Equals => Some(Action::IncreaseSpeed),
+        Keycode::Minus => Some(Action::DecreaseSpeed),
+        Keycode::F12 => Some(Action::Screenshot),
+        Keycode::F10 => Some(Action::ToggleRecording),
+        Keycode::Escape => Some(Action::Back),
+        Keycode::Up => Some(Action::Up),
+        Keycode::Down => Some(Action::Down),
+        Keycode::Return => Some(Action::Select),
+        _ => None,
+    }
+}
+
+/// Maps gamepad buttons to the menu actions.
+fn gamepad(button: Button) -> Option<Action> {
+    match button {
+        Button::DPadUp => Some(Action::Up),
+        Button::DPadDown => Some(Action::Down),
+        Button::A | Button::Start => Some(Action::Select),
+        Button::B | Button::Back => Some(Action::Back),
+        _ => None,
     }
 }
@@ -0,0 +1,27 @@
+extern crate sdl2;
+
+pub mod audio;
+pub mod capture;
+pub mod cartridge;
+pub mod chip8;
+pub mod controls;
+pub mod dap;
+pub mod database;
+pub mod debugger;
+pub mod disassembler;
+pub mod framebuffer;
+pub mod gdb;
+pub mod keypad;
+pub mod memory;
+pub mod menu;
+pub mod monitor;
+pub mod octo;
+pub mod overlay;
+pub mod palette;
+pub mod platform;
+pub mod profiler;
+pub mod quirks;
+pub mod speaker;
+pub mod symbols;
+pub mod trace;
+pub mod window;
@@ -1,59 +1,739 @@
 extern crate sdl2;
 
-mod cartridge;
-mod chip8;
-mod keypad;
-mod memory;
-mod window;
-
-use cartridge::Cartridge;
-use chip8::Chip8;
-use keypad::Keypad;
+mod options;
+
+use chip8_interpreter::audio::{Synthesizer, WavRecorder, SAMPLES_PER_FRAME};
+use chip8_interpreter::capture::{self, Recorder};
+use chip8_interpreter::cartridge::{open_cartridge, Cartridge};
+use chip8_interpreter::chip8::{Chip8, FRAME_RATE};
+use chip8_interpreter::controls::{Controls, Speed};
+use chip8_interpreter::dap::DapServer;
+use chip8_interpreter::database::RomDatabase;
+use chip8_interpreter::debugger::{DebugServer, Debugger};
+use chip8_interpreter::framebuffer::Framebuffer;
+use chip8_interpreter::gdb::GdbServer;
+use chip8_interpreter::keypad::{Action, Keypad};
+use chip8_interpreter::menu::Menu;
+use chip8_interpreter::monitor::Monitor;
+use chip8_interpreter::overlay::Overlay;
+use chip8_interpreter::platform::Platform;
+use chip8_interpreter::profiler::Profiler;
+use chip8_interpreter::quirks::Quirks;
+use chip8_interpreter::speaker::Speaker;
+use chip8_interpreter::trace::{Tracer, Verifier};
+use chip8_interpreter::window::{self, Window};
+use options::Options;
+use std::cell::RefCell;
 use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+use std::rc::Rc;
 use std::thread;
-use std::time::Duration;
-use window::Window;
+use std::time::{Duration, Instant};
 
-pub const CHIP8_CPU_FREQUENCY: u64 = 500;
-pub const CHIP8_CPU_FREQUENCY_SUPER_CHIP: u64 = 4000;
+/// Frames between redraws of the memory monitor.
+const MONITOR_REFRESH_FRAMES: u64 = 15;
+/// Frames between checks whether the ROM changed on disk.
+const WATCH_INTERVAL_FRAMES: u64 = 30;
+/// ROMs shown at once in the menu.
+const MENU_ROWS: usize = 12;
+/// Seed of the random numbers when tracing or verifying without `--seed`,
+/// so that traces can be verified.
+const TRACE_SEED: u64 = 0;
 
 fn main() {
-    let sdl_context = sdl2::init().unwrap();
     let args: Vec<String> = env::args().collect();
-    let cartridge_filename = &args[1];
-    let super_chip = is_super_chip_enabled();
-    let sleep_duration = get_sleep_duration(super_chip);
-    let cartridge = Cartridge::new(cartridge_filename);
-    let mut display = Window::new(&sdl_context);
+
+    let options = Options::parse(&args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+
+    let frame_duration = Duration::from_nanos(1_000_000_000 / FRAME_RATE);
+    let database = match &options.database {
+        Some(path) => RomDatabase::load(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        }),
+        None => RomDatabase::new(),
+    };
+
+    let mut menu = options.menu.as_ref().map(|directory| {
+        Menu::scan(directory, &database).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    });
+
+    let mut chip8 = Chip8::new(options.platform, options.quirks);
+    chip8.set_machine_code_policy(options.machine_code_policy);
+
+    let tracing = options.trace.is_some() || options.verify.is_some();
+
+    if let Some(seed) = options.seed.or(tracing.then_some(TRACE_SEED)) {
+        chip8.set_seed(seed);
+    }
+    let mut framebuffer = Framebuffer::new(options.palette, options.filter);
+    let mut controls = Controls::new(options.platform.cpu_frequency() / FRAME_RATE);
+    let mut cartridge = Cartridge::default();
+    let mut in_menu = true;
+
+    if let Some(path) = &options.cartridge {
+        let archive = Menu::archive(path, &database).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        });
+
+        // An archive with several ROMs offers a selection.
+        if archive.entries().len() > 1 && options.verify.is_none() && !options.headless {
+            menu = Some(archive);
+        } else {
+            cartridge =
+                open_cartridge(path, None, options.symbols.as_deref()).unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                });
+            start(
+                &mut chip8,
+                &mut controls,
+                &mut framebuffer,
+                &cartridge,
+                None,
+                &options,
+            );
+            in_menu = false;
+        }
+    }
+
+    if let Some(path) = &options.verify {
+        let (platform, ticks_per_frame) = (chip8.platform(), controls.ticks_per_frame());
+        verify(chip8, path, platform, ticks_per_frame);
+    }
+
+    let tracer = options.trace.as_ref().map(|path| {
+        let format = options.trace_format;
+        let mut tracer =
+            Tracer::create(path, options.trace_filter.clone(), format, options.platform)
+                .unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                });
+
+        tracer.set_symbols(cartridge.symbols.clone());
+        let tracer = Rc::new(RefCell::new(tracer));
+        let hook_tracer = Rc::clone(&tracer);
+        chip8.add_trace_hook(Box::new(move |entry| {
+            hook_tracer
+                .borrow_mut()
+                .record(entry)
+                .map_err(|err| format!("failed to write trace: {}", err))
+        }));
+        tracer
+    });
+
+    let profiler = options.profile.as_ref().map(|_| {
+        let mut profiler = Profiler::new(options.platform);
+        profiler.set_symbols(cartridge.symbols.clone());
+        let profiler = Rc::new(RefCell::new(profiler));
+        let hook_profiler = Rc::clone(&profiler);
+        chip8.add_trace_hook(Box::new(move |entry| {
+            hook_profiler.borrow_mut().record(entry);
+            Ok(())
+        }));
+        profiler
+    });
+
+    let monitor = if options.monitor {
+        let monitor = Rc::new(RefCell::new(Monitor::new()));
+        let hook_monitor = Rc::clone(&monitor);
+        chip8.add_trace_hook(Box::new(move |entry| {
+            hook_monitor.borrow_mut().record_writes(&entry.writes);
+            Ok(())
+        }));
+        Some((monitor, Monitor::spawn_input()))
+    } else {
+        None
+    };
+
+    let mut debugger = Debugger::new();
+    let mut debug_server: Option<Box<dyn DebugServer>> = options.gdb_port.map(|port| {
+        let server = GdbServer::bind(port).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        });
+
+        println!("waiting for GDB on port {}", port);
+        Box::new(server) as Box<dyn DebugServer>
+    });
+
+    if let Some(port) = options.dap_port {
+        let mut server = DapServer::bind(port).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        });
+
+        server.set_symbols(cartridge.symbols.clone());
+
+        println!("waiting for a DAP client on port {}", port);
+        debug_server = Some(Box::new(server));
+    }
+
+    let mut recorder = options.record.as_ref().map(|path| {
+        create_recorder(path, &framebuffer, options.scale).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    });
+
+    let mut wav = options.wav.as_ref().map(|path| {
+        WavRecorder::create(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    });
+
+    if options.headless {
+        let result = run_headless(
+            &mut chip8,
+            &mut framebuffer,
+            &controls,
+            &mut recorder,
+            &mut wav,
+            &profiler,
+            options.frames.unwrap_or_default(),
+        );
+        // Dropping the hooks flushes the trace file.
+        chip8.clear_trace_hooks();
+        finish(&framebuffer, recorder, wav, &profiler, &options);
+
+        if let Err(err) = result {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+
+        return;
+    }
+
+    let sdl_context = sdl2::init().unwrap();
+    let canvas = window::create_canvas(&sdl_context, options.scale);
+    let texture_creator = canvas.texture_creator();
+    let mut display = Window::new(canvas, &texture_creator, options.integer_scale);
     let mut keypad = Keypad::new(&sdl_context).unwrap();
-    let mut chip8 = Chip8::new(super_chip);
+    let mut speaker = Speaker::new(&sdl_context)
+        .map_err(|err| eprintln!("warning: no sound: {}", err))
+        .ok();
+    let mut synthesizer = Synthesizer::new();
+
+    let mut overlay = Overlay::new();
+    let mut monitor_message = String::new();
+    let mut frames: u64 = 0;
+    let mut menu_message = None;
+
+    'running: while let Some(input) = keypad.poll() {
+        let frame_start = Instant::now();
+
+        if let (true, Some(menu)) = (in_menu, &mut menu) {
+            for action in input.actions {
+                match action {
+                    Action::ToggleFullscreen => display.toggle_fullscreen(),
+                    Action::Up => menu.move_selection(-1),
+                    Action::Down => menu.move_selection(1),
+                    Action::Back => break 'running,
+                    Action::Select => {
+                        let entry = match menu.selected() {
+                            Some(entry) => entry,
+                            None => continue,
+                        };
+
+                        match open_cartridge(&entry.path, entry.entry.as_deref(), None) {
+                            Ok(opened) => {
+                                cartridge = opened;
+                                start(
+                                    &mut chip8,
+                                    &mut controls,
+                                    &mut framebuffer,
+                                    &cartridge,
+                                    entry.platform,
+                                    &options,
+                                );
+                                set_symbols(&cartridge, &tracer, &profiler, &mut debug_server);
+                                overlay.notify(&format!("Loaded {}", entry.title), frame_start);
+                                menu_message = None;
+                                in_menu = false;
+                            }
+                            Err(err) => menu_message = Some(err),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            let mut lines = menu.lines(MENU_ROWS);
+            lines.extend(menu_message.clone());
+            display.draw_menu(&lines, MENU_ROWS + 2);
+            sleep_until(frame_start + frame_duration);
+            continue;
+        }
+
+        frames += 1;
+
+        if let Some((monitor, commands)) = &monitor {
+            for command in commands.try_iter() {
+                monitor_message = match monitor.borrow_mut().execute(&command, &mut chip8) {
+                    Ok(message) => message,
+                    Err(err) => err,
+                };
+            }
+        }
+
+        let paused = monitor
+            .as_ref()
+            .is_some_and(|(monitor, _)| monitor.borrow().paused());
+
+        for action in input.actions {
+            match action {
+                Action::ToggleFullscreen => display.toggle_fullscreen(),
+                Action::NextFilter => framebuffer.set_filter(framebuffer.filter().next()),
+                Action::ToggleOverlay => overlay.toggle(),
+                Action::Screenshot => {
+                    let path = capture::next_file_name("screenshot", "png");
+                    let message = match capture::write_png(&framebuffer, options.scale, &path) {
+                        Ok(()) => format!("Saved {}", path),
+                        Err(err) => err,
+                    };
+
+                    overlay.notify(&message, frame_start);
+                }
+                Action::ToggleRecording => {
+                    let message = match recorder.take() {
+                        Some(recording) => {
+                            let path = recording.path().to_string();
+                            recording.finish().map(|()| format!("Saved {}", path))
+                        }
+                        None => {
+                            let path = capture::next_file_name("recording", "gif");
+                            create_recorder(&path, &framebuffer, options.scale).map(|recording| {
+                                recorder = Some(recording);
+                                format!("Recording {}", path)
+                            })
+                        }
+                    };
+
+                    overlay.notify(&message.unwrap_or_else(|err| err), frame_start);
+                }
+                Action::Back if menu.is_some() => in_menu = true,
+                Action::Back => break 'running,
+                Action::Reset => {
+                    let message = match cartridge.reload() {
+                        Ok(()) => "Reset".to_string(),
+                        Err(err) => err,
+                    };
+
+                    restart(&mut chip8, &mut controls, &mut framebuffer, &cartridge);
+                    set_symbols(&cartridge, &tracer, &profiler, &mut debug_server);
+                    overlay.notify(&message, frame_start);
+                }
+                Action::OpenRom(path) => match Menu::archive(&path, &database) {
+                    Ok(archive) if archive.entries().len() > 1 => {
+                        menu = Some(archive);
+                        in_menu = true;
+                    }
+                    _ => match open_cartridge(&path, None, None) {
+                        Ok(opened) => {
+                            cartridge = opened;
+                            start(
+                                &mut chip8,
+                                &mut controls,
+                                &mut framebuffer,
+                                &cartridge,
+                                None,
+                                &options,
+                            );
+                            set_symbols(&cartridge, &tracer, &profiler, &mut debug_server);
+                            overlay.notify(&format!("Loaded {}", cartridge.name()), frame_start);
+                        }
+                        Err(err) => overlay.notify(&err, frame_start),
+                    },
+                },
+                _ => {
+                    if let Some(message) = controls.apply(action) {
+                        overlay.notify(&message, frame_start);
+                    }
+                }
+            }
+        }
+
+        if frames.is_multiple_of(WATCH_INTERVAL_FRAMES) && cartridge.changed() {
+            let message = match cartridge.reload() {
+                Ok(()) => {
+                    restart(&mut chip8, &mut controls, &mut framebuffer, &cartridge);
+                    set_symbols(&cartridge, &tracer, &profiler, &mut debug_server);
+                    format!("Reloaded {}", cartridge.path())
+                }
+                Err(err) => err,
+            };
+
+            overlay.notify(&message, frame_start);
+        }
+
+        chip8.set_second_keypad(input.second_keys);
+
+        if let Some(server) = &mut debug_server {
+            if let Err(err) = server.poll(&mut debugger, &mut chip8) {
+                eprintln!("{}", err);
+            }
+        }
+
+        if let Some(program) = debug_server
+            .as_mut()
+            .and_then(|server| server.take_program())
+        {
+            cartridge = program;
+            start(
+                &mut chip8,
+                &mut controls,
+                &mut framebuffer,
+                &cartridge,
+                None,
+                &options,
+            );
+            set_symbols(&cartridge, &tracer, &profiler, &mut debug_server);
+            overlay.notify(&format!("Loaded {}", cartridge.name()), frame_start);
+        }
+
+        let output = if let Some(server) = &mut debug_server {
+            if let Some(reason) =
+                debugger.run_frame(&mut chip8, input.keys, controls.ticks_per_frame())
+            {
+                if let Err(err) = server.stopped(&reason, &mut chip8) {
+                    eprintln!("{}", err);
+                }
+            }
+
+            chip8.output_state()
+        } else if paused || !controls.next_frame() {
+            chip8.output_state()
+        } else {
+            let output = chip8.run_frame(input.keys, controls.ticks_per_frame());
+
+            if let Some(profiler) = &profiler {
+                profiler.borrow_mut().end_frame();
+            }
+
+            output
+        };
+
+        let beep = output.beep;
+        framebuffer.render(output.memory, output.width, output.height);
+
+        if let Some(Err(err)) = recorder
+            .as_mut()
+            .map(|recording| recording.record(&framebuffer))
+        {
+            overlay.notify(&err, frame_start);
+            recorder = None;
+        }
+
+        let stopped =
+            paused || controls.paused() || (debug_server.is_some() && !debugger.running());
+        let samples = if stopped {
+            vec![0; SAMPLES_PER_FRAME]
+        } else {
+            synthesizer.frame(beep, chip8.sound())
+        };
+
+        if let Some(Err(err)) = speaker.as_mut().map(|speaker| speaker.play(&samples)) {
+            overlay.notify(&err, frame_start);
+            speaker = None;
+        }
+
+        if let Some(Err(err)) = wav.as_mut().map(|wav| wav.record(&samples)) {
+            overlay.notify(&err, frame_start);
+            wav = None;
+        }
+        overlay.end_frame(frame_start, chip8.cycles());
+        display.draw(
+            &framebuffer,
+            &overlay.lines(&status(&chip8, &controls, stopped)),
+        );
+
+        if let Some((monitor, _)) = &monitor {
+            let mut monitor = monitor.borrow_mut();
+
+            if frames.is_multiple_of(MONITOR_REFRESH_FRAMES) {
+                let panel = monitor.render(&mut chip8);
+                print!("\x1b[2J\x1b[H{}\n{}\n> ", panel, monitor_message);
+                let _ = io::stdout().flush();
+            }
+
+            monitor.end_frame();
+        }
+
+        print_warnings(&mut chip8);
+
+        // A debugger keeps the halted program around for inspection.
+        if let Some(error) = chip8.error().filter(|_| debug_server.is_none()) {
+            eprintln!("error: {}", error);
+            // Dropping the hook flushes the trace file.
+            chip8.clear_trace_hooks();
+            finish(&framebuffer, recorder, wav, &profiler, &options);
+            process::exit(1);
+        }
+
+        if options.frames.is_some_and(|limit| frames >= limit) {
+            break;
+        }
+
+        if let Some(duration) = controls.frame_duration(frame_duration) {
+            sleep_until(frame_start + duration);
+        }
+    }
+
+    finish(&framebuffer, recorder, wav, &profiler, &options);
+}
+
+/// Runs a newly opened cartridge with the settings of an Octo cartridge,
+/// else on `platform`, e.g. from the ROM database, or the one of the
+/// options.
+fn start(
+    chip8: &mut Chip8,
+    controls: &mut Controls,
+    framebuffer: &mut Framebuffer,
+    cartridge: &Cartridge,
+    platform: Option<Platform>,
+    options: &Options,
+) {
+    let settings = cartridge.options.as_ref();
+    let (platform, quirks) = match (settings, platform) {
+        (Some(settings), _) => (settings.platform, settings.quirks),
+        // Quirks given on the command line are meant for their platform only.
+        (None, Some(platform)) if platform != options.platform => (platform, Quirks::new(platform)),
+        _ => (options.platform, options.quirks),
+    };
+
+    chip8.set_platform(platform, quirks);
     chip8.load(&cartridge.rom);
+    *controls = Controls::new(
+        settings
+            .and_then(|settings| settings.ticks_per_frame)
+            .unwrap_or(platform.cpu_frequency() / FRAME_RATE),
+    );
+    framebuffer.set_palette(
+        settings
+            .and_then(|settings| settings.palette)
+            .unwrap_or(options.palette),
+    );
+}
+
+/// Hands the labels of a newly loaded ROM to the tools that show them.
+fn set_symbols(
+    cartridge: &Cartridge,
+    tracer: &Option<Rc<RefCell<Tracer<BufWriter<File>>>>>,
+    profiler: &Option<Rc<RefCell<Profiler>>>,
+    debug_server: &mut Option<Box<dyn DebugServer>>,
+) {
+    if let Some(tracer) = tracer {
+        tracer.borrow_mut().set_symbols(cartridge.symbols.clone());
+    }
+
+    if let Some(profiler) = profiler {
+        profiler.borrow_mut().set_symbols(cartridge.symbols.clone());
+    }
+
+    if let Some(server) = debug_server {
+        server.set_symbols(cartridge.symbols.clone());
+    }
+}
+
+/// Runs the ROM of `cartridge` on a freshly initialized machine, applying
+/// the settings of an Octo cartridge again as they may have changed.
+fn restart(
+    chip8: &mut Chip8,
+    controls: &mut Controls,
+    framebuffer: &mut Framebuffer,
+    cartridge: &Cartridge,
+) {
+    match &cartridge.options {
+        Some(settings) => {
+            chip8.set_platform(settings.platform, settings.quirks);
 
-    while let Ok(keypad) = keypad.poll() {
-        let output = chip8.tick(keypad);
+            if let Some(ticks_per_frame) = settings.ticks_per_frame {
+                controls.set_ticks_per_frame(ticks_per_frame);
+            }
 
-        if output.draw_flag {
-            display.draw(output.memory, output.hi_res);
+            if let Some(palette) = settings.palette {
+                framebuffer.set_palette(palette);
+            }
         }
+        None => chip8.reset(),
+    }
+
+    chip8.load(&cartridge.rom);
+}
 
-        thread::sleep(sleep_duration);
+/// Describes the platform, quirks and speed for the overlay.
+fn status(chip8: &Chip8, controls: &Controls, paused: bool) -> Vec<String> {
+    let quirks = chip8.quirks().enabled();
+    let mut lines = vec![
+        format!(
+            "{}  {} IPF",
+            chip8.platform().name(),
+            controls.ticks_per_frame()
+        ),
+        format!(
+            "Quirks: {}",
+            if quirks.is_empty() {
+                "none".to_string()
+            } else {
+                quirks.join(", ")
+            }
+        ),
+    ];
+
+    match controls.speed() {
+        Speed::Normal => {}
+        Speed::FastForward => lines.push("Fast forward".to_string()),
+        Speed::SlowMotion => lines.push("Slow motion".to_string()),
+    }
+
+    if paused {
+        lines.push("Paused".to_string());
     }
+
+    lines
 }
 
-fn is_super_chip_enabled() -> bool {
-    let args: Vec<String> = env::args().collect();
+fn sleep_until(deadline: Instant) {
+    if let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        thread::sleep(remaining);
+    }
+}
 
-    if args.len() < 3 {
-        return false;
+fn print_warnings(chip8: &mut Chip8) {
+    for warning in chip8.take_warnings() {
+        eprintln!("warning: {}", warning);
     }
+}
+
+/// Runs the ROM for a number of frames without a window or input.
+fn run_headless(
+    chip8: &mut Chip8,
+    framebuffer: &mut Framebuffer,
+    controls: &Controls,
+    recorder: &mut Option<Recorder>,
+    wav: &mut Option<WavRecorder>,
+    profiler: &Option<Rc<RefCell<Profiler>>>,
+    frames: u64,
+) -> Result<(), String> {
+    let mut synthesizer = Synthesizer::new();
 
-    matches!(args[2].to_lowercase().as_str(), "true")
+    for _ in 0..frames {
+        let output = chip8.run_frame([false; 16], controls.ticks_per_frame());
+        let beep = output.beep;
+
+        if let Some(profiler) = profiler {
+            profiler.borrow_mut().end_frame();
+        }
+
+        framebuffer.render(output.memory, output.width, output.height);
+
+        if let Some(recorder) = recorder {
+            recorder.record(framebuffer)?;
+        }
+
+        if let Some(wav) = wav {
+            wav.record(&synthesizer.frame(beep, chip8.sound()))?;
+        }
+
+        print_warnings(chip8);
+
+        if let Some(error) = chip8.error() {
+            return Err(error.to_string());
+        }
+    }
+
+    Ok(())
 }
 
-fn get_sleep_duration(super_chip: bool) -> Duration {
-    if super_chip {
-        Duration::from_millis(1000 / CHIP8_CPU_FREQUENCY_SUPER_CHIP)
-    } else {
-        Duration::from_millis(1000 / CHIP8_CPU_FREQUENCY)
+/// Records at the size of the current display times `scale`.
+fn create_recorder(path: &str, framebuffer: &Framebuffer, scale: u32) -> Result<Recorder, String> {
+    let scale = scale as usize;
+    Recorder::create(
+        path,
+        framebuffer.width() * scale,
+        framebuffer.height() * scale,
+    )
+}
+
+/// Writes the recordings, the final screenshot and the profile at the end
+/// of a session.
+fn finish(
+    framebuffer: &Framebuffer,
+    recorder: Option<Recorder>,
+    wav: Option<WavRecorder>,
+    profiler: &Option<Rc<RefCell<Profiler>>>,
+    options: &Options,
+) {
+    if let Some(Err(err)) = recorder.map(Recorder::finish) {
+        eprintln!("{}", err);
+    }
+
+    if let Some(Err(err)) = wav.map(WavRecorder::finish) {
+        eprintln!("{}", err);
     }
+
+    if let Some(path) = &options.screenshot {
+        if let Err(err) = capture::write_png(framebuffer, options.scale, path) {
+            eprintln!("{}", err);
+        }
+    }
+
+    write_profile(profiler, &options.profile);
+}
+
+fn write_profile(profiler: &Option<Rc<RefCell<Profiler>>>, path: &Option<String>) {
+    if let (Some(profiler), Some(path)) = (profiler, path) {
+        if let Err(err) = profiler.borrow().write_report(path) {
+            eprintln!("{}", err);
+        }
+    }
+}
+
+/// Runs the ROM without a window or input until the reference trace ends or
+/// the first instruction diverges from it, then exits.
+fn verify(mut chip8: Chip8, path: &str, platform: Platform, ticks_per_frame: u64) -> ! {
+    let verifier = Verifier::open(path, platform).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+
+    let verifier = Rc::new(RefCell::new(verifier));
+    let hook_verifier = Rc::clone(&verifier);
+    chip8.add_trace_hook(Box::new(move |entry| {
+        hook_verifier.borrow_mut().check(entry)
+    }));
+
+    while !verifier.borrow().finished() {
+        let cycles = chip8.cycles();
+        chip8.run_frame([false; 16], ticks_per_frame);
+        print_warnings(&mut chip8);
+
+        if let Some(error) = chip8.error() {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+
+        // Without input, a program waiting for a key never continues.
+        if chip8.cycles() == cycles {
+            eprintln!("program waits for a key at cycle {}", cycles);
+            process::exit(1);
+        }
+    }
+
+    println!(
+        "{} instructions match the reference trace",
+        verifier.borrow().checked()
+    );
+    process::exit(0);
 }
@@ -1,7 +1,13 @@
-use crate::window::{HEIGHT_HI_RES, WIDTH_HI_RES};
+use crate::platform::Platform;
+use crate::window::{HEIGHT_LO_RES, HEIGHT_MEGA_CHIP, WIDTH_LO_RES, WIDTH_MEGA_CHIP};
+use sdl2::pixels::Color;
 
-const CHIP8_RAM: usize = 1024 * 32;
-const CHIP8_VRAM: usize = WIDTH_HI_RES * HEIGHT_HI_RES;
+/// VRAM is sized for the largest display, smaller ones use its top-left part.
+const VRAM_WIDTH: usize = WIDTH_MEGA_CHIP;
+const VRAM_HEIGHT: usize = HEIGHT_MEGA_CHIP;
+pub const COLOR_ZONE_WIDTH: usize = 8;
+const COLOR_COLUMNS: usize = WIDTH_LO_RES / COLOR_ZONE_WIDTH;
+const DEFAULT_FOREGROUND: u8 = 1;
 
 pub const FONT_SET: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -33,7 +39,7 @@ pub const FONT_SET_SUPER_CHIP: [u8; 160] = [
     0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
     0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
     0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
-    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFf, 0xC3, 0xC3, 0xC3, // A
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
     0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
     0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
     0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
@@ -41,50 +47,293 @@ pub const FONT_SET_SUPER_CHIP: [u8; 160] = [
     0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
 ];
 
+/// Color RAM of the CHIP-8X color board. Every row of an 8 pixel wide
+/// column has its own foreground color; the background color applies to the
+/// whole display.
+pub struct ColorAttributes {
+    background: u8,
+    foreground: [u8; COLOR_COLUMNS * HEIGHT_LO_RES],
+}
+
+impl ColorAttributes {
+    fn new() -> Self {
+        ColorAttributes {
+            background: 0,
+            foreground: [DEFAULT_FOREGROUND; COLOR_COLUMNS * HEIGHT_LO_RES],
+        }
+    }
+
+    pub fn background(&self) -> u8 {
+        self.background
+    }
+
+    pub fn set_background(&mut self, color: u8) {
+        self.background = color;
+    }
+
+    /// Foreground color of the pixel at `x`, `y`.
+    pub fn foreground(&self, x: usize, y: usize) -> u8 {
+        let column = (x / COLOR_ZONE_WIDTH) % COLOR_COLUMNS;
+        self.foreground[column + (y % HEIGHT_LO_RES) * COLOR_COLUMNS]
+    }
+
+    /// Sets the foreground color of one row of an 8 pixel wide column.
+    pub fn set_foreground(&mut self, column: usize, row: usize, color: u8) {
+        let column = column % COLOR_COLUMNS;
+        self.foreground[column + (row % HEIGHT_LO_RES) * COLOR_COLUMNS] = color;
+    }
+}
+
+/// How MEGA-CHIP sprite pixels are combined with the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    Normal,
+    Opacity25,
+    Opacity50,
+    Opacity75,
+    Add,
+    Multiply,
+}
+
+impl BlendMode {
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            1 => BlendMode::Opacity25,
+            2 => BlendMode::Opacity50,
+            3 => BlendMode::Opacity75,
+            4 => BlendMode::Add,
+            5 => BlendMode::Multiply,
+            _ => BlendMode::Normal,
+        }
+    }
+}
+
+/// True color screen of the MEGA-CHIP mode. Sprite pixels are blended into
+/// it, while VRAM keeps their palette indices for collision detection.
+pub struct MegaChipScreen {
+    enabled: bool,
+    palette: [Color; 256],
+    pixels: Vec<Color>,
+    alpha: u8,
+}
+
+impl MegaChipScreen {
+    fn new() -> Self {
+        let mut palette = [Color::RGBA(0xFF, 0xFF, 0xFF, 0xFF); 256];
+        palette[0] = Color::RGBA(0, 0, 0, 0xFF);
+
+        MegaChipScreen {
+            enabled: false,
+            palette,
+            pixels: vec![Color::RGBA(0, 0, 0, 0xFF); WIDTH_MEGA_CHIP * HEIGHT_MEGA_CHIP],
+            alpha: 0xFF,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn palette_color(&self, index: u8) -> Color {
+        self.palette[index as usize]
+    }
+
+    pub fn set_palette_color(&mut self, index: u8, color: Color) {
+        self.palette[index as usize] = color;
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[x + y * WIDTH_MEGA_CHIP]
+    }
+
+    /// Blends the palette color `index` into the pixel at `x`, `y`.
+    pub fn blend_pixel(&mut self, x: usize, y: usize, index: u8, mode: BlendMode) {
+        let source = self.palette[index as usize];
+        let target = &mut self.pixels[x + y * WIDTH_MEGA_CHIP];
+
+        let channel = |source: u8, target: u8| -> u8 {
+            let (s, t) = (source as u32, target as u32);
+
+            match mode {
+                BlendMode::Normal => source,
+                BlendMode::Opacity25 => ((s + 3 * t) / 4) as u8,
+                BlendMode::Opacity50 => ((s + t) / 2) as u8,
+                BlendMode::Opacity75 => ((3 * s + t) / 4) as u8,
+                BlendMode::Add => (s + t).min(0xFF) as u8,
+                BlendMode::Multiply => (s * t / 0xFF) as u8,
+            }
+        };
+
+        *target = Color::RGBA(
+            channel(source.r, target.r),
+            channel(source.g, target.g),
+            channel(source.b, target.b),
+            0xFF,
+        );
+    }
+
+    fn clear(&mut self) {
+        self.pixels.fill(Color::RGBA(0, 0, 0, 0xFF));
+    }
+}
+
 pub struct Memory {
-    ram: [u8; CHIP8_RAM],
-    vram: [u8; CHIP8_VRAM],
+    ram: Vec<u8>,
+    vram: Vec<u8>,
+    colors: Option<ColorAttributes>,
+    mega_chip: Option<MegaChipScreen>,
+    write_log: Option<Vec<(usize, u8)>>,
 }
 
 impl Memory {
-    pub fn new(super_chip: bool) -> Self {
-        let mut ram = [0u8; CHIP8_RAM];
-        let vram = [0u8; CHIP8_VRAM];
+    pub fn new(platform: Platform) -> Self {
+        let mut ram = vec![0u8; platform.ram_size()];
+        let vram = vec![0u8; VRAM_WIDTH * VRAM_HEIGHT];
+
+        ram[..FONT_SET.len()].copy_from_slice(&FONT_SET);
 
-        for i in 0..FONT_SET.len() {
-            ram[i] = FONT_SET[i];
+        if platform.is_super_chip() {
+            ram[80..80 + FONT_SET_SUPER_CHIP.len()].copy_from_slice(&FONT_SET_SUPER_CHIP);
         }
 
-        if super_chip {
-            for i in 0..FONT_SET_SUPER_CHIP.len() {
-                ram[i + 80] = FONT_SET_SUPER_CHIP[i];
-            }
+        let colors = if platform.has_color() {
+            Some(ColorAttributes::new())
+        } else {
+            None
+        };
+
+        let mega_chip = if platform == Platform::MegaChip {
+            Some(MegaChipScreen::new())
+        } else {
+            None
+        };
+
+        Memory {
+            ram,
+            vram,
+            colors,
+            mega_chip,
+            write_log: None,
         }
+    }
 
-        Memory { ram, vram }
+    pub fn size(&self) -> usize {
+        self.ram.len()
     }
 
-    pub fn read_byte(&mut self, address: usize) -> u8 {
+    pub fn read_byte(&self, address: usize) -> u8 {
         self.ram[address]
     }
 
     pub fn write_byte(&mut self, address: usize, value: u8) {
         self.ram[address] = value;
+
+        if let Some(log) = &mut self.write_log {
+            log.push((address, value));
+        }
+    }
+
+    /// Starts or stops recording the RAM writes for tracing.
+    pub fn set_write_log(&mut self, enabled: bool) {
+        self.write_log = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    /// Returns the RAM writes recorded since the last call.
+    pub fn take_writes(&mut self) -> Vec<(usize, u8)> {
+        self.write_log
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
     }
 
-    pub fn read_word(&mut self, address: usize) -> u16 {
+    pub fn read_word(&self, address: usize) -> u16 {
         (self.ram[address] as u16) << 8 | (self.ram[address + 1] as u16)
     }
 
-    pub fn read_vram(&mut self, x: usize, y: usize) -> u8 {
-        self.vram[x + y * WIDTH_HI_RES]
+    pub fn read_vram(&self, x: usize, y: usize) -> u8 {
+        self.vram[x + y * VRAM_WIDTH]
     }
 
     pub fn write_vram(&mut self, x: usize, y: usize, value: u8) {
-        self.vram[x + y * WIDTH_HI_RES] = value;
+        self.vram[x + y * VRAM_WIDTH] = value;
+    }
+
+    pub fn clear_vram(&mut self) {
+        self.vram.fill(0);
+
+        if let Some(screen) = self.mega_chip.as_mut() {
+            screen.clear();
+        }
+    }
+
+    /// Moves the top-left `width` x `height` pixels of the display by `dx`,
+    /// `dy`. Pixels scrolled in from outside are cleared.
+    pub fn scroll_vram(&mut self, dx: isize, dy: isize, width: usize, height: usize) {
+        scroll(&mut self.vram, VRAM_WIDTH, width, height, dx, dy, 0);
+
+        if let Some(screen) = self.mega_chip.as_mut() {
+            let black = Color::RGBA(0, 0, 0, 0xFF);
+            let pixels = &mut screen.pixels;
+            scroll(pixels, WIDTH_MEGA_CHIP, width, height, dx, dy, black);
+        }
     }
 
     pub fn xor_vram(&mut self, x: usize, y: usize, value: u8) {
-        self.vram[x + y * WIDTH_HI_RES] ^= value;
+        self.vram[x + y * VRAM_WIDTH] ^= value;
+    }
+
+    pub fn colors(&self) -> Option<&ColorAttributes> {
+        self.colors.as_ref()
+    }
+
+    pub fn colors_mut(&mut self) -> Option<&mut ColorAttributes> {
+        self.colors.as_mut()
+    }
+
+    pub fn mega_chip(&self) -> Option<&MegaChipScreen> {
+        self.mega_chip.as_ref()
+    }
+
+    pub fn mega_chip_mut(&mut self) -> Option<&mut MegaChipScreen> {
+        self.mega_chip.as_mut()
+    }
+}
+
+fn scroll<T: Copy>(
+    buffer: &mut [T],
+    stride: usize,
+    width: usize,
+    height: usize,
+    dx: isize,
+    dy: isize,
+    empty: T,
+) {
+    let source = buffer.to_vec();
+
+    for y in 0..height {
+        for x in 0..width {
+            let from_x = x as isize - dx;
+            let from_y = y as isize - dy;
+            let inside =
+                (0..width as isize).contains(&from_x) && (0..height as isize).contains(&from_y);
+
+            buffer[x + y * stride] = if inside {
+                source[from_x as usize + from_y as usize * stride]
+            } else {
+                empty
+            };
+        }
     }
 }
@@ -0,0 +1,150 @@
+#[cfg(test)]
+#[path = "./menu_test.rs"]
+mod menu_test;
+
+use crate::cartridge::{self, Cartridge};
+use crate::database::RomDatabase;
+use crate::platform::Platform;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MenuEntry {
+    pub path: String,
+    /// ROM in the zip archive at `path`.
+    pub entry: Option<String>,
+    pub title: String,
+    /// Platform from the ROM database, if the ROM is known.
+    pub platform: Option<Platform>,
+}
+
+/// List of ROMs to pick one from.
+pub struct Menu {
+    entries: Vec<MenuEntry>,
+    selected: usize,
+}
+
+impl Menu {
+    /// Creates a menu with the entries sorted by title.
+    pub fn new(mut entries: Vec<MenuEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.title.to_lowercase());
+        Menu {
+            entries,
+            selected: 0,
+        }
+    }
+
+    /// Lists the ROMs in `directory` and in the zip archives there, named
+    /// by the database or else by their file name.
+    pub fn scan(directory: &str, database: &RomDatabase) -> Result<Self, String> {
+        let error = |err| format!("failed to read ROM directory '{}': {}", directory, err);
+        let mut entries = Vec::new();
+
+        for file in fs::read_dir(directory).map_err(error)? {
+            let path = file.map_err(error)?.path();
+            let path = path.to_string_lossy();
+
+            if !Path::new(path.as_ref()).is_file() {
+                continue;
+            }
+
+            if cartridge::is_archive_file(&path) {
+                match Menu::archive(&path, database) {
+                    Ok(archive) => entries.extend(archive.entries),
+                    // Listed like unreadable ROMs, so picking it shows why.
+                    Err(_) => entries.push(create_entry(&path, None, None, database)),
+                }
+            } else if cartridge::is_rom_file(&path) {
+                let rom = fs::read(path.as_ref()).ok();
+                entries.push(create_entry(&path, None, rom.as_deref(), database));
+            }
+        }
+
+        Ok(Menu::new(entries))
+    }
+
+    /// Lists the ROMs in the zip archive at `path`.
+    pub fn archive(path: &str, database: &RomDatabase) -> Result<Self, String> {
+        let entries = Cartridge::read_entries(path)?
+            .iter()
+            .map(|(entry, rom)| create_entry(path, Some(entry), Some(rom), database))
+            .collect();
+
+        Ok(Menu::new(entries))
+    }
+
+    pub fn entries(&self) -> &[MenuEntry] {
+        &self.entries
+    }
+
+    pub fn selected(&self) -> Option<&MenuEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// Moves the selection up or down, wrapping around at the ends.
+    pub fn move_selection(&mut self, offset: isize) {
+        let count = self.entries.len() as isize;
+
+        if count > 0 {
+            self.selected = (self.selected as isize + offset).rem_euclid(count) as usize;
+        }
+    }
+
+    /// Returns a heading and up to `rows` entries around the selection, the
+    /// selected one marked with `>`.
+    pub fn lines(&self, rows: usize) -> Vec<String> {
+        if self.entries.is_empty() {
+            return vec!["No ROMs found".to_string()];
+        }
+
+        let first = self
+            .selected
+            .saturating_sub(rows / 2)
+            .min(self.entries.len().saturating_sub(rows));
+        let mut lines = vec![format!(
+            "Select a ROM ({}/{})",
+            self.selected + 1,
+            self.entries.len()
+        )];
+
+        for (index, entry) in self.entries.iter().enumerate().skip(first).take(rows) {
+            let marker = if index == self.selected { ">" } else { " " };
+
+            match entry.platform {
+                Some(platform) => {
+                    lines.push(format!("{} {} ({})", marker, entry.title, platform.name()))
+                }
+                None => lines.push(format!("{} {}", marker, entry.title)),
+            }
+        }
+
+        lines
+    }
+}
+
+/// Looks up the raw data of a ROM in the database. ROMs that can't be read
+/// are still listed, so picking them shows why.
+fn create_entry(
+    path: &str,
+    entry: Option<&str>,
+    rom: Option<&[u8]>,
+    database: &RomDatabase,
+) -> MenuEntry {
+    let info = rom.and_then(|rom| database.lookup(rom).cloned());
+    let file = entry.unwrap_or(path);
+
+    MenuEntry {
+        path: path.to_string(),
+        entry: entry.map(str::to_string),
+        title: info
+            .as_ref()
+            .map_or_else(|| file_title(Path::new(file)), |info| info.title.clone()),
+        platform: info.and_then(|info| info.platform),
+    }
+}
+
+fn file_title(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,139 @@
+use super::*;
+use crate::database::{hash, RomDatabase};
+use std::env;
+
+fn create_entry(title: &str, platform: Option<Platform>) -> MenuEntry {
+    MenuEntry {
+        path: format!("{}.ch8", title),
+        entry: None,
+        title: title.to_string(),
+        platform,
+    }
+}
+
+fn create_menu(count: usize) -> Menu {
+    Menu::new(
+        (0..count)
+            .map(|index| create_entry(&format!("Game {:02}", index), None))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_sorted_by_title() {
+    let menu = Menu::new(vec![
+        create_entry("tetris", None),
+        create_entry("Pong", Some(Platform::Chip8)),
+    ]);
+    assert_eq!(menu.entries()[0].title, "Pong");
+    assert_eq!(menu.selected().unwrap().title, "Pong");
+}
+
+#[test]
+fn test_move_selection() {
+    let mut menu = create_menu(3);
+    menu.move_selection(-1);
+    assert_eq!(menu.selected().unwrap().title, "Game 02");
+    menu.move_selection(2);
+    assert_eq!(menu.selected().unwrap().title, "Game 01");
+
+    let mut menu = create_menu(0);
+    menu.move_selection(1);
+    assert_eq!(menu.selected(), None);
+}
+
+#[test]
+fn test_lines() {
+    let menu = Menu::new(vec![
+        create_entry("Pong", Some(Platform::Chip8)),
+        create_entry("Tetris", None),
+    ]);
+    assert_eq!(
+        menu.lines(5),
+        vec!["Select a ROM (1/2)", "> Pong (CHIP-8)", "  Tetris"]
+    );
+    assert_eq!(create_menu(0).lines(5), vec!["No ROMs found"]);
+}
+
+#[test]
+fn test_lines_scroll() {
+    let mut menu = create_menu(10);
+    menu.move_selection(6);
+    let lines = menu.lines(4);
+    assert_eq!(lines[0], "Select a ROM (7/10)");
+    assert_eq!(
+        &lines[1..],
+        ["  Game 04", "  Game 05", "> Game 06", "  Game 07"]
+    );
+
+    menu.move_selection(3);
+    assert_eq!(menu.lines(4)[4], "> Game 09");
+}
+
+#[test]
+fn test_scan() {
+    let directory = env::temp_dir().join("chip8-menu-scan");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("pong.ch8"), [0x12, 0x00]).unwrap();
+    fs::write(directory.join("unknown.CH8"), [0x00, 0xe0]).unwrap();
+    fs::write(directory.join("pong.sym"), b"0x200 main\n").unwrap();
+
+    let programs = format!(
+        r#"[{{ "title": "Pong", "roms": {{ "{}": {{ "platforms": ["superchip"] }} }} }}]"#,
+        hash(&[0x12, 0x00])
+    );
+    let database = RomDatabase::parse(&programs).unwrap();
+    let menu = Menu::scan(directory.to_str().unwrap(), &database).unwrap();
+    assert_eq!(menu.entries().len(), 2);
+    assert_eq!(menu.entries()[0].title, "Pong");
+    assert_eq!(menu.entries()[0].platform, Some(Platform::SuperChip));
+    assert_eq!(menu.entries()[1].title, "unknown");
+    assert!(Menu::scan("missing-directory", &database).is_err());
+}
+
+#[test]
+fn test_scan_archive() {
+    let directory = env::temp_dir().join("chip8-menu-archive");
+    fs::create_dir_all(&directory).unwrap();
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+
+    for (name, rom) in [
+        ("games/pong.ch8", [0x12, 0x00]),
+        ("tetris.ch8", [0x00, 0xe0]),
+    ] {
+        writer
+            .start_file(name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        std::io::Write::write_all(&mut writer, &rom).unwrap();
+    }
+
+    let archive = writer.finish().unwrap().into_inner();
+    fs::write(directory.join("roms.zip"), archive).unwrap();
+
+    let menu = Menu::scan(directory.to_str().unwrap(), &RomDatabase::new()).unwrap();
+    let titles: Vec<&str> = menu
+        .entries()
+        .iter()
+        .map(|entry| entry.title.as_str())
+        .collect();
+    assert_eq!(titles, ["pong", "tetris"]);
+    assert_eq!(menu.entries()[0].entry.as_deref(), Some("games/pong.ch8"));
+    assert!(menu.entries()[0].path.ends_with("roms.zip"));
+}
+
+#[test]
+fn test_scan_unreadable() {
+    let directory = env::temp_dir().join("chip8-menu-unreadable");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("broken.zip"), b"PK\x03\x04broken").unwrap();
+    fs::write(directory.join("broken.gif"), b"GIF89a").unwrap();
+
+    let menu = Menu::scan(directory.to_str().unwrap(), &RomDatabase::new()).unwrap();
+    let titles: Vec<&str> = menu
+        .entries()
+        .iter()
+        .map(|entry| entry.title.as_str())
+        .collect();
+    assert_eq!(titles, ["broken", "broken"]);
+    assert!(menu.entries().iter().all(|entry| entry.entry.is_none()));
+}
@@ -0,0 +1,283 @@
+#[cfg(test)]
+#[path = "./monitor_test.rs"]
+mod monitor_test;
+
+use crate::chip8::Chip8;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+const BYTES_PER_ROW: usize = 16;
+/// Rows shown around PC and I.
+const ROWS_PER_AREA: usize = 4;
+/// Number of frames a written byte stays highlighted.
+const HIGHLIGHT_FRAMES: u32 = 30;
+const DEFAULT_SPRITE_HEIGHT: usize = 8;
+const MAX_SPRITE_HEIGHT: usize = 16;
+
+const HIGHLIGHT: &str = "\x1b[7m";
+const BOLD: &str = "\x1b[1m";
+const UNDERLINE: &str = "\x1b[4m";
+const RESET: &str = "\x1b[0m";
+
+const HELP: &str = "commands: pause, resume, poke ADDR BYTE..., set REG VALUE, \
+                    view ADDR, sprite HEIGHT, help (numbers are hex)";
+
+/// Terminal panel with a hex dump of the RAM around PC and I, a preview of
+/// the sprite at I and the registers. Memory and registers can be edited
+/// while paused.
+pub struct Monitor {
+    paused: bool,
+    recent: HashMap<usize, u32>,
+    view: Option<usize>,
+    sprite_height: usize,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Monitor {
+            paused: false,
+            recent: HashMap::new(),
+            view: None,
+            sprite_height: DEFAULT_SPRITE_HEIGHT,
+        }
+    }
+
+    /// Reads monitor commands from stdin on a background thread.
+    pub fn spawn_input() -> Receiver<String> {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        receiver
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Highlights the given RAM writes for the next frames.
+    pub fn record_writes(&mut self, writes: &[(usize, u8)]) {
+        for &(address, _) in writes {
+            self.recent.insert(address, HIGHLIGHT_FRAMES);
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.recent.retain(|_, frames| {
+            *frames -= 1;
+            *frames > 0
+        });
+    }
+
+    /// Runs a command and returns the message to show for it.
+    pub fn execute(&mut self, command: &str, chip8: &mut Chip8) -> Result<String, String> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+
+        match words.as_slice() {
+            [] => Ok(String::new()),
+            ["pause"] => {
+                self.paused = true;
+                Ok("paused".to_string())
+            }
+            ["resume"] => {
+                self.paused = false;
+                Ok("resumed".to_string())
+            }
+            ["poke", address, values @ ..] if !values.is_empty() => {
+                self.require_pause()?;
+                self.poke(chip8, parse_hex(address)?, values)
+            }
+            ["set", register, value] => {
+                self.require_pause()?;
+                set_register(chip8, register, parse_hex(value)?)?;
+                Ok(format!("{} = {}", register.to_uppercase(), value))
+            }
+            ["view", address] => {
+                self.view = Some(parse_hex(address)?);
+                Ok(format!("viewing {}", address))
+            }
+            ["sprite", height] => {
+                let height = parse_hex(height)?;
+
+                if height == 0 || height > MAX_SPRITE_HEIGHT {
+                    return Err(format!(
+                        "sprite height must be 1 to {:X}",
+                        MAX_SPRITE_HEIGHT
+                    ));
+                }
+
+                self.sprite_height = height;
+                Ok(format!("sprite height {:X}", height))
+            }
+            ["help"] => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command '{}', {}", command.trim(), HELP)),
+        }
+    }
+
+    pub fn render(&self, chip8: &mut Chip8) -> String {
+        let state = chip8.state();
+        let mut panel = format!(
+            "PC {:04X}  I {:04X}  SP {:X}  DT {:02X}  ST {:02X}{}\n",
+            state.pc,
+            state.i,
+            state.sp,
+            state.dt,
+            state.st,
+            if self.paused { "  [paused]" } else { "" }
+        );
+
+        for (register, value) in state.v.iter().enumerate() {
+            let _ = write!(panel, "V{:X} {:02X} ", register, value);
+        }
+
+        let pc = state.pc as usize;
+        let i = state.i as usize;
+        panel.push_str("\n\nPC:\n");
+        panel.push_str(&self.dump(chip8, pc, pc, i));
+        panel.push_str("\nI:\n");
+        panel.push_str(&self.dump(chip8, i, pc, i));
+
+        if let Some(view) = self.view {
+            let _ = write!(panel, "\n{:04X}:\n", view);
+            panel.push_str(&self.dump(chip8, view, pc, i));
+        }
+
+        let _ = writeln!(panel, "\nSprite at I (8x{:X}):", self.sprite_height);
+        let size = chip8.memory().size();
+
+        for row in 0..self.sprite_height {
+            let byte = chip8.memory().read_byte((i + row) % size);
+            let pixels: String = (0..8)
+                .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+                .collect();
+            let _ = writeln!(panel, "  {}", pixels);
+        }
+
+        panel
+    }
+
+    fn require_pause(&self) -> Result<(), String> {
+        if self.paused {
+            Ok(())
+        } else {
+            Err("pause before editing".to_string())
+        }
+    }
+
+    fn poke(
+        &mut self,
+        chip8: &mut Chip8,
+        address: usize,
+        values: &[&str],
+    ) -> Result<String, String> {
+        let bytes = values
+            .iter()
+            .map(|value| {
+                let value = parse_hex(value)?;
+                u8::try_from(value)
+                    .map_err(|_| format!("value {:X} is too large for a byte", value))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+
+        let end = address.checked_add(bytes.len());
+
+        if end.is_none_or(|end| end > chip8.memory().size()) {
+            return Err(format!("address {:X} is out of range", address));
+        }
+
+        for (offset, &byte) in bytes.iter().enumerate() {
+            chip8.memory().write_byte(address + offset, byte);
+            self.recent.insert(address + offset, HIGHLIGHT_FRAMES);
+        }
+
+        Ok(format!("wrote {} bytes at {:04X}", bytes.len(), address))
+    }
+
+    /// Dumps the rows around `address`. Recently written bytes are shown
+    /// in reverse video, the instruction at PC in bold and the byte at I
+    /// underlined.
+    fn dump(&self, chip8: &mut Chip8, address: usize, pc: usize, i: usize) -> String {
+        let size = chip8.memory().size();
+        let first = (address / BYTES_PER_ROW).saturating_sub(1) * BYTES_PER_ROW;
+        let mut dump = String::new();
+
+        for row in (first..size).step_by(BYTES_PER_ROW).take(ROWS_PER_AREA) {
+            let _ = write!(dump, "{:04X} ", row);
+
+            for address in row..(row + BYTES_PER_ROW).min(size) {
+                let byte = chip8.memory().read_byte(address);
+                let mut style = String::new();
+
+                if self.recent.contains_key(&address) {
+                    style.push_str(HIGHLIGHT);
+                }
+
+                if address == pc || address == pc + 1 {
+                    style.push_str(BOLD);
+                }
+
+                if address == i {
+                    style.push_str(UNDERLINE);
+                }
+
+                if style.is_empty() {
+                    let _ = write!(dump, " {:02X}", byte);
+                } else {
+                    let _ = write!(dump, " {}{:02X}{}", style, byte, RESET);
+                }
+            }
+
+            dump.push('\n');
+        }
+
+        dump
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Monitor::new()
+    }
+}
+
+fn parse_hex(value: &str) -> Result<usize, String> {
+    usize::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid hex number '{}'", value))
+}
+
+fn set_register(chip8: &mut Chip8, register: &str, value: usize) -> Result<(), String> {
+    let register = register.to_uppercase();
+    let too_large = || format!("value {:X} is too large for {}", value, register);
+    let outside = || format!("address {:X} is outside of memory", value);
+
+    match register.as_str() {
+        "PC" | "I" if value > u32::MAX as usize => return Err(outside()),
+        "PC" if !chip8.is_valid_pc(value as u32) => return Err(outside()),
+        "I" if !chip8.is_valid_i(value as u32) => return Err(outside()),
+        "PC" => chip8.set_pc(value as u16),
+        "I" => chip8.set_i(value as u32),
+        "DT" => chip8.set_dt(u8::try_from(value).map_err(|_| too_large())?),
+        "ST" => chip8.set_st(u8::try_from(value).map_err(|_| too_large())?),
+        _ => {
+            let index = register
+                .strip_prefix('V')
+                .filter(|index| index.len() == 1)
+                .and_then(|index| usize::from_str_radix(index, 16).ok())
+                .ok_or(format!("unknown register '{}'", register))?;
+
+            chip8.set_v(index, u8::try_from(value).map_err(|_| too_large())?);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use super::*;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+
+fn create_chip8() -> Chip8 {
+    Chip8::new(Platform::Chip8, Quirks::new(Platform::Chip8))
+}
+
+fn create_paused_monitor(chip8: &mut Chip8) -> Monitor {
+    let mut monitor = Monitor::new();
+    monitor.execute("pause", chip8).unwrap();
+    monitor
+}
+
+#[test]
+fn test_poke() {
+    let mut chip8 = create_chip8();
+    let mut monitor = create_paused_monitor(&mut chip8);
+    assert!(monitor.execute("poke 300 a1 0xb2", &mut chip8).is_ok());
+    assert_eq!(chip8.memory().read_byte(0x300), 0xa1);
+    assert_eq!(chip8.memory().read_byte(0x301), 0xb2);
+    assert!(monitor.execute("poke 7fff 1 2", &mut chip8).is_err());
+    assert_eq!(
+        monitor.execute("poke 300 1ff", &mut chip8),
+        Err("value 1FF is too large for a byte".to_string())
+    );
+    assert_eq!(chip8.memory().read_byte(0x300), 0xa1);
+    assert!(monitor
+        .execute("poke ffffffffffffffff 1", &mut chip8)
+        .is_err());
+}
+
+#[test]
+fn test_set_register() {
+    let mut chip8 = create_chip8();
+    let mut monitor = create_paused_monitor(&mut chip8);
+    monitor.execute("set va 42", &mut chip8).unwrap();
+    monitor.execute("set I 300", &mut chip8).unwrap();
+    monitor.execute("set pc 222", &mut chip8).unwrap();
+    assert_eq!(chip8.v(0xa), 0x42);
+    assert_eq!(chip8.i(), 0x300);
+    assert_eq!(chip8.state().pc, 0x222);
+    assert!(monitor.execute("set va 100", &mut chip8).is_err());
+    assert!(monitor.execute("set vg 1", &mut chip8).is_err());
+}
+
+#[test]
+fn test_set_address_outside_of_memory() {
+    let mut chip8 = create_chip8();
+    let mut monitor = create_paused_monitor(&mut chip8);
+    let size = chip8.memory().size();
+    let set = |monitor: &mut Monitor, chip8: &mut Chip8, register: &str, value: usize| {
+        monitor.execute(&format!("set {} {:x}", register, value), chip8)
+    };
+
+    assert!(set(&mut monitor, &mut chip8, "pc", size - 2).is_ok());
+    assert_eq!(
+        set(&mut monitor, &mut chip8, "pc", size - 1),
+        Err(format!("address {:X} is outside of memory", size - 1))
+    );
+    assert!(set(&mut monitor, &mut chip8, "i", size - 1).is_ok());
+    assert!(set(&mut monitor, &mut chip8, "i", size).is_err());
+    assert!(set(&mut monitor, &mut chip8, "pc", 0x1_0000_0000).is_err());
+    assert_eq!(chip8.state().pc as usize, size - 2);
+    assert_eq!(chip8.i() as usize, size - 1);
+}
+
+#[test]
+fn test_edit_requires_pause() {
+    let mut chip8 = create_chip8();
+    let mut monitor = Monitor::new();
+    assert!(monitor.execute("poke 300 1", &mut chip8).is_err());
+    assert!(monitor.execute("set v0 1", &mut chip8).is_err());
+    assert_eq!(chip8.memory().read_byte(0x300), 0);
+}
+
+#[test]
+fn test_render_sprite_preview() {
+    let mut chip8 = create_chip8();
+    let mut monitor = create_paused_monitor(&mut chip8);
+    monitor.execute("sprite 2", &mut chip8).unwrap();
+    chip8.set_i(0);
+    let panel = monitor.render(&mut chip8);
+    assert!(panel.contains("[paused]"));
+    assert!(panel.contains("Sprite at I (8x2):\n  ####....\n  #..#....\n"));
+    assert!(monitor.execute("sprite 0", &mut chip8).is_err());
+}
+
+#[test]
+fn test_render_highlights_writes() {
+    let mut chip8 = create_chip8();
+    let mut monitor = Monitor::new();
+    chip8.set_i(0x300);
+    chip8.memory().write_byte(0x300, 0x12);
+    monitor.record_writes(&[(0x300, 0x12)]);
+    let panel = monitor.render(&mut chip8);
+    assert!(panel.contains(&format!(" {}{}12{}", HIGHLIGHT, UNDERLINE, RESET)));
+
+    for _ in 0..HIGHLIGHT_FRAMES {
+        monitor.end_frame();
+    }
+
+    let panel = monitor.render(&mut chip8);
+    assert!(panel.contains(&format!(" {}12{}", UNDERLINE, RESET)));
+}
+
+#[test]
+fn test_unknown_command() {
+    let mut chip8 = create_chip8();
+    let mut monitor = Monitor::new();
+    assert!(monitor.execute("peek 300", &mut chip8).is_err());
+    assert_eq!(monitor.execute("", &mut chip8), Ok(String::new()));
+}
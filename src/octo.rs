@@ -0,0 +1,1006 @@
+#[cfg(test)]
+#[path = "./octo_test.rs"]
+mod octo_test;
+
+use crate::palette::Palette;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::symbols::Symbols;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const PROGRAM_START: u16 = 0x200;
+const MAX_ADDRESS: u16 = 0xFFF;
+/// Macro expansions per program, to stop macros that expand themselves.
+const MAX_EXPANSIONS: usize = 100_000;
+
+const BINARY_OPERATORS: &[&str] = &[
+    "+", "-", "*", "/", "%", "&", "|", "^", "<<", ">>", "pow", "min", "max", "<", "<=", "==", "!=",
+    ">=", ">",
+];
+const UNARY_OPERATORS: &[&str] = &[
+    "-", "~", "!", "sin", "cos", "tan", "exp", "log", "abs", "sqrt", "sign", "ceil", "floor", "@",
+];
+/// Statements of XO-CHIP, which the interpreter doesn't run.
+const XO_CHIP_STATEMENTS: &[&str] = &["scroll-up", "plane", "audio", "pitch", ":stringmode"];
+
+/// A program assembled from Octo source.
+#[derive(Debug, PartialEq)]
+pub struct Program {
+    pub rom: Vec<u8>,
+    /// Labels of the source.
+    pub symbols: Symbols,
+    /// Whether the program uses Super CHIP instructions.
+    pub super_chip: bool,
+}
+
+/// The options of an Octo cartridge, mapped onto the interpreter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OctoOptions {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub ticks_per_frame: Option<u64>,
+    pub palette: Option<Palette>,
+}
+
+impl OctoOptions {
+    /// Maps the options object of a cartridge. The shift, load/store and
+    /// jump quirks only exist as part of the Super CHIP platform, which is
+    /// also picked for programs using its instructions.
+    pub fn parse(options: &Value, program: &Program) -> Result<Self, String> {
+        let flag = |name: &str| options[name].as_bool().unwrap_or(false);
+        let platform = if program.super_chip
+            || flag("shiftQuirks")
+            || flag("loadStoreQuirks")
+            || flag("jumpQuirks")
+        {
+            Platform::SuperChip
+        } else {
+            Platform::Chip8
+        };
+
+        let mut quirks = Quirks::new(platform);
+        quirks.display_wait = flag("vBlankQuirks");
+        quirks.wrap = !flag("clipQuirks");
+        quirks.resolution_clear = true;
+
+        let colors: Vec<&str> = ["backgroundColor", "fillColor", "fillColor2", "blendColor"]
+            .iter()
+            .map_while(|name| options[*name].as_str())
+            .collect();
+        let palette = if colors.is_empty() {
+            None
+        } else {
+            Some(Palette::parse(&colors.join(","))?)
+        };
+
+        Ok(OctoOptions {
+            platform,
+            quirks,
+            ticks_per_frame: options["tickrate"].as_u64().filter(|&ticks| ticks > 0),
+            palette,
+        })
+    }
+}
+
+/// Whether `data` is a GIF image, the format of Octo cartridges.
+pub fn is_cartridge(data: &[u8]) -> bool {
+    data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a")
+}
+
+/// Reads the program and options of an Octo cartridge. The cartridge keeps
+/// four bits in the low nibble of the color index of each pixel, high
+/// nibble first, across all frames. They spell out the length of a JSON
+/// payload as a 32-bit big-endian number, followed by the payload with the
+/// program source and its options.
+pub fn read_cartridge(data: &[u8]) -> Result<(Program, OctoOptions), String> {
+    let error = |err: gif::DecodingError| format!("invalid cartridge image: {}", err);
+    let mut decode_options = gif::DecodeOptions::new();
+    decode_options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = decode_options.read_info(data).map_err(error)?;
+    let mut nibbles = Vec::new();
+
+    while let Some(frame) = decoder.read_next_frame().map_err(error)? {
+        nibbles.extend(frame.buffer.iter().map(|index| index & 0xF));
+    }
+
+    let bytes: Vec<u8> = nibbles
+        .chunks_exact(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect();
+
+    let size = match bytes.get(..4) {
+        Some(&[a, b, c, d]) => u32::from_be_bytes([a, b, c, d]) as usize,
+        _ => return Err("cartridge contains no program".to_string()),
+    };
+
+    let payload = bytes
+        .get(4..4 + size)
+        .ok_or("cartridge payload is truncated")?;
+    let payload: Value = serde_json::from_slice(payload)
+        .map_err(|err| format!("invalid cartridge payload: {}", err))?;
+    let source = payload["program"]
+        .as_str()
+        .ok_or("cartridge payload contains no program")?;
+    let program = assemble(source)?;
+    let options = OctoOptions::parse(&payload["options"], &program)?;
+
+    Ok((program, options))
+}
+
+/// Assembles Octo source into a CHIP-8 or Super CHIP program at 0x200.
+pub fn assemble(source: &str) -> Result<Program, String> {
+    let mut assembler = Assembler::new(tokenize(source));
+
+    while assembler.position < assembler.tokens.len() {
+        let token = assembler.next()?;
+        assembler
+            .statement(&token)
+            .map_err(|err| format!("line {}: {}", assembler.line, err))?;
+    }
+
+    assembler.finish()
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+/// Splits the source at whitespace, dropping `#` comments.
+fn tokenize(source: &str) -> Vec<Token> {
+    source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let code = line.split('#').next().unwrap_or_default();
+            code.split_whitespace().map(move |text| Token {
+                text: text.to_string(),
+                line: index + 1,
+            })
+        })
+        .collect()
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()? as f64
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()? as f64
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+fn parse_register(text: &str) -> Option<u8> {
+    let mut chars = text.chars();
+
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v' | 'V'), Some(digit), None) => digit.to_digit(16).map(|digit| digit as u8),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Key,
+    NotKey,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Condition {
+    register: u8,
+    comparison: Comparison,
+    operand: Operand,
+}
+
+impl Condition {
+    fn negate(self) -> Self {
+        let comparison = match self.comparison {
+            Comparison::Equal => Comparison::NotEqual,
+            Comparison::NotEqual => Comparison::Equal,
+            Comparison::Less => Comparison::GreaterEqual,
+            Comparison::GreaterEqual => Comparison::Less,
+            Comparison::Greater => Comparison::LessEqual,
+            Comparison::LessEqual => Comparison::Greater,
+            Comparison::Key => Comparison::NotKey,
+            Comparison::NotKey => Comparison::Key,
+        };
+
+        Condition { comparison, ..self }
+    }
+}
+
+/// An open `if ... begin` or `loop`, with the jumps to patch once it ends.
+enum Block {
+    If(u16),
+    Loop { start: u16, exits: Vec<u16> },
+}
+
+struct Macro {
+    arguments: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// A jump or call to a label that is defined later.
+struct Fixup {
+    address: u16,
+    name: String,
+    line: usize,
+}
+
+struct Assembler {
+    tokens: Vec<Token>,
+    position: usize,
+    line: usize,
+    rom: Vec<u8>,
+    here: u16,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    expansions: usize,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    /// Whether 0x200 holds the jump to `main`, which is left out when
+    /// `main` comes first.
+    main_jump: bool,
+    super_chip: bool,
+}
+
+impl Assembler {
+    fn new(tokens: Vec<Token>) -> Self {
+        Assembler {
+            tokens,
+            position: 0,
+            line: 0,
+            rom: vec![0x10, 0x00],
+            here: PROGRAM_START + 2,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            expansions: 0,
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+            main_jump: true,
+            super_chip: false,
+        }
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or("unexpected end of program")?;
+
+        self.position += 1;
+        self.line = token.line;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens
+            .get(self.position)
+            .map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), String> {
+        let token = self.next()?;
+
+        if token.text == text {
+            Ok(())
+        } else {
+            Err(format!("expected '{}', got '{}'", text, token.text))
+        }
+    }
+
+    fn statement(&mut self, token: &Token) -> Result<(), String> {
+        let text = token.text.as_str();
+
+        match text {
+            ":" => {
+                let name = self.next()?.text;
+
+                if name == "main" && self.main_jump && self.here == PROGRAM_START + 2 {
+                    self.rom.clear();
+                    self.here = PROGRAM_START;
+                    self.main_jump = false;
+                }
+
+                self.define_label(name, self.here)?;
+            }
+            ":next" => {
+                let name = self.next()?.text;
+                self.define_label(name, self.here + 1)?;
+            }
+            ":const" | ":calc" => {
+                let name = self.next()?.text;
+                let value = self.known_value()?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.next()?.text;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":org" => {
+                let address = self.known_value()? as i64;
+
+                if !(PROGRAM_START as i64..=MAX_ADDRESS as i64).contains(&address) {
+                    return Err(format!("address {:#x} is outside the program", address));
+                }
+
+                self.here = address as u16;
+            }
+            ":unpack" => {
+                let nibble = self.nibble()? as u16;
+                let address = self.address_value()?;
+                self.emit(0x6000 | (nibble << 4) | (address >> 8))?;
+                self.emit(0x6100 | (address & 0xFF))?;
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.write(byte)?;
+            }
+            ":call" => self.emit_address(0x2000)?,
+            ":macro" => self.define_macro()?,
+            ":proto" | ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            ";" | "return" => self.emit(0x00EE)?,
+            "clear" => self.emit(0x00E0)?,
+            "bcd" => self.emit_register(0xF033)?,
+            "save" | "load" => {
+                let register = self.register()? as u16;
+
+                if self.peek() == Some("-") {
+                    return Err("XO-CHIP register ranges are not supported".to_string());
+                }
+
+                let opcode = if text == "save" { 0xF055 } else { 0xF065 };
+                self.emit(opcode | (register << 8))?;
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let height = self.nibble()? as u16;
+                self.super_chip |= height == 0;
+                self.emit(0xD000 | (x << 8) | (y << 4) | height)?;
+            }
+            "jump" => self.emit_address(0x1000)?,
+            "jump0" => self.emit_address(0xB000)?,
+            "native" => self.emit_address(0x0000)?,
+            "hires" => self.emit_super_chip(0x00FF)?,
+            "lores" => self.emit_super_chip(0x00FE)?,
+            "scroll-down" => {
+                let rows = self.nibble()? as u16;
+                self.emit_super_chip(0x00C0 | rows)?;
+            }
+            "scroll-right" => self.emit_super_chip(0x00FB)?,
+            "scroll-left" => self.emit_super_chip(0x00FC)?,
+            "exit" => self.emit_super_chip(0x00FD)?,
+            "saveflags" | "loadflags" => {
+                let register = self.register()? as u16;
+                let opcode = if text == "saveflags" { 0xF075 } else { 0xF085 };
+                self.emit_super_chip(opcode | (register << 8))?;
+            }
+            "if" => {
+                let condition = self.condition()?;
+                let keyword = self.next()?;
+
+                match keyword.text.as_str() {
+                    "then" => self.skip_unless(condition)?,
+                    "begin" => {
+                        self.skip_unless(condition.negate())?;
+                        self.blocks.push(Block::If(self.here));
+                        self.emit(0x1000)?;
+                    }
+                    other => return Err(format!("expected 'then' or 'begin', got '{}'", other)),
+                }
+            }
+            "else" => {
+                let jump = self.pop_if("else")?;
+                self.blocks.push(Block::If(self.here));
+                self.emit(0x1000)?;
+                self.patch(jump, self.here);
+            }
+            "end" => {
+                let jump = self.pop_if("end")?;
+                self.patch(jump, self.here);
+            }
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here,
+                exits: Vec::new(),
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                self.skip_unless(condition.negate())?;
+                let here = self.here;
+
+                match self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find(|block| matches!(block, Block::Loop { .. }))
+                {
+                    Some(Block::Loop { exits, .. }) => exits.push(here),
+                    _ => return Err("'while' outside of a loop".to_string()),
+                }
+
+                self.emit(0x1000)?;
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits }) => {
+                    self.emit(0x1000 | start)?;
+
+                    for exit in exits {
+                        self.patch(exit, self.here);
+                    }
+                }
+                _ => return Err("'again' without 'loop'".to_string()),
+            },
+            "i" => self.index_statement()?,
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let opcode = if text == "delay" { 0xF015 } else { 0xF018 };
+                self.emit_register(opcode)?;
+            }
+            _ if XO_CHIP_STATEMENTS.contains(&text) => {
+                return Err(format!("XO-CHIP statement '{}' is not supported", text));
+            }
+            _ if self.is_register(text) => self.register_statement(token)?,
+            _ if self.macros.contains_key(text) => self.expand(text)?,
+            _ => match self.value(token)? {
+                Some(value) => {
+                    let byte = to_byte(value)?;
+                    self.write(byte)?;
+                }
+                None if text.starts_with(':') => {
+                    return Err(format!("unknown directive '{}'", text));
+                }
+                None => {
+                    self.fixups.push(Fixup {
+                        address: self.here,
+                        name: text.to_string(),
+                        line: self.line,
+                    });
+                    self.emit(0x2000)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn index_statement(&mut self) -> Result<(), String> {
+        let operator = self.next()?;
+
+        match operator.text.as_str() {
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.next()?;
+                    self.emit_register(0xF029)
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    self.super_chip = true;
+                    self.emit_register(0xF030)
+                }
+                Some("long") => Err("XO-CHIP statement 'i := long' is not supported".to_string()),
+                _ => self.emit_address(0xA000),
+            },
+            "+=" => self.emit_register(0xF01E),
+            other => Err(format!("unknown operator 'i {}'", other)),
+        }
+    }
+
+    fn register_statement(&mut self, token: &Token) -> Result<(), String> {
+        let x = (self.register_of(&token.text)? as u16) << 8;
+        let operator = self.next()?;
+        let operand = self.next()?;
+        let y = if self.is_register(&operand.text) {
+            Some((self.register_of(&operand.text)? as u16) << 4)
+        } else {
+            None
+        };
+
+        let opcode = match (operator.text.as_str(), y) {
+            (":=", Some(y)) => 0x8000 | x | y,
+            (":=", None) => match operand.text.as_str() {
+                "random" => 0xC000 | x | self.byte()? as u16,
+                "key" => 0xF00A | x,
+                "delay" => 0xF007 | x,
+                _ => 0x6000 | x | self.byte_of(&operand)? as u16,
+            },
+            ("+=", Some(y)) => 0x8004 | x | y,
+            ("+=", None) => 0x7000 | x | self.byte_of(&operand)? as u16,
+            ("-=", Some(y)) => 0x8005 | x | y,
+            ("-=", None) => 0x7000 | x | 0u8.wrapping_sub(self.byte_of(&operand)?) as u16,
+            ("|=", Some(y)) => 0x8001 | x | y,
+            ("&=", Some(y)) => 0x8002 | x | y,
+            ("^=", Some(y)) => 0x8003 | x | y,
+            (">>=", Some(y)) => 0x8006 | x | y,
+            ("=-", Some(y)) => 0x8007 | x | y,
+            ("<<=", Some(y)) => 0x800E | x | y,
+            ("|=" | "&=" | "^=" | ">>=" | "=-" | "<<=", None) => {
+                return Err(format!(
+                    "operator '{}' expects a register, got '{}'",
+                    operator.text, operand.text
+                ));
+            }
+            (other, _) => return Err(format!("unknown operator '{}'", other)),
+        };
+
+        self.emit(opcode)
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let register = self.register()?;
+        let operator = self.next()?;
+        let comparison = match operator.text.as_str() {
+            "key" => Comparison::Key,
+            "-key" => Comparison::NotKey,
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            ">" => Comparison::Greater,
+            "<=" => Comparison::LessEqual,
+            ">=" => Comparison::GreaterEqual,
+            other => return Err(format!("unknown comparison '{}'", other)),
+        };
+
+        let operand = match comparison {
+            Comparison::Key | Comparison::NotKey => Operand::Byte(0),
+            _ => {
+                let token = self.next()?;
+
+                if self.is_register(&token.text) {
+                    Operand::Register(self.register_of(&token.text)?)
+                } else {
+                    Operand::Byte(self.byte_of(&token)?)
+                }
+            }
+        };
+
+        Ok(Condition {
+            register,
+            comparison,
+            operand,
+        })
+    }
+
+    /// Emits the instructions that skip the next one unless `condition`
+    /// holds. Ordering comparisons subtract into vF.
+    fn skip_unless(&mut self, condition: Condition) -> Result<(), String> {
+        let x = (condition.register as u16) << 8;
+        let register = Operand::Register(condition.register);
+
+        match (condition.comparison, condition.operand) {
+            (Comparison::Equal, Operand::Byte(byte)) => self.emit(0x4000 | x | byte as u16),
+            (Comparison::Equal, Operand::Register(y)) => self.emit(0x9000 | x | (y as u16) << 4),
+            (Comparison::NotEqual, Operand::Byte(byte)) => self.emit(0x3000 | x | byte as u16),
+            (Comparison::NotEqual, Operand::Register(y)) => self.emit(0x5000 | x | (y as u16) << 4),
+            (Comparison::Key, _) => self.emit(0xE0A1 | x),
+            (Comparison::NotKey, _) => self.emit(0xE09E | x),
+            (Comparison::Less, operand) => {
+                self.subtract(register, operand)?;
+                self.emit(0x4F00)
+            }
+            (Comparison::GreaterEqual, operand) => {
+                self.subtract(register, operand)?;
+                self.emit(0x3F00)
+            }
+            (Comparison::Greater, operand) => {
+                self.subtract(operand, register)?;
+                self.emit(0x4F00)
+            }
+            (Comparison::LessEqual, operand) => {
+                self.subtract(operand, register)?;
+                self.emit(0x3F00)
+            }
+        }
+    }
+
+    /// Sets vF to the flag of `minuend - subtrahend`, which is 1 unless the
+    /// subtraction borrows.
+    fn subtract(&mut self, minuend: Operand, subtrahend: Operand) -> Result<(), String> {
+        match (minuend, subtrahend) {
+            (Operand::Register(a), Operand::Register(b)) => {
+                self.emit(0x8F00 | (a as u16) << 4)?;
+                self.emit(0x8F05 | (b as u16) << 4)
+            }
+            (Operand::Register(a), Operand::Byte(byte)) => {
+                self.emit(0x6F00 | byte as u16)?;
+                self.emit(0x8F07 | (a as u16) << 4)
+            }
+            (Operand::Byte(byte), Operand::Register(b)) => {
+                self.emit(0x6F00 | byte as u16)?;
+                self.emit(0x8F05 | (b as u16) << 4)
+            }
+            (Operand::Byte(_), Operand::Byte(_)) => Err("can't compare two numbers".to_string()),
+        }
+    }
+
+    fn pop_if(&mut self, keyword: &str) -> Result<u16, String> {
+        match self.blocks.pop() {
+            Some(Block::If(jump)) => Ok(jump),
+            _ => Err(format!("'{}' without 'if ... begin'", keyword)),
+        }
+    }
+
+    fn define_label(&mut self, name: String, address: u16) -> Result<(), String> {
+        if self.labels.contains_key(&name) || self.constants.contains_key(&name) {
+            return Err(format!("name '{}' is already defined", name));
+        }
+
+        self.labels.insert(name, address);
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), String> {
+        let name = self.next()?.text;
+        let mut arguments = Vec::new();
+
+        loop {
+            let token = self.next()?;
+
+            if token.text == "{" {
+                break;
+            }
+
+            arguments.push(token.text);
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let token = self.next()?;
+
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" if depth == 0 => break,
+                "}" => depth -= 1,
+                _ => {}
+            }
+
+            body.push(token);
+        }
+
+        self.macros.insert(name, Macro { arguments, body });
+        Ok(())
+    }
+
+    /// Replaces a macro call with the body of the macro.
+    fn expand(&mut self, name: &str) -> Result<(), String> {
+        self.expansions += 1;
+
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(format!("macro '{}' expands too often", name));
+        }
+
+        let count = self.macros[name].arguments.len();
+        let values = (0..count)
+            .map(|_| self.next().map(|token| token.text))
+            .collect::<Result<Vec<String>, String>>()?;
+        let definition = &self.macros[name];
+        let body: Vec<Token> = definition
+            .body
+            .iter()
+            .map(|token| {
+                let text = definition
+                    .arguments
+                    .iter()
+                    .position(|argument| *argument == token.text)
+                    .map_or_else(|| token.text.clone(), |index| values[index].clone());
+
+                Token {
+                    text,
+                    line: token.line,
+                }
+            })
+            .collect();
+
+        self.tokens.splice(self.position..self.position, body);
+        Ok(())
+    }
+
+    fn is_register(&self, text: &str) -> bool {
+        parse_register(text).is_some() || self.aliases.contains_key(text)
+    }
+
+    fn register(&mut self) -> Result<u8, String> {
+        let token = self.next()?;
+        self.register_of(&token.text)
+    }
+
+    fn register_of(&self, text: &str) -> Result<u8, String> {
+        parse_register(text)
+            .or_else(|| self.aliases.get(text).copied())
+            .ok_or(format!("expected a register, got '{}'", text))
+    }
+
+    /// Evaluates a number, a constant, a label defined before or a `{ ... }`
+    /// expression. Returns `None` for other names.
+    fn value(&mut self, token: &Token) -> Result<Option<f64>, String> {
+        if token.text == "{" {
+            let value = self.expression()?;
+            self.expect("}")?;
+            return Ok(Some(value));
+        }
+
+        Ok(parse_number(&token.text)
+            .or_else(|| self.constants.get(&token.text).copied())
+            .or_else(|| self.labels.get(&token.text).map(|&address| address as f64))
+            .or(match token.text.as_str() {
+                "HERE" => Some(self.here as f64),
+                "PI" => Some(std::f64::consts::PI),
+                "E" => Some(std::f64::consts::E),
+                _ => None,
+            }))
+    }
+
+    fn known_value(&mut self) -> Result<f64, String> {
+        let token = self.next()?;
+        self.known_value_of(&token)
+    }
+
+    fn known_value_of(&mut self, token: &Token) -> Result<f64, String> {
+        self.value(token)?
+            .ok_or(format!("undefined name '{}'", token.text))
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let token = self.next()?;
+        self.byte_of(&token)
+    }
+
+    fn byte_of(&mut self, token: &Token) -> Result<u8, String> {
+        let value = self.known_value_of(token)?;
+        to_byte(value)
+    }
+
+    fn nibble(&mut self) -> Result<u8, String> {
+        let value = self.known_value()? as i64;
+
+        if (0..=0xF).contains(&value) {
+            Ok(value as u8)
+        } else {
+            Err(format!("value {} does not fit in 4 bits", value))
+        }
+    }
+
+    fn address_value(&mut self) -> Result<u16, String> {
+        to_address(self.known_value()?)
+    }
+
+    /// Parses a `{ ... }` expression. Operators have no precedence and are
+    /// evaluated from right to left, as in Octo.
+    fn expression(&mut self) -> Result<f64, String> {
+        let left = self.term()?;
+
+        match self.peek() {
+            Some(operator) if BINARY_OPERATORS.contains(&operator) => {
+                let operator = self.next()?.text;
+                let right = self.expression()?;
+                Ok(binary(&operator, left, right))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let token = self.next()?;
+
+        match token.text.as_str() {
+            "(" => {
+                let value = self.expression()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            "@" => {
+                let address = to_address(self.term()?)?;
+                let index = address.wrapping_sub(PROGRAM_START) as usize;
+                Ok(self.rom.get(index).copied().unwrap_or(0) as f64)
+            }
+            operator if UNARY_OPERATORS.contains(&operator) => {
+                let value = self.term()?;
+                Ok(unary(operator, value))
+            }
+            _ => self.known_value_of(&token),
+        }
+    }
+
+    fn write(&mut self, byte: u8) -> Result<(), String> {
+        if self.here > MAX_ADDRESS {
+            return Err("program does not fit into memory".to_string());
+        }
+
+        let index = (self.here - PROGRAM_START) as usize;
+
+        if index >= self.rom.len() {
+            self.rom.resize(index + 1, 0);
+        }
+
+        self.rom[index] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn emit(&mut self, opcode: u16) -> Result<(), String> {
+        self.write((opcode >> 8) as u8)?;
+        self.write(opcode as u8)
+    }
+
+    fn emit_super_chip(&mut self, opcode: u16) -> Result<(), String> {
+        self.super_chip = true;
+        self.emit(opcode)
+    }
+
+    fn emit_register(&mut self, opcode: u16) -> Result<(), String> {
+        let register = self.register()? as u16;
+        self.emit(opcode | (register << 8))
+    }
+
+    /// Emits an instruction with a 12-bit address, which may refer to a
+    /// label defined later.
+    fn emit_address(&mut self, opcode: u16) -> Result<(), String> {
+        let token = self.next()?;
+
+        match self.value(&token)? {
+            Some(value) => self.emit(opcode | to_address(value)?),
+            None => {
+                self.fixups.push(Fixup {
+                    address: self.here,
+                    name: token.text,
+                    line: self.line,
+                });
+                self.emit(opcode)
+            }
+        }
+    }
+
+    fn patch(&mut self, address: u16, target: u16) {
+        let index = (address - PROGRAM_START) as usize;
+        self.rom[index] = (self.rom[index] & 0xF0) | (target >> 8) as u8;
+        self.rom[index + 1] = target as u8;
+    }
+
+    fn finish(mut self) -> Result<Program, String> {
+        match self.blocks.last() {
+            Some(Block::If(_)) => return Err("'if ... begin' without 'end'".to_string()),
+            Some(Block::Loop { .. }) => return Err("'loop' without 'again'".to_string()),
+            None => {}
+        }
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            let target = self.labels.get(&fixup.name).copied().ok_or(format!(
+                "line {}: undefined name '{}'",
+                fixup.line, fixup.name
+            ))?;
+            self.patch(fixup.address, target);
+        }
+
+        if self.main_jump {
+            let main = self
+                .labels
+                .get("main")
+                .copied()
+                .ok_or("program has no 'main' label")?;
+            self.patch(PROGRAM_START, main);
+        }
+
+        let mut labels: Vec<(&String, &u16)> = self.labels.iter().collect();
+        labels.sort();
+        let mut symbols = Symbols::new();
+
+        for (name, &address) in labels {
+            symbols.insert(address, name);
+        }
+
+        Ok(Program {
+            rom: self.rom,
+            symbols,
+            super_chip: self.super_chip,
+        })
+    }
+}
+
+fn to_byte(value: f64) -> Result<u8, String> {
+    let value = value as i64;
+
+    if (-128..=255).contains(&value) {
+        Ok(value as u8)
+    } else {
+        Err(format!("value {} does not fit in a byte", value))
+    }
+}
+
+fn to_address(value: f64) -> Result<u16, String> {
+    let value = value as i64;
+
+    if (0..=MAX_ADDRESS as i64).contains(&value) {
+        Ok(value as u16)
+    } else {
+        Err(format!("address {} is out of range", value))
+    }
+}
+
+fn binary(operator: &str, left: f64, right: f64) -> f64 {
+    let (a, b) = (left as i64, right as i64);
+    let flag = |condition: bool| if condition { 1.0 } else { 0.0 };
+
+    match operator {
+        "+" => left + right,
+        "-" => left - right,
+        "*" => left * right,
+        "/" => left / right,
+        "%" => left % right,
+        "&" => (a & b) as f64,
+        "|" => (a | b) as f64,
+        "^" => (a ^ b) as f64,
+        "<<" => a.wrapping_shl(b as u32) as f64,
+        ">>" => a.wrapping_shr(b as u32) as f64,
+        "pow" => left.powf(right),
+        "min" => left.min(right),
+        "max" => left.max(right),
+        "<" => flag(left < right),
+        "<=" => flag(left <= right),
+        "==" => flag(left == right),
+        "!=" => flag(left != right),
+        ">=" => flag(left >= right),
+        _ => flag(left > right),
+    }
+}
+
+fn unary(operator: &str, value: f64) -> f64 {
+    match operator {
+        "-" => -value,
+        "~" => !(value as i64) as f64,
+        "!" => {
+            if value == 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        "sin" => value.sin(),
+        "cos" => value.cos(),
+        "tan" => value.tan(),
+        "exp" => value.exp(),
+        "log" => value.ln(),
+        "abs" => value.abs(),
+        "sqrt" => value.sqrt(),
+        "sign" => value.signum(),
+        "ceil" => value.ceil(),
+        _ => value.floor(),
+    }
+}
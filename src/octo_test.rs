@@ -0,0 +1,229 @@
+use super::*;
+use serde_json::json;
+
+/// Encodes a cartridge payload the way Octo does, in the low nibbles of
+/// the color indices, spread over frames of 16x8 pixels.
+fn create_cartridge(payload: &Value) -> Vec<u8> {
+    let json = payload.to_string();
+    let mut bytes = (json.len() as u32).to_be_bytes().to_vec();
+    bytes.extend(json.as_bytes());
+
+    // The label in the high nibble must not change the data.
+    let pixels: Vec<u8> = bytes
+        .iter()
+        .flat_map(|byte| [0x30 | (byte >> 4), 0x30 | (byte & 0xF)])
+        .collect();
+
+    let palette: Vec<u8> = (0..=255).flat_map(|index| [index, index, index]).collect();
+    let mut image = Vec::new();
+    let mut encoder = gif::Encoder::new(&mut image, 16, 8, &palette).unwrap();
+
+    for chunk in pixels.chunks(16 * 8) {
+        let mut buffer = chunk.to_vec();
+        buffer.resize(16 * 8, 0);
+        let frame = gif::Frame {
+            width: 16,
+            height: 8,
+            buffer: buffer.into(),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).unwrap();
+    }
+
+    drop(encoder);
+    image
+}
+
+#[test]
+fn test_assemble_main_first() {
+    let program = assemble(": main\n  v0 := 5 # comment\n  loop again").unwrap();
+    assert_eq!(program.rom, vec![0x60, 0x05, 0x12, 0x02]);
+    assert_eq!(program.symbols.name(0x200), Some("main"));
+    assert!(!program.super_chip);
+}
+
+#[test]
+fn test_assemble_jump_to_main() {
+    let program = assemble(": data 1 2\n: main i := data sprite v0 v1 2").unwrap();
+    assert_eq!(
+        program.rom,
+        vec![0x12, 0x04, 0x01, 0x02, 0xa2, 0x02, 0xd0, 0x12]
+    );
+    assert!(assemble(": start clear").is_err());
+}
+
+#[test]
+fn test_assemble_registers() {
+    let source = ": main
+        :alias x v3
+        x := v4  x += 2  x += v4  x -= 1  x -= v4  x =- v4
+        x |= v4  x &= v4  x ^= v4  x >>= v4  x <<= v4
+        x := random 0xff  x := key  x := delay
+        delay := x  buzzer := x  i := hex x  i += x
+        bcd x  save x  load x";
+    let program = assemble(source).unwrap();
+    let opcodes: Vec<u16> = program
+        .rom
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    assert_eq!(
+        opcodes,
+        vec![
+            0x8340, 0x7302, 0x8344, 0x73ff, 0x8345, 0x8347, 0x8341, 0x8342, 0x8343, 0x8346, 0x834e,
+            0xc3ff, 0xf30a, 0xf307, 0xf315, 0xf318, 0xf329, 0xf31e, 0xf333, 0xf355, 0xf365,
+        ]
+    );
+}
+
+#[test]
+fn test_assemble_forward_call() {
+    let program = assemble(": main draw jump main\n: draw ;").unwrap();
+    assert_eq!(program.rom, vec![0x22, 0x04, 0x12, 0x00, 0x00, 0xee]);
+    assert_eq!(
+        assemble(": main missing").unwrap_err(),
+        "line 1: undefined name 'missing'"
+    );
+}
+
+#[test]
+fn test_assemble_if_then() {
+    let program = assemble(
+        ": main
+        if v1 == 3 then v0 := 1
+        if v1 != v2 then v0 := 1
+        if v1 key then v0 := 1
+        if v1 > 3 then v0 := 1",
+    )
+    .unwrap();
+    assert_eq!(
+        program.rom,
+        vec![
+            0x41, 0x03, 0x60, 0x01, 0x51, 0x20, 0x60, 0x01, 0xe1, 0xa1, 0x60, 0x01, 0x6f, 0x03,
+            0x8f, 0x15, 0x4f, 0x00, 0x60, 0x01,
+        ]
+    );
+}
+
+#[test]
+fn test_assemble_blocks() {
+    let program = assemble(
+        ": main
+        loop
+            if v0 == 0 begin v1 := 1 else v1 := 2 end
+            while v0 != 5
+            v0 += 1
+        again",
+    )
+    .unwrap();
+    assert_eq!(
+        program.rom,
+        vec![
+            0x30, 0x00, 0x12, 0x08, 0x61, 0x01, 0x12, 0x0a, 0x61, 0x02, 0x40, 0x05, 0x12, 0x12,
+            0x70, 0x01, 0x12, 0x00,
+        ]
+    );
+    assert!(assemble(": main if v0 == 1 begin").is_err());
+    assert!(assemble(": main again").is_err());
+}
+
+#[test]
+fn test_assemble_constants_and_calc() {
+    let program = assemble(
+        ":const SPEED 2
+        :calc DOUBLE { SPEED * 1 + 2 }
+        : main
+        v0 := SPEED
+        v1 := DOUBLE
+        v2 := { 0xF0 >> 4 }
+        :byte -1
+        :unpack 0xA main",
+    )
+    .unwrap();
+    assert_eq!(
+        program.rom,
+        vec![0x60, 0x02, 0x61, 0x06, 0x62, 0x0f, 0xff, 0x60, 0xa2, 0x61, 0x00]
+    );
+    assert!(assemble(": main v0 := 256").is_err());
+}
+
+#[test]
+fn test_assemble_macros() {
+    let program = assemble(
+        ":macro add-both A B { A += B B += A }
+        : main add-both v1 v2",
+    )
+    .unwrap();
+    assert_eq!(program.rom, vec![0x81, 0x24, 0x82, 0x14]);
+}
+
+#[test]
+fn test_assemble_next_and_org() {
+    let program = assemble(
+        ": main
+        :next target v0 := 0
+        :org 0x208
+        : data 0xaa",
+    )
+    .unwrap();
+    assert_eq!(program.rom, vec![0x60, 0x00, 0, 0, 0, 0, 0, 0, 0xaa]);
+    assert_eq!(program.symbols.name(0x201), Some("target"));
+}
+
+#[test]
+fn test_assemble_super_chip() {
+    let program = assemble(": main hires scroll-down 4 sprite v0 v0 0 exit").unwrap();
+    assert_eq!(
+        program.rom,
+        vec![0x00, 0xff, 0x00, 0xc4, 0xd0, 0x00, 0x00, 0xfd]
+    );
+    assert!(program.super_chip);
+    assert!(assemble(": main plane 3").is_err());
+    assert!(assemble(": main save v0 - v3").is_err());
+}
+
+#[test]
+fn test_options() {
+    let program = assemble(": main clear").unwrap();
+    let options = OctoOptions::parse(
+        &json!({
+            "tickrate": 30,
+            "vBlankQuirks": true,
+            "clipQuirks": true,
+            "backgroundColor": "#000000",
+            "fillColor": "#FF0000",
+        }),
+        &program,
+    )
+    .unwrap();
+    assert_eq!(options.platform, Platform::Chip8);
+    assert_eq!(options.ticks_per_frame, Some(30));
+    assert!(options.quirks.display_wait);
+    assert!(!options.quirks.wrap);
+    assert_eq!(
+        options.palette,
+        Some(Palette::parse("000000,ff0000").unwrap())
+    );
+
+    let options = OctoOptions::parse(&json!({ "shiftQuirks": true }), &program).unwrap();
+    assert_eq!(options.platform, Platform::SuperChip);
+    assert!(options.quirks.wrap);
+    assert_eq!(options.palette, None);
+}
+
+#[test]
+fn test_read_cartridge() {
+    let cartridge = create_cartridge(&json!({
+        "program": ": main\n  v0 := 1\n  loop again",
+        "options": { "tickrate": 100 },
+    }));
+    assert!(is_cartridge(&cartridge));
+
+    let (program, options) = read_cartridge(&cartridge).unwrap();
+    assert_eq!(program.rom, vec![0x60, 0x01, 0x12, 0x02]);
+    assert_eq!(options.ticks_per_frame, Some(100));
+
+    let cartridge = create_cartridge(&json!({ "options": {} }));
+    assert!(read_cartridge(&cartridge).is_err());
+    assert!(read_cartridge(b"GIF89a").is_err());
+}
@@ -0,0 +1,212 @@
+#[cfg(test)]
+#[path = "./options_test.rs"]
+mod options_test;
+
+use chip8_interpreter::chip8::MachineCodePolicy;
+use chip8_interpreter::framebuffer::Filter;
+use chip8_interpreter::palette::Palette;
+use chip8_interpreter::platform::Platform;
+use chip8_interpreter::quirks::Quirks;
+use chip8_interpreter::trace::{TraceFilter, TraceFormat};
+use chip8_interpreter::window::DEFAULT_SCALE;
+
+const USAGE: &str =
+    "usage: chip8-interpreter [ROM] [true] [--menu DIR] [--database FILE] [--platform chip8|schip|two-page|chip8x|megachip] \
+                     [--symbols FILE] [--palette THEME|RRGGBB,...] [--palette-file FILE] \
+                     [--scale N] [--integer-scale] \
+                     [--filter none|phosphor|blend] [--quirks LIST] \
+                     [--machine-code ignore|warn|halt] \
+                     [--trace FILE] [--trace-format text|state] \
+                     [--trace-pc START-END] [--trace-ops LIST] [--verify FILE] [--seed N] \
+                     [--profile FILE] [--monitor] [--gdb PORT] [--dap PORT] \
+                     [--screenshot FILE] [--record FILE] [--wav FILE] [--frames N] \
+                     [--headless]";
+
+pub struct Options {
+    pub cartridge: Option<String>,
+    pub menu: Option<String>,
+    pub database: Option<String>,
+    pub symbols: Option<String>,
+    pub platform: Platform,
+    pub palette: Palette,
+    pub scale: u32,
+    pub integer_scale: bool,
+    pub filter: Filter,
+    pub quirks: Quirks,
+    pub machine_code_policy: MachineCodePolicy,
+    pub trace: Option<String>,
+    pub trace_format: TraceFormat,
+    pub trace_filter: TraceFilter,
+    pub verify: Option<String>,
+    pub seed: Option<u64>,
+    pub profile: Option<String>,
+    pub monitor: bool,
+    pub gdb_port: Option<u16>,
+    pub dap_port: Option<u16>,
+    pub screenshot: Option<String>,
+    pub record: Option<String>,
+    pub wav: Option<String>,
+    pub frames: Option<u64>,
+    pub headless: bool,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut menu = None;
+        let mut database = None;
+        let mut symbols = None;
+        let mut platform = None;
+        let mut palette = Palette::default();
+        let mut scale = DEFAULT_SCALE;
+        let mut integer_scale = false;
+        let mut filter = Filter::None;
+        let mut quirks = Vec::new();
+        let mut machine_code_policy = MachineCodePolicy::Warn;
+        let mut trace = None;
+        let mut trace_format = TraceFormat::Text;
+        let mut trace_filter = TraceFilter::default();
+        let mut verify = None;
+        let mut seed = None;
+        let mut profile = None;
+        let mut monitor = false;
+        let mut gdb_port = None;
+        let mut dap_port = None;
+        let mut screenshot = None;
+        let mut record = None;
+        let mut wav = None;
+        let mut frames = None;
+        let mut headless = false;
+        let mut args = args.iter().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--menu" => menu = Some(value(&mut args, arg)?.to_string()),
+                "--database" => database = Some(value(&mut args, arg)?.to_string()),
+                "--symbols" => symbols = Some(value(&mut args, arg)?.to_string()),
+                "--platform" => platform = Some(Platform::parse(value(&mut args, arg)?)?),
+                "--palette" => palette = Palette::parse(value(&mut args, arg)?)?,
+                "--palette-file" => palette = Palette::load(value(&mut args, arg)?)?,
+                "--scale" => scale = parse_number(value(&mut args, arg)?, arg)?,
+                "--integer-scale" => integer_scale = true,
+                "--filter" => filter = Filter::parse(value(&mut args, arg)?)?,
+                "--quirks" => quirks.push(value(&mut args, arg)?),
+                "--machine-code" => {
+                    machine_code_policy = MachineCodePolicy::parse(value(&mut args, arg)?)?
+                }
+                "--trace" => trace = Some(value(&mut args, arg)?.to_string()),
+                "--trace-format" => trace_format = TraceFormat::parse(value(&mut args, arg)?)?,
+                "--trace-pc" => {
+                    trace_filter.pc_range =
+                        Some(TraceFilter::parse_pc_range(value(&mut args, arg)?)?)
+                }
+                "--trace-ops" => {
+                    trace_filter.classes = TraceFilter::parse_classes(value(&mut args, arg)?)?
+                }
+                "--verify" => verify = Some(value(&mut args, arg)?.to_string()),
+                "--seed" => seed = Some(parse_number(value(&mut args, arg)?, arg)?),
+                "--profile" => profile = Some(value(&mut args, arg)?.to_string()),
+                "--monitor" => monitor = true,
+                "--gdb" => gdb_port = Some(parse_number(value(&mut args, arg)?, arg)?),
+                "--dap" => dap_port = Some(parse_number(value(&mut args, arg)?, arg)?),
+                "--screenshot" => screenshot = Some(value(&mut args, arg)?.to_string()),
+                "--record" => record = Some(value(&mut args, arg)?.to_string()),
+                "--wav" => wav = Some(value(&mut args, arg)?.to_string()),
+                "--frames" => frames = Some(parse_number(value(&mut args, arg)?, arg)?),
+                "--headless" => headless = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+                _ => positional.push(arg.clone()),
+            }
+        }
+
+        let cartridge = positional.first().cloned();
+
+        if cartridge.is_none() && menu.is_none() {
+            return Err(USAGE.to_string());
+        }
+
+        if cartridge.is_none() && verify.is_some() {
+            return Err("option '--verify' requires a ROM".to_string());
+        }
+
+        if trace.is_some() && verify.is_some() {
+            return Err("options '--trace' and '--verify' can't be combined".to_string());
+        }
+
+        if headless && cartridge.is_none() {
+            return Err("option '--headless' requires a ROM".to_string());
+        }
+
+        if headless && frames.is_none() {
+            return Err("option '--headless' requires '--frames'".to_string());
+        }
+
+        if headless && (monitor || gdb_port.is_some() || dap_port.is_some()) {
+            return Err(
+                "option '--headless' can't be combined with '--monitor', '--gdb' or '--dap'"
+                    .to_string(),
+            );
+        }
+
+        if gdb_port.is_some() && dap_port.is_some() {
+            return Err("options '--gdb' and '--dap' can't be combined".to_string());
+        }
+
+        if scale == 0 {
+            return Err("option '--scale' must be at least 1".to_string());
+        }
+
+        // The second positional argument enables Super CHIP, as in earlier
+        // versions without the --platform option.
+        let platform = platform.unwrap_or(match positional.get(1) {
+            Some(arg) if arg.to_lowercase() == "true" => Platform::SuperChip,
+            _ => Platform::Chip8,
+        });
+
+        let mut platform_quirks = Quirks::new(platform);
+
+        for list in quirks {
+            platform_quirks.apply(list)?;
+        }
+
+        Ok(Options {
+            cartridge,
+            menu,
+            database,
+            symbols,
+            platform,
+            palette,
+            scale,
+            integer_scale,
+            filter,
+            quirks: platform_quirks,
+            machine_code_policy,
+            trace,
+            trace_format,
+            trace_filter,
+            verify,
+            seed,
+            profile,
+            monitor,
+            gdb_port,
+            dap_port,
+            screenshot,
+            record,
+            wav,
+            frames,
+            headless,
+        })
+    }
+}
+
+fn value<'a>(args: &mut impl Iterator<Item = &'a String>, option: &str) -> Result<&'a str, String> {
+    args.next()
+        .map(|value| value.as_str())
+        .ok_or(format!("option '{}' requires a value", option))
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, option: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for option '{}'", value, option))
+}
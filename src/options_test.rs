@@ -0,0 +1,140 @@
+use super::*;
+
+fn parse(args: &str) -> Result<Options, String> {
+    let args: Vec<String> = format!("chip8-interpreter {}", args)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    Options::parse(&args)
+}
+
+fn parse_error(args: &str) -> String {
+    parse(args).err().unwrap()
+}
+
+#[test]
+fn test_defaults() {
+    let options = parse("game.ch8").unwrap();
+    assert_eq!(options.cartridge, Some("game.ch8".to_string()));
+    assert_eq!(options.platform, Platform::Chip8);
+    assert_eq!(options.quirks, Quirks::new(Platform::Chip8));
+    assert_eq!(options.palette, Palette::default());
+    assert_eq!(options.scale, DEFAULT_SCALE);
+    assert_eq!(options.filter, Filter::None);
+    assert_eq!(options.machine_code_policy, MachineCodePolicy::Warn);
+    assert_eq!(options.trace_format, TraceFormat::Text);
+    assert!(!options.headless);
+    assert_eq!(options.frames, None);
+}
+
+#[test]
+fn test_options() {
+    let options = parse(
+        "game.ch8 --platform schip --palette amber --scale 4 --integer-scale \
+         --filter blend --machine-code halt --trace trace.log --trace-format state \
+         --seed 7 --frames 60 --headless --screenshot end.png",
+    )
+    .unwrap();
+    assert_eq!(options.platform, Platform::SuperChip);
+    assert_eq!(options.palette, Palette::parse("amber").unwrap());
+    assert_eq!(options.scale, 4);
+    assert!(options.integer_scale);
+    assert_eq!(options.filter, Filter::Blend);
+    assert_eq!(options.machine_code_policy, MachineCodePolicy::Halt);
+    assert_eq!(options.trace, Some("trace.log".to_string()));
+    assert_eq!(options.trace_format, TraceFormat::State);
+    assert_eq!(options.seed, Some(7));
+    assert_eq!(options.frames, Some(60));
+    assert!(options.headless);
+    assert_eq!(options.screenshot, Some("end.png".to_string()));
+}
+
+#[test]
+fn test_legacy_super_chip() {
+    assert_eq!(
+        parse("game.ch8 true").unwrap().platform,
+        Platform::SuperChip
+    );
+    assert_eq!(parse("game.ch8 false").unwrap().platform, Platform::Chip8);
+}
+
+#[test]
+fn test_quirks() {
+    let options =
+        parse("game.ch8 --quirks wrap,no-display-wait --quirks resolution-clear").unwrap();
+    assert!(options.quirks.wrap);
+    assert!(!options.quirks.display_wait);
+    assert!(options.quirks.resolution_clear);
+    assert!(parse("game.ch8 --quirks fast").is_err());
+}
+
+#[test]
+fn test_menu_without_rom() {
+    let options = parse("--menu roms").unwrap();
+    assert_eq!(options.cartridge, None);
+    assert_eq!(options.menu, Some("roms".to_string()));
+}
+
+#[test]
+fn test_invalid_values() {
+    assert_eq!(parse_error(""), USAGE);
+    assert_eq!(parse_error("game.ch8 --fast"), "unknown option '--fast'");
+    assert_eq!(
+        parse_error("game.ch8 --scale"),
+        "option '--scale' requires a value"
+    );
+    assert_eq!(
+        parse_error("game.ch8 --scale big"),
+        "invalid value 'big' for option '--scale'"
+    );
+    assert_eq!(
+        parse_error("game.ch8 --scale 0"),
+        "option '--scale' must be at least 1"
+    );
+    assert!(parse("game.ch8 --platform xo-chip").is_err());
+    assert!(parse("game.ch8 --gdb 70000").is_err());
+}
+
+#[test]
+fn test_headless_requirements() {
+    assert_eq!(
+        parse_error("game.ch8 --headless"),
+        "option '--headless' requires '--frames'"
+    );
+    assert_eq!(
+        parse_error("--menu roms --headless --frames 60"),
+        "option '--headless' requires a ROM"
+    );
+
+    for option in ["--monitor", "--gdb 1234", "--dap 1234"] {
+        let args = format!("game.ch8 --headless --frames 60 {}", option);
+        assert_eq!(
+            parse_error(&args),
+            "option '--headless' can't be combined with '--monitor', '--gdb' or '--dap'"
+        );
+    }
+}
+
+#[test]
+fn test_debug_servers_exclusive() {
+    assert_eq!(
+        parse_error("game.ch8 --gdb 1234 --dap 1235"),
+        "options '--gdb' and '--dap' can't be combined"
+    );
+    assert_eq!(parse("game.ch8 --gdb 1234").unwrap().gdb_port, Some(1234));
+    assert_eq!(parse("game.ch8 --dap 1235").unwrap().dap_port, Some(1235));
+}
+
+#[test]
+fn test_verify_requires_rom() {
+    assert_eq!(
+        parse_error("--menu roms --verify reference.log"),
+        "option '--verify' requires a ROM"
+    );
+    assert_eq!(
+        parse_error("game.ch8 --verify reference.log --trace trace.log"),
+        "options '--trace' and '--verify' can't be combined"
+    );
+    let options = parse("game.ch8 --verify reference.log").unwrap();
+    assert_eq!(options.verify, Some("reference.log".to_string()));
+}
@@ -0,0 +1,194 @@
+#[cfg(test)]
+#[path = "./overlay_test.rs"]
+mod overlay_test;
+
+use std::time::{Duration, Instant};
+
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+/// Empty pixels between characters and between lines.
+pub const GLYPH_SPACING: usize = 1;
+
+/// Time a message stays visible.
+const MESSAGE_DURATION: Duration = Duration::from_secs(2);
+/// Interval the frame rate and instruction rate are averaged over.
+const MEASURE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Lines of text drawn over the display: the frame rate, the instruction
+/// rate and a status while visible, and recent messages at all times.
+pub struct Overlay {
+    visible: bool,
+    messages: Vec<(String, Instant)>,
+    measure_start: Option<(Instant, u64)>,
+    frames: u64,
+    fps: f64,
+    ips: f64,
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Overlay {
+            visible: false,
+            messages: Vec::new(),
+            measure_start: None,
+            frames: 0,
+            fps: 0.0,
+            ips: 0.0,
+        }
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Shows a message for a moment.
+    pub fn notify(&mut self, message: &str, now: Instant) {
+        self.messages
+            .push((message.to_string(), now + MESSAGE_DURATION));
+    }
+
+    /// Counts a displayed frame and the instructions executed so far, and
+    /// drops the expired messages.
+    pub fn end_frame(&mut self, now: Instant, cycles: u64) {
+        self.messages.retain(|(_, end)| *end > now);
+
+        let (start, start_cycles) = match self.measure_start {
+            Some(start) => start,
+            None => {
+                self.measure_start = Some((now, cycles));
+                return;
+            }
+        };
+
+        self.frames += 1;
+        let elapsed = now.duration_since(start);
+
+        if elapsed >= MEASURE_INTERVAL {
+            let seconds = elapsed.as_secs_f64();
+            self.fps = self.frames as f64 / seconds;
+            // A reset starts counting the cycles from zero again.
+            self.ips = cycles.saturating_sub(start_cycles) as f64 / seconds;
+            self.measure_start = Some((now, cycles));
+            self.frames = 0;
+        }
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    pub fn ips(&self) -> f64 {
+        self.ips
+    }
+
+    /// Returns the lines to draw, with the `status` lines after the rates.
+    pub fn lines(&self, status: &[String]) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        if self.visible {
+            lines.push(format!("{:.0} FPS  {:.0} IPS", self.fps, self.ips));
+            lines.extend(status.iter().cloned());
+        }
+
+        lines.extend(self.messages.iter().map(|(message, _)| message.clone()));
+        lines
+    }
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Overlay::new()
+    }
+}
+
+pub fn text_width(text: &str) -> usize {
+    let count = text.chars().count();
+    (count * (GLYPH_WIDTH + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING)
+}
+
+/// Returns the pixels set by `text` as x and y relative to its top left
+/// corner.
+pub fn text_pixels(text: &str) -> Vec<(usize, usize)> {
+    let mut pixels = Vec::new();
+
+    for (index, character) in text.chars().enumerate() {
+        let left = index * (GLYPH_WIDTH + GLYPH_SPACING);
+
+        for (y, row) in glyph(character).iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if row & (0b100 >> x) != 0 {
+                    pixels.push((left + x, y));
+                }
+            }
+        }
+    }
+
+    pixels
+}
+
+/// Rows of the 3x5 glyph of `character`, with the leftmost pixel in bit 2.
+/// Lowercase letters are shown as uppercase ones and unknown characters as
+/// a question mark.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [7, 1, 7, 4, 7],
+        '3' => [7, 1, 7, 1, 7],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 7, 1, 7],
+        '6' => [7, 4, 7, 5, 7],
+        '7' => [7, 1, 1, 1, 1],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 7],
+        'A' => [2, 5, 7, 5, 5],
+        'B' => [6, 5, 6, 5, 6],
+        'C' => [3, 4, 4, 4, 3],
+        'D' => [6, 5, 5, 5, 6],
+        'E' => [7, 4, 6, 4, 7],
+        'F' => [7, 4, 6, 4, 4],
+        'G' => [3, 4, 5, 5, 3],
+        'H' => [5, 5, 7, 5, 5],
+        'I' => [7, 2, 2, 2, 7],
+        'J' => [1, 1, 1, 5, 2],
+        'K' => [5, 5, 6, 5, 5],
+        'L' => [4, 4, 4, 4, 7],
+        'M' => [5, 7, 7, 5, 5],
+        'N' => [6, 5, 5, 5, 5],
+        'O' => [2, 5, 5, 5, 2],
+        'P' => [6, 5, 6, 4, 4],
+        'Q' => [2, 5, 5, 6, 3],
+        'R' => [6, 5, 6, 5, 5],
+        'S' => [3, 4, 2, 1, 6],
+        'T' => [7, 2, 2, 2, 2],
+        'U' => [5, 5, 5, 5, 7],
+        'V' => [5, 5, 5, 5, 2],
+        'W' => [5, 5, 7, 7, 5],
+        'X' => [5, 5, 2, 5, 5],
+        'Y' => [5, 5, 2, 2, 2],
+        'Z' => [7, 1, 2, 4, 7],
+        ' ' => [0, 0, 0, 0, 0],
+        '.' => [0, 0, 0, 0, 2],
+        ',' => [0, 0, 0, 2, 4],
+        ':' => [0, 2, 0, 2, 0],
+        '-' => [0, 0, 7, 0, 0],
+        '+' => [0, 2, 7, 2, 0],
+        '=' => [0, 7, 0, 7, 0],
+        '_' => [0, 0, 0, 0, 7],
+        '/' => [1, 1, 2, 4, 4],
+        '%' => [5, 1, 2, 4, 5],
+        '(' => [1, 2, 2, 2, 1],
+        ')' => [4, 2, 2, 2, 4],
+        '[' => [3, 2, 2, 2, 3],
+        ']' => [6, 2, 2, 2, 6],
+        '<' => [1, 2, 4, 2, 1],
+        '>' => [4, 2, 1, 2, 4],
+        '!' => [2, 2, 2, 0, 2],
+        '\'' => [2, 2, 0, 0, 0],
+        _ => [7, 1, 2, 0, 2],
+    }
+}
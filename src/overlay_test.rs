@@ -0,0 +1,67 @@
+use super::*;
+
+#[test]
+fn test_text_pixels() {
+    assert_eq!(
+        text_pixels("1"),
+        vec![
+            (1, 0),
+            (0, 1),
+            (1, 1),
+            (1, 2),
+            (1, 3),
+            (0, 4),
+            (1, 4),
+            (2, 4)
+        ]
+    );
+    assert_eq!(text_pixels(" ."), vec![(5, 4)]);
+    assert_eq!(text_pixels("a"), text_pixels("A"));
+}
+
+#[test]
+fn test_text_width() {
+    assert_eq!(text_width(""), 0);
+    assert_eq!(text_width("A"), 3);
+    assert_eq!(text_width("FPS"), 11);
+}
+
+#[test]
+fn test_messages() {
+    let now = Instant::now();
+    let mut overlay = Overlay::new();
+    overlay.notify("Paused", now);
+    assert_eq!(overlay.lines(&[]), vec!["Paused".to_string()]);
+
+    overlay.end_frame(now + Duration::from_secs(1), 0);
+    assert_eq!(overlay.lines(&[]).len(), 1);
+    overlay.end_frame(now + Duration::from_secs(3), 0);
+    assert!(overlay.lines(&[]).is_empty());
+}
+
+#[test]
+fn test_status() {
+    let mut overlay = Overlay::new();
+    let status = vec!["CHIP-8".to_string()];
+    assert!(overlay.lines(&status).is_empty());
+
+    overlay.toggle();
+    assert!(overlay.visible());
+    assert_eq!(
+        overlay.lines(&status),
+        vec!["0 FPS  0 IPS".to_string(), "CHIP-8".to_string()]
+    );
+}
+
+#[test]
+fn test_rates() {
+    let now = Instant::now();
+    let mut overlay = Overlay::new();
+
+    for frame in 0..=60 {
+        overlay.end_frame(now + Duration::from_millis(frame * 50 / 3), frame * 8);
+    }
+
+    assert_eq!(overlay.fps().round(), 60.0);
+    assert_eq!(overlay.ips().round(), 480.0);
+}
@@ -0,0 +1,140 @@
+#[cfg(test)]
+#[path = "./palette_test.rs"]
+mod palette_test;
+
+use sdl2::pixels::Color;
+use std::fs;
+
+pub const PALETTE_SIZE: usize = 16;
+
+const CLASSIC: [Color; 4] = [
+    Color::RGB(0x00, 0x00, 0x00),
+    Color::RGB(0xFF, 0xFF, 0xFF),
+    Color::RGB(0xAA, 0xAA, 0xAA),
+    Color::RGB(0x55, 0x55, 0x55),
+];
+
+const AMBER: [Color; 4] = [
+    Color::RGB(0x1A, 0x0F, 0x00),
+    Color::RGB(0xFF, 0xB0, 0x00),
+    Color::RGB(0xB3, 0x6B, 0x00),
+    Color::RGB(0x66, 0x3D, 0x00),
+];
+
+const GREEN: [Color; 4] = [
+    Color::RGB(0x00, 0x14, 0x00),
+    Color::RGB(0x33, 0xFF, 0x33),
+    Color::RGB(0x1F, 0x99, 0x1F),
+    Color::RGB(0x0F, 0x4D, 0x0F),
+];
+
+const LCD: [Color; 4] = [
+    Color::RGB(0x9B, 0xBC, 0x0F),
+    Color::RGB(0x0F, 0x38, 0x0F),
+    Color::RGB(0x30, 0x62, 0x30),
+    Color::RGB(0x8B, 0xAC, 0x0F),
+];
+
+const OCTO: [Color; 4] = [
+    Color::RGB(0x99, 0x66, 0x00),
+    Color::RGB(0xFF, 0xCC, 0x00),
+    Color::RGB(0xFF, 0x66, 0x00),
+    Color::RGB(0x66, 0x22, 0x00),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    colors: [Color; PALETTE_SIZE],
+}
+
+impl Palette {
+    /// Builds a palette from up to 16 colors. Entries that are not given
+    /// fall back to the foreground color, so every non-zero VRAM value is
+    /// visible.
+    pub fn new(colors: &[Color]) -> Self {
+        let background = colors.first().copied().unwrap_or(CLASSIC[0]);
+        let foreground = colors.get(1).copied().unwrap_or(CLASSIC[1]);
+        let mut palette = [foreground; PALETTE_SIZE];
+        palette[0] = background;
+
+        for (i, &color) in colors.iter().take(PALETTE_SIZE).enumerate() {
+            palette[i] = color;
+        }
+
+        Palette { colors: palette }
+    }
+
+    pub fn from_theme(name: &str) -> Option<Self> {
+        let colors = match name.to_lowercase().as_str() {
+            "classic" => CLASSIC,
+            "amber" => AMBER,
+            "green" => GREEN,
+            "lcd" => LCD,
+            "octo" => OCTO,
+            _ => return None,
+        };
+
+        Some(Palette::new(&colors))
+    }
+
+    /// Parses either a theme name or a comma-separated list of hex colors,
+    /// e.g. `000000,ffffff`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if let Some(palette) = Palette::from_theme(value) {
+            return Ok(palette);
+        }
+
+        let colors = value
+            .split(',')
+            .map(parse_hex_color)
+            .collect::<Result<Vec<Color>, String>>()?;
+
+        if colors.len() > PALETTE_SIZE {
+            return Err(format!(
+                "palette has {} colors, at most {} are supported",
+                colors.len(),
+                PALETTE_SIZE
+            ));
+        }
+
+        Ok(Palette::new(&colors))
+    }
+
+    /// Reads a palette file with a theme name or colors, separated by
+    /// commas or line breaks. Text after a `;` is a comment.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read palette '{}': {}", path, err))?;
+        let value = text
+            .lines()
+            .map(|line| line.split(';').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        Palette::parse(&value).map_err(|err| format!("invalid palette '{}': {}", path, err))
+    }
+
+    pub fn color(&self, value: u8) -> Color {
+        self.colors[value as usize % PALETTE_SIZE]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::new(&CLASSIC)
+    }
+}
+
+fn parse_hex_color(value: &str) -> Result<Color, String> {
+    let hex = value.trim().trim_start_matches('#');
+
+    if hex.len() != 6 {
+        return Err(format!("invalid color '{}', expected RRGGBB", value));
+    }
+
+    let rgb = u32::from_str_radix(hex, 16)
+        .map_err(|_| format!("invalid color '{}', expected RRGGBB", value))?;
+
+    Ok(Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
@@ -0,0 +1,64 @@
+use super::*;
+use std::env;
+
+#[test]
+fn test_theme() {
+    let palette = Palette::parse("amber").unwrap();
+    assert_eq!(palette.color(0), AMBER[0]);
+    assert_eq!(palette.color(1), AMBER[1]);
+    assert_eq!(palette.color(3), AMBER[3]);
+}
+
+#[test]
+fn test_theme_case_insensitive() {
+    assert_eq!(Palette::parse("LCD"), Palette::parse("lcd"));
+}
+
+#[test]
+fn test_hex_colors() {
+    let palette = Palette::parse("#102030,ffeedd").unwrap();
+    assert_eq!(palette.color(0), Color::RGB(0x10, 0x20, 0x30));
+    assert_eq!(palette.color(1), Color::RGB(0xFF, 0xEE, 0xDD));
+}
+
+#[test]
+fn test_missing_entries_use_foreground() {
+    let palette = Palette::parse("000000,ff0000").unwrap();
+
+    for value in 1..PALETTE_SIZE as u8 {
+        assert_eq!(palette.color(value), Color::RGB(0xFF, 0x00, 0x00));
+    }
+}
+
+#[test]
+fn test_invalid_color() {
+    assert!(Palette::parse("fff").is_err());
+    assert!(Palette::parse("00000g").is_err());
+    assert!(Palette::parse("purple").is_err());
+}
+
+#[test]
+fn test_too_many_colors() {
+    let colors = vec!["000000"; PALETTE_SIZE + 1].join(",");
+    assert!(Palette::parse(&colors).is_err());
+}
+
+#[test]
+fn test_load() {
+    let path = env::temp_dir().join("chip8-palette.txt");
+    fs::write(&path, "; dark background\n#102030\nffeedd, 00ff00\n\n").unwrap();
+    let palette = Palette::load(path.to_str().unwrap()).unwrap();
+    assert_eq!(palette.color(0), Color::RGB(0x10, 0x20, 0x30));
+    assert_eq!(palette.color(1), Color::RGB(0xFF, 0xEE, 0xDD));
+    assert_eq!(palette.color(2), Color::RGB(0x00, 0xFF, 0x00));
+
+    fs::write(&path, "amber\n").unwrap();
+    assert_eq!(
+        Palette::load(path.to_str().unwrap()),
+        Palette::parse("amber")
+    );
+
+    fs::write(&path, "purple\n").unwrap();
+    assert!(Palette::load(path.to_str().unwrap()).is_err());
+    assert!(Palette::load("missing.txt").is_err());
+}
@@ -0,0 +1,104 @@
+use crate::window::{HEIGHT_LO_RES, WIDTH_LO_RES};
+
+const CHIP8_RAM: usize = 1024 * 32;
+const MEGA_CHIP_RAM: usize = 1 << 24;
+const PROGRAM_START: usize = 0x200;
+const TWO_PAGE_PROGRAM_START: usize = 0x260;
+const TWO_PAGE_HEIGHT: usize = 64;
+const CHIP8X_PROGRAM_START: usize = 0x300;
+
+/// The CHIP-8 variant a ROM was written for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    /// The 64x64 two-page display variant of the COSMAC VIP interpreter.
+    TwoPage,
+    /// CHIP-8X for the COSMAC VIP with the VP-590 color board.
+    Chip8X,
+    /// MEGA-CHIP, a Super CHIP extension with a 256x192 true color mode.
+    MegaChip,
+}
+
+impl Platform {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "chip8" | "chip-8" => Ok(Platform::Chip8),
+            "schip" | "superchip" | "super-chip" => Ok(Platform::SuperChip),
+            "two-page" | "chip8-64x64" => Ok(Platform::TwoPage),
+            "chip8x" | "chip-8x" => Ok(Platform::Chip8X),
+            "megachip" | "mega-chip" => Ok(Platform::MegaChip),
+            _ => Err(format!("unknown platform '{}'", value)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "CHIP-8",
+            Platform::SuperChip => "SCHIP",
+            Platform::TwoPage => "CHIP-8 two-page",
+            Platform::Chip8X => "CHIP-8X",
+            Platform::MegaChip => "MEGA-CHIP",
+        }
+    }
+
+    /// Whether the platform supports the Super CHIP instructions.
+    pub fn is_super_chip(self) -> bool {
+        matches!(self, Platform::SuperChip | Platform::MegaChip)
+    }
+
+    pub fn has_color(self) -> bool {
+        self == Platform::Chip8X
+    }
+
+    pub fn cpu_frequency(self) -> u64 {
+        match self {
+            Platform::Chip8 | Platform::TwoPage | Platform::Chip8X => 500,
+            Platform::SuperChip | Platform::MegaChip => 4000,
+        }
+    }
+
+    /// MEGA-CHIP uses 24-bit addresses for its large sprite and sound data.
+    pub fn ram_size(self) -> usize {
+        match self {
+            Platform::MegaChip => MEGA_CHIP_RAM,
+            _ => CHIP8_RAM,
+        }
+    }
+
+    /// Address the ROM is copied to. Two-page ROMs are usually dumped from
+    /// 0x200 and start with a jump over the patched interpreter to 0x260;
+    /// ROMs without that header only contain the program itself.
+    pub fn load_address(self, rom: &[u8]) -> usize {
+        match self {
+            Platform::TwoPage if !rom.starts_with(&[0x12, 0x60]) => TWO_PAGE_PROGRAM_START,
+            Platform::Chip8X => CHIP8X_PROGRAM_START,
+            _ => PROGRAM_START,
+        }
+    }
+
+    pub fn start_address(self) -> u16 {
+        match self {
+            Platform::TwoPage => TWO_PAGE_PROGRAM_START as u16,
+            Platform::Chip8X => CHIP8X_PROGRAM_START as u16,
+            _ => PROGRAM_START as u16,
+        }
+    }
+
+    /// Display size outside of the SCHIP hi-res mode.
+    pub fn lo_res_size(self) -> (usize, usize) {
+        match self {
+            Platform::TwoPage => (WIDTH_LO_RES, TWO_PAGE_HEIGHT),
+            _ => (WIDTH_LO_RES, HEIGHT_LO_RES),
+        }
+    }
+
+    /// Address of the 1802 routine that clears the display, if the platform
+    /// has one that is called with 0NNN instead of 00E0.
+    pub fn clear_routine(self) -> Option<u16> {
+        match self {
+            Platform::TwoPage => Some(0x230),
+            _ => None,
+        }
+    }
+}
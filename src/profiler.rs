@@ -0,0 +1,337 @@
+#[cfg(test)]
+#[path = "./profiler_test.rs"]
+mod profiler_test;
+
+use crate::disassembler::{disassemble, disassemble_with_symbols};
+use crate::platform::Platform;
+use crate::symbols::Symbols;
+use crate::trace::TraceEntry;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Number of addresses listed as hot spots.
+const HOT_ADDRESSES: usize = 20;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RoutineStats {
+    pub calls: u64,
+    /// Instructions run by the routine and everything it called.
+    pub inclusive: u64,
+    /// Instructions run by the routine itself.
+    pub exclusive: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CallStats {
+    pub calls: u64,
+    pub inclusive: u64,
+}
+
+struct Frame {
+    routine: u16,
+    start: u64,
+}
+
+/// Counts where the instructions are spent. Subroutines are found through
+/// 2NNN and 00EE; the code before the first call belongs to the routine at
+/// the start address.
+pub struct Profiler {
+    platform: Platform,
+    symbols: Symbols,
+    instructions: u64,
+    executions: HashMap<u16, (u16, u64)>,
+    opcodes: HashMap<u16, u64>,
+    root: u16,
+    frames: Vec<Frame>,
+    routines: HashMap<u16, RoutineStats>,
+    calls: HashMap<(u16, u16), CallStats>,
+    draws: u64,
+    frame_draws: Vec<u64>,
+}
+
+impl Profiler {
+    pub fn new(platform: Platform) -> Self {
+        let root = platform.start_address();
+        let mut routines = HashMap::new();
+        routines.insert(root, RoutineStats::default());
+
+        Profiler {
+            platform,
+            symbols: Symbols::new(),
+            instructions: 0,
+            executions: HashMap::new(),
+            opcodes: HashMap::new(),
+            root,
+            frames: Vec::new(),
+            routines,
+            calls: HashMap::new(),
+            draws: 0,
+            frame_draws: Vec::new(),
+        }
+    }
+
+    /// Names the addresses and routines in the report.
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    pub fn record(&mut self, entry: &TraceEntry) {
+        let pc = entry.before.pc;
+        let opcode = entry.opcode;
+        let routine = self.current_routine();
+
+        self.instructions += 1;
+        self.executions.entry(pc).or_insert((opcode, 0)).1 += 1;
+        *self.opcodes.entry(opcode).or_insert(0) += 1;
+        self.routines.entry(routine).or_default().exclusive += 1;
+
+        if opcode & 0xF000 == 0xD000 {
+            self.draws += 1;
+        }
+
+        if opcode & 0xF000 == 0x2000 && entry.after.sp > entry.before.sp {
+            let callee = entry.after.pc;
+            self.routines.entry(callee).or_default().calls += 1;
+            self.calls.entry((routine, callee)).or_default().calls += 1;
+            self.frames.push(Frame {
+                routine: callee,
+                start: self.instructions,
+            });
+        } else if opcode == 0x00EE && entry.after.sp < entry.before.sp {
+            if let Some(frame) = self.frames.pop() {
+                self.close_frame(&frame);
+            }
+        }
+    }
+
+    /// Ends the current display frame for the sprite draw statistics.
+    pub fn end_frame(&mut self) {
+        self.frame_draws.push(self.draws);
+        self.draws = 0;
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn executions(&self, pc: u16) -> u64 {
+        self.executions.get(&pc).map_or(0, |&(_, count)| count)
+    }
+
+    /// Returns the statistics of the routine starting at `address`,
+    /// including the calls that haven't returned yet.
+    pub fn routine(&self, address: u16) -> RoutineStats {
+        let mut stats = self.routines.get(&address).copied().unwrap_or_default();
+        stats.inclusive += self.open_inclusive(address);
+
+        if address == self.root {
+            stats.inclusive = self.instructions;
+        }
+
+        stats
+    }
+
+    pub fn call(&self, caller: u16, callee: u16) -> CallStats {
+        self.calls
+            .get(&(caller, callee))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn frame_draws(&self) -> &[u64] {
+        &self.frame_draws
+    }
+
+    pub fn write_report(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|err| format!("failed to create profile '{}': {}", path, err))?;
+
+        let mut writer = BufWriter::new(file);
+        self.report(&mut writer)
+            .and_then(|_| writer.flush())
+            .map_err(|err| format!("failed to write profile '{}': {}", path, err))
+    }
+
+    pub fn report(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(writer, "Instructions: {}", self.instructions)?;
+        self.report_hot_addresses(writer)?;
+        self.report_opcodes(writer)?;
+        self.report_call_graph(writer)?;
+        self.report_draws(writer)?;
+        self.report_disassembly(writer)
+    }
+
+    fn current_routine(&self) -> u16 {
+        self.frames.last().map_or(self.root, |frame| frame.routine)
+    }
+
+    fn close_frame(&mut self, frame: &Frame) {
+        let inclusive = self.instructions - frame.start;
+        let caller = self.current_routine();
+        self.routines.entry(frame.routine).or_default().inclusive += inclusive;
+        self.calls
+            .entry((caller, frame.routine))
+            .or_default()
+            .inclusive += inclusive;
+    }
+
+    fn open_inclusive(&self, address: u16) -> u64 {
+        self.frames
+            .iter()
+            .filter(|frame| frame.routine == address)
+            .map(|frame| self.instructions - frame.start)
+            .sum()
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        if self.instructions == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / self.instructions as f64
+        }
+    }
+
+    fn routine_name(&self, address: u16) -> String {
+        match self.symbols.name(address) {
+            Some(name) => name.to_string(),
+            None => format!("{:04X}", address),
+        }
+    }
+
+    fn report_hot_addresses(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut addresses: Vec<_> = self.executions.iter().collect();
+        addresses.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(b.0)));
+
+        writeln!(writer, "\nHot addresses:")?;
+
+        for (pc, (opcode, count)) in addresses.into_iter().take(HOT_ADDRESSES) {
+            writeln!(
+                writer,
+                "{:>12} {:>6.2}%  {}  {}",
+                count,
+                self.percent(*count),
+                self.symbols.describe(*pc),
+                disassemble_with_symbols(*opcode, self.platform, &self.symbols)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn report_opcodes(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut mnemonics: BTreeMap<String, u64> = BTreeMap::new();
+
+        for (&opcode, &count) in &self.opcodes {
+            let mnemonic = disassemble(opcode, self.platform);
+            let name = mnemonic.split_whitespace().next().unwrap_or_default();
+            *mnemonics.entry(name.to_string()).or_insert(0) += count;
+        }
+
+        let mut mnemonics: Vec<_> = mnemonics.into_iter().collect();
+        mnemonics.sort_by_key(|&(_, count)| Reverse(count));
+
+        writeln!(writer, "\nOpcode types:")?;
+
+        for (name, count) in mnemonics {
+            writeln!(
+                writer,
+                "{:>12} {:>6.2}%  {}",
+                count,
+                self.percent(count),
+                name
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn report_call_graph(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut routines: Vec<u16> = self.routines.keys().copied().collect();
+        routines.sort_by_key(|&address| (Reverse(self.routine(address).inclusive), address));
+
+        writeln!(writer, "\nCall graph:")?;
+        writeln!(
+            writer,
+            "{:>8} {:>12} {:>12} {:>12}",
+            "routine", "calls", "inclusive", "exclusive"
+        )?;
+
+        for address in routines {
+            let stats = self.routine(address);
+            writeln!(
+                writer,
+                "{:>8} {:>12} {:>12} {:>12}",
+                self.routine_name(address),
+                stats.calls,
+                stats.inclusive,
+                stats.exclusive
+            )?;
+
+            let mut callees: Vec<_> = self
+                .calls
+                .iter()
+                .filter(|((caller, _), _)| *caller == address)
+                .collect();
+            callees.sort_by_key(|((_, callee), _)| *callee);
+
+            for ((_, callee), call) in callees {
+                writeln!(
+                    writer,
+                    "      -> {:<4} {:>9} {:>12}",
+                    self.routine_name(*callee),
+                    call.calls,
+                    call.inclusive
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn report_draws(&self, writer: &mut impl Write) -> io::Result<()> {
+        let frames = self.frame_draws.len() as u64;
+        let total: u64 = self.frame_draws.iter().sum();
+        let max = self.frame_draws.iter().max().copied().unwrap_or(0);
+        let average = if frames == 0 {
+            0.0
+        } else {
+            total as f64 / frames as f64
+        };
+
+        writeln!(writer, "\nSprite draws:")?;
+        writeln!(writer, "{:>12} frames", frames)?;
+        writeln!(writer, "{:>12.2} per frame on average", average)?;
+        writeln!(writer, "{:>12} in the busiest frame", max)
+    }
+
+    fn report_disassembly(&self, writer: &mut impl Write) -> io::Result<()> {
+        let executions: BTreeMap<_, _> = self.executions.iter().collect();
+
+        writeln!(writer, "\nAnnotated disassembly:")?;
+
+        for (&pc, &(opcode, count)) in executions {
+            if let Some(stats) = self.routines.get(&pc).filter(|_| pc != self.root) {
+                writeln!(
+                    writer,
+                    "\n; sub {}, {} calls",
+                    self.routine_name(pc),
+                    stats.calls
+                )?;
+            }
+
+            writeln!(
+                writer,
+                "{:>12} {:>6.2}%  {:04X}  {:04X}  {}",
+                count,
+                self.percent(count),
+                pc,
+                opcode,
+                disassemble_with_symbols(opcode, self.platform, &self.symbols)
+            )?;
+        }
+
+        Ok(())
+    }
+}
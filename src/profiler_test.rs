@@ -0,0 +1,149 @@
+use super::*;
+use crate::chip8::CpuState;
+
+const START: u16 = 0x200;
+
+/// Builds the trace of a program, given as address and opcode pairs. Calls
+/// and returns move the stack pointer.
+fn create_entries(program: &[(u16, u16)]) -> Vec<TraceEntry> {
+    let mut sp = 0;
+    let mut entries = Vec::new();
+
+    for (cycle, window) in program.windows(2).enumerate() {
+        let ((pc, opcode), (next, _)) = (window[0], window[1]);
+        let mut state = CpuState {
+            pc,
+            i: 0,
+            v: [0; 16],
+            sp,
+            stack: [0; 16],
+            dt: 0,
+            st: 0,
+        };
+        let before = state;
+
+        if opcode & 0xF000 == 0x2000 {
+            sp += 1;
+        } else if opcode == 0x00EE {
+            sp -= 1;
+        }
+
+        state.pc = next;
+        state.sp = sp;
+        entries.push(TraceEntry {
+            cycle: cycle as u64,
+            opcode,
+            before,
+            after: state,
+            writes: Vec::new(),
+        });
+    }
+
+    entries
+}
+
+fn create_profiler() -> Profiler {
+    // main: CALL 300, CALL 300, JP 200; sub 300: LD, DRW, RET
+    let program = [
+        (0x200, 0x2300),
+        (0x300, 0x6001),
+        (0x302, 0xd015),
+        (0x304, 0x00ee),
+        (0x202, 0x2300),
+        (0x300, 0x6001),
+        (0x302, 0xd015),
+        (0x304, 0x00ee),
+        (0x204, 0x1200),
+        (0x200, 0x0000),
+    ];
+
+    let mut profiler = Profiler::new(Platform::Chip8);
+
+    for entry in create_entries(&program) {
+        profiler.record(&entry);
+    }
+
+    profiler
+}
+
+#[test]
+fn test_executions() {
+    let profiler = create_profiler();
+    assert_eq!(profiler.instructions(), 9);
+    assert_eq!(profiler.executions(0x300), 2);
+    assert_eq!(profiler.executions(0x204), 1);
+    assert_eq!(profiler.executions(0x206), 0);
+}
+
+#[test]
+fn test_call_graph() {
+    let profiler = create_profiler();
+    let sub = profiler.routine(0x300);
+    assert_eq!(sub.calls, 2);
+    assert_eq!(sub.inclusive, 6);
+    assert_eq!(sub.exclusive, 6);
+
+    let main = profiler.routine(START);
+    assert_eq!(main.inclusive, 9);
+    assert_eq!(main.exclusive, 3);
+    assert_eq!(
+        profiler.call(START, 0x300),
+        CallStats {
+            calls: 2,
+            inclusive: 6
+        }
+    );
+}
+
+#[test]
+fn test_open_call_is_inclusive() {
+    let program = [
+        (0x200, 0x2300),
+        (0x300, 0x6001),
+        (0x302, 0x1302),
+        (0x302, 0),
+    ];
+    let mut profiler = Profiler::new(Platform::Chip8);
+
+    for entry in create_entries(&program) {
+        profiler.record(&entry);
+    }
+
+    assert_eq!(profiler.routine(0x300).inclusive, 2);
+}
+
+#[test]
+fn test_frame_draws() {
+    let mut profiler = create_profiler();
+    profiler.end_frame();
+    profiler.end_frame();
+    assert_eq!(profiler.frame_draws(), &[2, 0]);
+}
+
+#[test]
+fn test_report() {
+    let mut profiler = create_profiler();
+    profiler.end_frame();
+    let mut report = Vec::new();
+    profiler.report(&mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    assert!(report.contains("Instructions: 9"));
+    assert!(report.contains("; sub 0300, 2 calls"));
+    assert!(report.contains("0302  D015  DRW V0, V1, 5"));
+    assert!(report.contains("      -> 0300         2            6"));
+}
+
+#[test]
+fn test_report_with_symbols() {
+    let mut profiler = create_profiler();
+    let mut symbols = Symbols::new();
+    symbols.insert(0x300, "draw");
+    profiler.set_symbols(symbols);
+    let mut report = Vec::new();
+    profiler.report(&mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    assert!(report.contains("; sub draw, 2 calls"));
+    assert!(report.contains("0200  2300  CALL draw"));
+    assert!(report.contains("0302 <draw+2>  DRW V0, V1, 5"));
+    assert!(report.contains("      -> draw         2            6"));
+}
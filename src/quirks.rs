@@ -0,0 +1,78 @@
+#[cfg(test)]
+#[path = "./quirks_test.rs"]
+mod quirks_test;
+
+use crate::platform::Platform;
+
+/// Gives access to the flag of a quirk.
+type Flag = fn(&mut Quirks) -> &mut bool;
+
+/// Names of the quirks with the flags they stand for.
+const QUIRKS: [(&str, Flag); 4] = [
+    ("display-wait", |quirks| &mut quirks.display_wait),
+    ("wrap", |quirks| &mut quirks.wrap),
+    ("resolution-clear", |quirks| &mut quirks.resolution_clear),
+    ("double-lo-res", |quirks| &mut quirks.double_lo_res),
+];
+
+/// Behavioral differences between CHIP-8 implementations that ROMs may rely
+/// on. The defaults match the platform selected on the command line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    /// DXYN waits for the next vertical blank, as on the COSMAC VIP, which
+    /// limits programs to one sprite draw per frame.
+    pub display_wait: bool,
+    /// Sprites drawn across a screen edge wrap around to the opposite side
+    /// instead of being clipped.
+    pub wrap: bool,
+    /// Switching between lo-res and hi-res with 00FE/00FF clears the
+    /// screen, as on modern SCHIP and XO-CHIP.
+    pub resolution_clear: bool,
+    /// Lo-res pixels are drawn as 2x2 blocks on the 128x64 display, as on the
+    /// HP-48, instead of using the top-left quarter of it.
+    pub double_lo_res: bool,
+}
+
+impl Quirks {
+    pub fn new(platform: Platform) -> Self {
+        Quirks {
+            display_wait: false,
+            wrap: false,
+            resolution_clear: false,
+            double_lo_res: platform.is_super_chip(),
+        }
+    }
+
+    /// Applies a comma-separated list of quirk names. A `no-` prefix turns
+    /// the quirk off, e.g. `display-wait,no-display-wait`.
+    pub fn apply(&mut self, list: &str) -> Result<(), String> {
+        for name in list
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let (name, enabled) = match name.strip_prefix("no-") {
+                Some(name) => (name, false),
+                None => (name, true),
+            };
+
+            let (_, flag) = QUIRKS
+                .iter()
+                .find(|(quirk, _)| *quirk == name)
+                .ok_or(format!("unknown quirk '{}'", name))?;
+            *flag(self) = enabled;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the names of the quirks that are switched on.
+    pub fn enabled(&self) -> Vec<&'static str> {
+        let mut quirks = *self;
+        QUIRKS
+            .iter()
+            .filter(|(_, flag)| *flag(&mut quirks))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
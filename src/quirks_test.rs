@@ -0,0 +1,52 @@
+use super::*;
+
+#[test]
+fn test_defaults() {
+    assert!(Quirks::new(Platform::Chip8).enabled().is_empty());
+    assert_eq!(
+        Quirks::new(Platform::SuperChip).enabled(),
+        vec!["double-lo-res"]
+    );
+}
+
+#[test]
+fn test_apply() {
+    let mut quirks = Quirks::new(Platform::Chip8);
+    quirks
+        .apply("display-wait, wrap,,resolution-clear,double-lo-res")
+        .unwrap();
+    assert!(quirks.display_wait);
+    assert!(quirks.wrap);
+    assert!(quirks.resolution_clear);
+    assert!(quirks.double_lo_res);
+    assert_eq!(
+        quirks.enabled(),
+        vec!["display-wait", "wrap", "resolution-clear", "double-lo-res"]
+    );
+}
+
+#[test]
+fn test_apply_no_prefix() {
+    let mut quirks = Quirks::new(Platform::SuperChip);
+    quirks.apply("no-double-lo-res,wrap").unwrap();
+    assert!(!quirks.double_lo_res);
+    assert_eq!(quirks.enabled(), vec!["wrap"]);
+
+    // Later entries win.
+    quirks.apply("display-wait,no-display-wait").unwrap();
+    assert!(!quirks.display_wait);
+}
+
+#[test]
+fn test_apply_unknown() {
+    let mut quirks = Quirks::new(Platform::Chip8);
+    assert_eq!(
+        quirks.apply("wrap,fast"),
+        Err("unknown quirk 'fast'".to_string())
+    );
+    assert_eq!(
+        quirks.apply("no-fast"),
+        Err("unknown quirk 'fast'".to_string())
+    );
+    assert!(quirks.apply("no-").is_err());
+}
@@ -0,0 +1,37 @@
+use crate::audio::{SAMPLES_PER_FRAME, SAMPLE_RATE};
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::Sdl;
+use std::mem;
+
+/// Frames of sound queued at most. Running faster than real time, e.g. in
+/// fast forward, drops the sound beyond that instead of lagging behind.
+const MAX_QUEUED_FRAMES: usize = 4;
+
+/// Plays the synthesized samples on the default audio device.
+pub struct Speaker {
+    queue: AudioQueue<i16>,
+}
+
+impl Speaker {
+    pub fn new(sdl_context: &Sdl) -> Result<Self, String> {
+        let spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: None,
+        };
+        let queue = sdl_context.audio()?.open_queue(None, &spec)?;
+        queue.resume();
+
+        Ok(Speaker { queue })
+    }
+
+    pub fn play(&mut self, samples: &[i16]) -> Result<(), String> {
+        let queued = self.queue.size() as usize / mem::size_of::<i16>();
+
+        if queued > MAX_QUEUED_FRAMES * SAMPLES_PER_FRAME {
+            return Ok(());
+        }
+
+        self.queue.queue_audio(samples)
+    }
+}
@@ -0,0 +1,155 @@
+#[cfg(test)]
+#[path = "./symbols_test.rs"]
+mod symbols_test;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+/// Extension of symbol files looked up next to a ROM.
+pub const SYMBOL_EXTENSION: &str = "sym";
+
+/// Labels of a ROM by address, read from a symbol file of an assembler.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Symbols {
+    names: BTreeMap<u16, String>,
+    addresses: HashMap<String, u16>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Symbols::default()
+    }
+
+    /// Parses one symbol per line, either as `ADDRESS NAME`, `NAME ADDRESS`
+    /// or Octo style as `NAME = ADDRESS` and `:const NAME ADDRESS`.
+    /// Addresses are hex, and need a `0x` prefix in `NAME ADDRESS`. Empty
+    /// lines and lines starting with `#` or `;` are skipped.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut symbols = Symbols::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            let octo = line.starts_with(':') || line.contains('=');
+            let fields: Vec<&str> = line
+                .split(|c: char| c.is_whitespace() || c == '=')
+                .filter(|field| !field.is_empty())
+                .skip_while(|field| field.starts_with(':'))
+                .collect();
+
+            let (address, name) = match fields.as_slice() {
+                [first, second] => split_symbol(first, second, octo),
+                _ => None,
+            }
+            .ok_or(format!(
+                "line {}: expected 'ADDRESS NAME', got '{}'",
+                number + 1,
+                line
+            ))?;
+
+            symbols.insert(address, name);
+        }
+
+        Ok(symbols)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read symbols '{}': {}", path, err))?;
+
+        Symbols::parse(&text)
+    }
+
+    /// Adds a symbol. The first name given to an address is the one shown.
+    pub fn insert(&mut self, address: u16, name: &str) {
+        self.names
+            .entry(address)
+            .or_insert_with(|| name.to_string());
+        self.addresses.insert(name.to_string(), address);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    /// Names `address` relative to the closest symbol at or before it, e.g.
+    /// `draw+4`.
+    pub fn locate(&self, address: u16) -> Option<String> {
+        let (&start, name) = self.names.range(..=address).next_back()?;
+
+        if start == address {
+            Some(name.clone())
+        } else {
+            Some(format!("{}+{}", name, address - start))
+        }
+    }
+
+    /// Formats `address` in hex followed by its location, e.g.
+    /// `0206 <draw+2>`.
+    pub fn describe(&self, address: u16) -> String {
+        match self.locate(address) {
+            Some(location) => format!("{:04X} <{}>", address, location),
+            None => format!("{:04X}", address),
+        }
+    }
+
+    /// Resolves a location given as a name, a name with a decimal offset
+    /// like `draw+4`, or a hex address with a `0x` prefix.
+    pub fn resolve(&self, location: &str) -> Result<u16, String> {
+        let location = location.trim();
+        let (name, offset) = match location.split_once('+') {
+            Some((name, offset)) => (name.trim(), offset.trim().parse::<u16>().ok()),
+            None => (location, Some(0)),
+        };
+
+        if let (Some(address), Some(offset)) = (self.address(name), offset) {
+            return Ok(address.wrapping_add(offset));
+        }
+
+        location
+            .strip_prefix("0x")
+            .and_then(parse_hex)
+            .ok_or(format!("unknown symbol '{}'", location))
+    }
+}
+
+/// Tells the address from the name. A field with a `0x` prefix is the
+/// address. Without one, the address comes second in the Octo forms and
+/// first otherwise, followed by a name that doesn't start with a digit.
+fn split_symbol<'a>(first: &'a str, second: &'a str, octo: bool) -> Option<(u16, &'a str)> {
+    let prefixed = |field: &str| field.strip_prefix("0x").and_then(parse_hex);
+
+    if let Some(address) = prefixed(first) {
+        return Some((address, second));
+    }
+
+    if let Some(address) = prefixed(second) {
+        return Some((address, first));
+    }
+
+    if octo {
+        return parse_hex(second).map(|address| (address, first));
+    }
+
+    if second.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    parse_hex(first).map(|address| (address, second))
+}
+
+fn parse_hex(value: &str) -> Option<u16> {
+    u16::from_str_radix(value, 16).ok()
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+#[test]
+fn test_parse() {
+    let text = "# labels\n0x200 main\ndraw 0x0206\n\nsprite = 0x300\n:const score 310\n";
+    let symbols = Symbols::parse(text).unwrap();
+    assert_eq!(symbols.address("main"), Some(0x200));
+    assert_eq!(symbols.address("draw"), Some(0x206));
+    assert_eq!(symbols.name(0x300), Some("sprite"));
+    assert_eq!(symbols.name(0x310), Some("score"));
+}
+
+#[test]
+fn test_parse_hex_names() {
+    let symbols = Symbols::parse("add 0x202\n204 beef\n").unwrap();
+    assert_eq!(symbols.address("add"), Some(0x202));
+    assert_eq!(symbols.address("beef"), Some(0x204));
+
+    // Without a prefix, a name that looks like hex isn't taken as the
+    // address.
+    assert!(Symbols::parse("add 200\n").is_err());
+    assert!(Symbols::parse("main 200\n").is_err());
+    assert_eq!(
+        Symbols::parse("add = 200\n").unwrap().address("add"),
+        Some(0x200)
+    );
+}
+
+#[test]
+fn test_parse_error() {
+    assert_eq!(
+        Symbols::parse("0x200 main\nmain\n"),
+        Err("line 2: expected 'ADDRESS NAME', got 'main'".to_string())
+    );
+    assert!(Symbols::parse("main loop").is_err());
+}
+
+#[test]
+fn test_locate() {
+    let mut symbols = Symbols::new();
+    symbols.insert(0x200, "main");
+    symbols.insert(0x206, "draw");
+    assert_eq!(symbols.locate(0x206), Some("draw".to_string()));
+    assert_eq!(symbols.locate(0x20a), Some("draw+4".to_string()));
+    assert_eq!(symbols.locate(0x100), None);
+    assert_eq!(symbols.describe(0x202), "0202 <main+2>");
+    assert_eq!(symbols.describe(0x100), "0100");
+}
+
+#[test]
+fn test_resolve() {
+    let mut symbols = Symbols::new();
+    symbols.insert(0x206, "draw");
+    assert_eq!(symbols.resolve("draw"), Ok(0x206));
+    assert_eq!(symbols.resolve("draw+4"), Ok(0x20a));
+    assert_eq!(symbols.resolve("0x300"), Ok(0x300));
+    assert!(symbols.resolve("dead").is_err());
+    assert!(symbols.resolve("add+2").is_err());
+    assert_eq!(
+        symbols.resolve("missing"),
+        Err("unknown symbol 'missing'".to_string())
+    );
+}
@@ -0,0 +1,454 @@
+#[cfg(test)]
+#[path = "./trace_test.rs"]
+mod trace_test;
+
+use crate::chip8::CpuState;
+use crate::disassembler::disassemble_with_symbols;
+use crate::platform::Platform;
+use crate::symbols::Symbols;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+/// Number of instructions shown before a divergence.
+const HISTORY_SIZE: usize = 8;
+
+/// One executed instruction with the CPU state before and after it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEntry {
+    pub cycle: u64,
+    pub opcode: u16,
+    pub before: CpuState,
+    pub after: CpuState,
+    /// RAM writes as address and value.
+    pub writes: Vec<(usize, u8)>,
+}
+
+/// Coarse grouping of the instructions, used to filter traces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpcodeClass {
+    /// Jumps, calls and returns.
+    Flow,
+    /// Conditional skips, except the key checks.
+    Skip,
+    /// Register loads and arithmetic.
+    Arithmetic,
+    /// I and RAM access.
+    Memory,
+    Display,
+    Timer,
+    Input,
+    /// Machine code calls and platform extensions.
+    System,
+}
+
+impl OpcodeClass {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "flow" => Ok(OpcodeClass::Flow),
+            "skip" => Ok(OpcodeClass::Skip),
+            "arithmetic" => Ok(OpcodeClass::Arithmetic),
+            "memory" => Ok(OpcodeClass::Memory),
+            "display" => Ok(OpcodeClass::Display),
+            "timer" => Ok(OpcodeClass::Timer),
+            "input" => Ok(OpcodeClass::Input),
+            "system" => Ok(OpcodeClass::System),
+            _ => Err(format!("unknown opcode class '{}'", value)),
+        }
+    }
+
+    /// Classifies `opcode` by its bit pattern alone, so platform specific
+    /// instructions are grouped with the ones they share a prefix with.
+    pub fn of(opcode: u16) -> Self {
+        match (opcode >> 12, opcode & 0x00FF) {
+            (0x0, 0xEE) if opcode >> 8 == 0 => OpcodeClass::Flow,
+            (0x0, 0xE0) | (0x0, 0xB0..=0xCF) | (0x0, 0xFB..=0xFF) if opcode >> 8 == 0 => {
+                OpcodeClass::Display
+            }
+            (0x0, _) => OpcodeClass::System,
+            (0x1, _) | (0x2, _) | (0xB, _) => OpcodeClass::Flow,
+            (0x3, _) | (0x4, _) | (0x5, _) | (0x9, _) => OpcodeClass::Skip,
+            (0x6, _) | (0x7, _) | (0x8, _) | (0xC, _) => OpcodeClass::Arithmetic,
+            (0xD, _) | (0xF, 0x29) => OpcodeClass::Display,
+            (0xE, _) | (0xF, 0x0A) | (0xF, 0xFB) => OpcodeClass::Input,
+            (0xF, 0x07) | (0xF, 0x15) | (0xF, 0x18) => OpcodeClass::Timer,
+            (0xF, 0xF8) => OpcodeClass::System,
+            _ => OpcodeClass::Memory,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    /// Mnemonics and the changes made by each instruction.
+    Text,
+    /// Complete register state after each instruction, for comparing runs.
+    State,
+}
+
+impl TraceFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(TraceFormat::Text),
+            "state" => Ok(TraceFormat::State),
+            _ => Err(format!("unknown trace format '{}'", value)),
+        }
+    }
+}
+
+/// Registers compared when verifying against a reference trace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceState {
+    pub opcode: u16,
+    pub pc: u16,
+    pub i: u32,
+    pub v: [u8; 16],
+    pub sp: u16,
+    pub dt: u8,
+    pub st: u8,
+}
+
+impl TraceState {
+    pub fn from_entry(entry: &TraceEntry) -> Self {
+        let after = &entry.after;
+
+        TraceState {
+            opcode: entry.opcode,
+            pc: after.pc,
+            i: after.i,
+            v: after.v,
+            sp: after.sp,
+            dt: after.dt,
+            st: after.st,
+        }
+    }
+
+    /// Parses a line of the state format, e.g.
+    /// `12 6A02 PC=0206 I=0300 V=00...00 SP=0 DT=00 ST=00`. The leading
+    /// cycle is ignored.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let error = || format!("invalid state line '{}'", line);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        if fields.len() != 8 {
+            return Err(error());
+        }
+
+        let hex = |field: &str, name: &str| -> Result<u32, String> {
+            let value = field.strip_prefix(name).ok_or_else(error)?;
+            u32::from_str_radix(value, 16).map_err(|_| error())
+        };
+
+        let registers = fields[4].strip_prefix("V=").ok_or_else(error)?;
+        let mut v = [0; 16];
+
+        if registers.len() != 32 {
+            return Err(error());
+        }
+
+        for (register, value) in v.iter_mut().enumerate() {
+            let digits = registers
+                .get(register * 2..register * 2 + 2)
+                .ok_or_else(error)?;
+            *value = u8::from_str_radix(digits, 16).map_err(|_| error())?;
+        }
+
+        Ok(TraceState {
+            opcode: hex(fields[1], "")? as u16,
+            pc: hex(fields[2], "PC=")? as u16,
+            i: hex(fields[3], "I=")?,
+            v,
+            sp: hex(fields[5], "SP=")? as u16,
+            dt: hex(fields[6], "DT=")? as u8,
+            st: hex(fields[7], "ST=")? as u8,
+        })
+    }
+
+    fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            ("opcode".to_string(), format!("{:04X}", self.opcode)),
+            ("PC".to_string(), format!("{:04X}", self.pc)),
+            ("I".to_string(), format!("{:04X}", self.i)),
+        ];
+
+        for (register, value) in self.v.iter().enumerate() {
+            fields.push((format!("V{:X}", register), format!("{:02X}", value)));
+        }
+
+        fields.push(("SP".to_string(), format!("{:X}", self.sp)));
+        fields.push(("DT".to_string(), format!("{:02X}", self.dt)));
+        fields.push(("ST".to_string(), format!("{:02X}", self.st)));
+        fields
+    }
+}
+
+/// Selects the instructions that are written to a trace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceFilter {
+    /// Inclusive range of traced instruction addresses.
+    pub pc_range: Option<(u16, u16)>,
+    /// Traced opcode classes. Empty means all of them.
+    pub classes: Vec<OpcodeClass>,
+}
+
+impl TraceFilter {
+    /// Parses a hex address range, e.g. `200-2ff`.
+    pub fn parse_pc_range(value: &str) -> Result<(u16, u16), String> {
+        let error = || format!("invalid address range '{}', expected START-END", value);
+        let (start, end) = value.split_once('-').ok_or_else(error)?;
+        let address = |value: &str| {
+            u16::from_str_radix(value.trim().trim_start_matches("0x"), 16).map_err(|_| error())
+        };
+
+        Ok((address(start)?, address(end)?))
+    }
+
+    /// Parses a comma-separated list of opcode classes.
+    pub fn parse_classes(value: &str) -> Result<Vec<OpcodeClass>, String> {
+        value.split(',').map(OpcodeClass::parse).collect()
+    }
+
+    pub fn matches(&self, entry: &TraceEntry) -> bool {
+        let pc = entry.before.pc;
+        let in_range = self
+            .pc_range
+            .is_none_or(|(start, end)| pc >= start && pc <= end);
+
+        in_range
+            && (self.classes.is_empty() || self.classes.contains(&OpcodeClass::of(entry.opcode)))
+    }
+}
+
+/// Writes one line per executed instruction. The text format has the
+/// cycle, the address, the opcode, its mnemonic and the registers and RAM
+/// it changed, e.g.
+///
+/// ```text
+/// 42 0204 6A02 LD VA, 0x02 VA=02
+/// ```
+///
+/// With symbols, jump and call targets are shown by name and a `name:`
+/// line precedes the instructions at a symbol.
+///
+/// The state format has the cycle, the opcode and all registers after it.
+pub struct Tracer<W: Write> {
+    writer: W,
+    filter: TraceFilter,
+    format: TraceFormat,
+    platform: Platform,
+    symbols: Symbols,
+}
+
+impl Tracer<BufWriter<File>> {
+    pub fn create(
+        path: &str,
+        filter: TraceFilter,
+        format: TraceFormat,
+        platform: Platform,
+    ) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|err| format!("failed to create trace file '{}': {}", path, err))?;
+
+        Ok(Tracer::new(BufWriter::new(file), filter, format, platform))
+    }
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(writer: W, filter: TraceFilter, format: TraceFormat, platform: Platform) -> Self {
+        Tracer {
+            writer,
+            filter,
+            format,
+            platform,
+            symbols: Symbols::new(),
+        }
+    }
+
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    pub fn record(&mut self, entry: &TraceEntry) -> io::Result<()> {
+        if !self.filter.matches(entry) {
+            return Ok(());
+        }
+
+        let line = match self.format {
+            TraceFormat::Text => format_text(entry, self.platform, &self.symbols),
+            TraceFormat::State => format_state(entry),
+        };
+
+        if self.format == TraceFormat::Text {
+            if let Some(name) = self.symbols.name(entry.before.pc) {
+                writeln!(self.writer, "{}:", name)?;
+            }
+        }
+
+        writeln!(self.writer, "{}", line)
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+}
+
+/// Compares every executed instruction with the next line of a reference
+/// trace in the state format.
+pub struct Verifier<R: BufRead> {
+    reference: io::Lines<R>,
+    line: usize,
+    history: VecDeque<TraceEntry>,
+    platform: Platform,
+    finished: bool,
+}
+
+impl Verifier<BufReader<File>> {
+    pub fn open(path: &str, platform: Platform) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|err| format!("failed to open reference trace '{}': {}", path, err))?;
+
+        Ok(Verifier::new(BufReader::new(file), platform))
+    }
+}
+
+impl<R: BufRead> Verifier<R> {
+    pub fn new(reference: R, platform: Platform) -> Self {
+        Verifier {
+            reference: reference.lines(),
+            line: 0,
+            history: VecDeque::with_capacity(HISTORY_SIZE),
+            platform,
+            finished: false,
+        }
+    }
+
+    /// Checks `entry` against the reference. The error describes the first
+    /// divergence, with both states side by side and the instructions that
+    /// led to it.
+    pub fn check(&mut self, entry: &TraceEntry) -> Result<(), String> {
+        if self.finished {
+            return Ok(());
+        }
+
+        let expected = match self.reference.next() {
+            Some(line) => {
+                self.line += 1;
+                let line =
+                    line.map_err(|err| format!("failed to read reference trace: {}", err))?;
+                TraceState::parse(&line).map_err(|err| format!("line {}: {}", self.line, err))?
+            }
+            None => {
+                self.finished = true;
+                return Ok(());
+            }
+        };
+
+        let actual = TraceState::from_entry(entry);
+
+        if expected != actual {
+            return Err(self.report(entry, &expected, &actual));
+        }
+
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(entry.clone());
+        Ok(())
+    }
+
+    /// Returns true once every line of the reference has been matched.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Number of reference lines checked so far.
+    pub fn checked(&self) -> usize {
+        self.line
+    }
+
+    fn report(&self, entry: &TraceEntry, expected: &TraceState, actual: &TraceState) -> String {
+        let mut report = format!(
+            "divergence at cycle {} (reference line {})\n{:<8}{:<10}{}\n",
+            entry.cycle, self.line, "", "expected", "actual"
+        );
+
+        for ((name, expected), (_, actual)) in expected.fields().iter().zip(actual.fields()) {
+            let marker = if *expected != actual { " <" } else { "" };
+            let _ = writeln!(report, "{:<8}{:<10}{}{}", name, expected, actual, marker);
+        }
+
+        report.push_str("recent instructions:");
+
+        for previous in self.history.iter().chain(Some(entry)) {
+            let _ = write!(
+                report,
+                "\n  {}",
+                format_text(previous, self.platform, &Symbols::new())
+            );
+        }
+
+        report
+    }
+}
+
+fn format_text(entry: &TraceEntry, platform: Platform, symbols: &Symbols) -> String {
+    format!(
+        "{} {:04X} {:04X} {}{}",
+        entry.cycle,
+        entry.before.pc,
+        entry.opcode,
+        disassemble_with_symbols(entry.opcode, platform, symbols),
+        format_changes(entry)
+    )
+}
+
+fn format_state(entry: &TraceEntry) -> String {
+    let state = TraceState::from_entry(entry);
+    let registers: String = state
+        .v
+        .iter()
+        .map(|value| format!("{:02X}", value))
+        .collect();
+
+    format!(
+        "{} {:04X} PC={:04X} I={:04X} V={} SP={:X} DT={:02X} ST={:02X}",
+        entry.cycle, state.opcode, state.pc, state.i, registers, state.sp, state.dt, state.st
+    )
+}
+
+/// Lists the registers and RAM changed by the instruction. The program
+/// counter is left out, since the next line shows it.
+fn format_changes(entry: &TraceEntry) -> String {
+    let (before, after) = (&entry.before, &entry.after);
+    let mut changes = String::new();
+
+    for (register, (old, new)) in before.v.iter().zip(after.v.iter()).enumerate() {
+        if old != new {
+            let _ = write!(changes, " V{:X}={:02X}", register, new);
+        }
+    }
+
+    if before.i != after.i {
+        let _ = write!(changes, " I={:04X}", after.i);
+    }
+
+    if before.sp != after.sp {
+        let _ = write!(changes, " SP={:X}", after.sp);
+    }
+
+    if before.dt != after.dt {
+        let _ = write!(changes, " DT={:02X}", after.dt);
+    }
+
+    if before.st != after.st {
+        let _ = write!(changes, " ST={:02X}", after.st);
+    }
+
+    for (address, value) in &entry.writes {
+        let _ = write!(changes, " [{:04X}]={:02X}", address, value);
+    }
+
+    changes
+}
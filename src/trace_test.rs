@@ -0,0 +1,243 @@
+use super::*;
+use crate::chip8::{Chip8, TraceHook};
+use crate::quirks::Quirks;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn create_state(pc: u16) -> CpuState {
+    CpuState {
+        pc,
+        i: 0,
+        v: [0; 16],
+        sp: 0,
+        stack: [0; 16],
+        dt: 0,
+        st: 0,
+    }
+}
+
+fn create_entry(pc: u16, opcode: u16) -> TraceEntry {
+    TraceEntry {
+        cycle: 7,
+        opcode,
+        before: create_state(pc),
+        after: create_state(pc + 2),
+        writes: Vec::new(),
+    }
+}
+
+fn record(filter: TraceFilter, entry: &TraceEntry) -> String {
+    let mut tracer = Tracer::new(Vec::new(), filter, TraceFormat::Text, Platform::Chip8);
+    tracer.record(entry).unwrap();
+    String::from_utf8(tracer.get_ref().clone()).unwrap()
+}
+
+#[test]
+fn test_record() {
+    let mut entry = create_entry(0x204, 0x6a02);
+    entry.after.v[0x0a] = 0x02;
+    assert_eq!(
+        record(TraceFilter::default(), &entry),
+        "7 0204 6A02 LD VA, 0x02 VA=02\n"
+    );
+}
+
+#[test]
+fn test_record_with_symbols() {
+    let mut symbols = Symbols::new();
+    symbols.insert(0x204, "main");
+    symbols.insert(0x300, "draw");
+    let mut tracer = Tracer::new(
+        Vec::new(),
+        TraceFilter::default(),
+        TraceFormat::Text,
+        Platform::Chip8,
+    );
+    tracer.set_symbols(symbols);
+    let mut entry = create_entry(0x204, 0x2300);
+    entry.after.pc = 0x300;
+    tracer.record(&entry).unwrap();
+    assert_eq!(
+        String::from_utf8(tracer.get_ref().clone()).unwrap(),
+        "main:\n7 0204 2300 CALL draw\n"
+    );
+}
+
+#[test]
+fn test_record_memory_writes() {
+    let mut entry = create_entry(0x204, 0xf033);
+    entry.writes = vec![(0x300, 0x01), (0x301, 0x02)];
+    let line = record(TraceFilter::default(), &entry);
+    assert!(line.ends_with("LD B, V0 [0300]=01 [0301]=02\n"));
+}
+
+#[test]
+fn test_filter_pc_range() {
+    let filter = TraceFilter {
+        pc_range: Some(TraceFilter::parse_pc_range("200-2ff").unwrap()),
+        classes: Vec::new(),
+    };
+
+    assert!(filter.matches(&create_entry(0x2fe, 0x00e0)));
+    assert!(!filter.matches(&create_entry(0x300, 0x00e0)));
+}
+
+#[test]
+fn test_filter_classes() {
+    let filter = TraceFilter {
+        pc_range: None,
+        classes: TraceFilter::parse_classes("flow,display").unwrap(),
+    };
+
+    assert!(filter.matches(&create_entry(0x200, 0x1234)));
+    assert!(filter.matches(&create_entry(0x200, 0xd125)));
+    assert!(!filter.matches(&create_entry(0x200, 0x6a02)));
+    assert_eq!(record(filter, &create_entry(0x200, 0x6a02)), "");
+}
+
+#[test]
+fn test_opcode_class() {
+    assert_eq!(OpcodeClass::of(0x00ee), OpcodeClass::Flow);
+    assert_eq!(OpcodeClass::of(0x00e0), OpcodeClass::Display);
+    assert_eq!(OpcodeClass::of(0x0230), OpcodeClass::System);
+    assert_eq!(OpcodeClass::of(0xe19e), OpcodeClass::Input);
+    assert_eq!(OpcodeClass::of(0xf115), OpcodeClass::Timer);
+    assert_eq!(OpcodeClass::of(0xf155), OpcodeClass::Memory);
+}
+
+#[test]
+fn test_invalid_filter() {
+    assert!(TraceFilter::parse_pc_range("200").is_err());
+    assert!(TraceFilter::parse_pc_range("200-xyz").is_err());
+    assert!(TraceFilter::parse_classes("flow,jumps").is_err());
+}
+
+#[test]
+fn test_record_state() {
+    let mut entry = create_entry(0x204, 0x6a02);
+    entry.after.v[0x0a] = 0x02;
+    entry.after.i = 0x300;
+    let mut tracer = Tracer::new(
+        Vec::new(),
+        TraceFilter::default(),
+        TraceFormat::State,
+        Platform::Chip8,
+    );
+    tracer.record(&entry).unwrap();
+    let line = String::from_utf8(tracer.get_ref().clone()).unwrap();
+    assert_eq!(
+        line,
+        "7 6A02 PC=0206 I=0300 V=00000000000000000000020000000000 SP=0 DT=00 ST=00\n"
+    );
+    assert_eq!(
+        TraceState::parse(line.trim()),
+        Ok(TraceState::from_entry(&entry))
+    );
+}
+
+#[test]
+fn test_parse_invalid_state() {
+    assert!(TraceState::parse("7 6A02 PC=0206").is_err());
+    assert!(TraceState::parse("7 6A02 PC=0206 I=0300 V=00 SP=0 DT=00 ST=00").is_err());
+    assert!(TraceState::parse(
+        "7 6A02 PC=XYZ I=0300 V=00000000000000000000020000000000 SP=0 DT=00 ST=00"
+    )
+    .is_err());
+}
+
+fn reference(entries: &[TraceEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| format_state(entry) + "\n")
+        .collect()
+}
+
+#[test]
+fn test_verify() {
+    let entries = vec![create_entry(0x200, 0x00e0), create_entry(0x202, 0x1200)];
+    let reference = reference(&entries);
+    let mut verifier = Verifier::new(reference.as_bytes(), Platform::Chip8);
+
+    for entry in &entries {
+        assert_eq!(verifier.check(entry), Ok(()));
+    }
+
+    assert!(!verifier.finished());
+    assert_eq!(verifier.check(&create_entry(0x204, 0x00e0)), Ok(()));
+    assert!(verifier.finished());
+    assert_eq!(verifier.checked(), 2);
+}
+
+#[test]
+fn test_verify_divergence() {
+    let entries = vec![create_entry(0x200, 0x00e0), create_entry(0x202, 0x6a02)];
+    let reference = reference(&entries);
+    let mut verifier = Verifier::new(reference.as_bytes(), Platform::Chip8);
+    let mut diverged = entries[1].clone();
+    diverged.after.v[0x0a] = 0x03;
+
+    assert_eq!(verifier.check(&entries[0]), Ok(()));
+    let report = verifier.check(&diverged).unwrap_err();
+    assert!(report.starts_with("divergence at cycle 7 (reference line 2)"));
+    assert!(report.contains("VA      00        03 <"));
+    assert!(report.contains("0200 00E0 CLS"));
+    assert!(report.ends_with("0202 6A02 LD VA, 0x02 VA=03"));
+}
+
+/// Runs a ROM that draws random numbers with CXKK for a few frames.
+fn run_random(seed: u64, hook: TraceHook) -> Chip8 {
+    let mut chip8 = Chip8::new(Platform::Chip8, Quirks::new(Platform::Chip8));
+    chip8.set_seed(seed);
+    chip8.load(&[0xc0, 0xff, 0xc1, 0x0f, 0x12, 0x00]);
+    chip8.add_trace_hook(hook);
+
+    for _ in 0..4 {
+        chip8.run_frame([false; 16], 10);
+    }
+
+    chip8
+}
+
+#[test]
+fn test_verify_random() {
+    let tracer = Rc::new(RefCell::new(Tracer::new(
+        Vec::new(),
+        TraceFilter::default(),
+        TraceFormat::State,
+        Platform::Chip8,
+    )));
+    let hook_tracer = Rc::clone(&tracer);
+    run_random(
+        7,
+        Box::new(move |entry| {
+            hook_tracer
+                .borrow_mut()
+                .record(entry)
+                .map_err(|err| err.to_string())
+        }),
+    );
+    let reference = tracer.borrow().get_ref().clone();
+
+    let verifier = Rc::new(RefCell::new(Verifier::new(
+        io::Cursor::new(reference.clone()),
+        Platform::Chip8,
+    )));
+    let hook_verifier = Rc::clone(&verifier);
+    let chip8 = run_random(
+        7,
+        Box::new(move |entry| hook_verifier.borrow_mut().check(entry)),
+    );
+    assert_eq!(chip8.error(), None);
+    assert_eq!(verifier.borrow().checked(), 40);
+
+    let verifier = Rc::new(RefCell::new(Verifier::new(
+        io::Cursor::new(reference.clone()),
+        Platform::Chip8,
+    )));
+    let hook_verifier = Rc::clone(&verifier);
+    let chip8 = run_random(
+        8,
+        Box::new(move |entry| hook_verifier.borrow_mut().check(entry)),
+    );
+    assert!(chip8.error().unwrap().starts_with("divergence at cycle"));
+}
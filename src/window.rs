@@ -1,67 +1,184 @@
-use crate::memory::Memory;
+use crate::framebuffer::{Framebuffer, BYTES_PER_PIXEL};
+use crate::overlay::{text_pixels, text_width, GLYPH_HEIGHT, GLYPH_SPACING};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{FullscreenType, WindowContext};
 
 pub const WIDTH_LO_RES: usize = 64;
 pub const HEIGHT_LO_RES: usize = 32;
 pub const WIDTH_HI_RES: usize = 128;
 pub const HEIGHT_HI_RES: usize = 64;
-const SCALE_LO_RES: u32 = 16;
-const SCALE_HI_RES: u32 = 8;
-const SCREEN_WIDTH: u32 = WIDTH_LO_RES as u32 * SCALE_LO_RES;
-const SCREEN_HEIGHT: u32 = HEIGHT_LO_RES as u32 * SCALE_LO_RES;
-const COLOR_ON: sdl2::pixels::Color = sdl2::pixels::Color::RGB(255, 255, 255);
-const COLOR_OFF: sdl2::pixels::Color = sdl2::pixels::Color::RGB(0, 0, 0);
-
-pub struct Window {
-    canvas: sdl2::render::Canvas<sdl2::video::Window>,
+pub const WIDTH_MEGA_CHIP: usize = 256;
+pub const HEIGHT_MEGA_CHIP: usize = 192;
+pub const DEFAULT_SCALE: u32 = 16;
+/// Output height per overlay pixel, so the text grows with the window.
+const OVERLAY_PIXELS_PER_ROW: u32 = 160;
+
+/// Opens the window at `scale` times the 64x32 display.
+pub fn create_canvas(sdl_context: &sdl2::Sdl, scale: u32) -> Canvas<sdl2::video::Window> {
+    let video_subsystem = sdl_context.video().unwrap();
+
+    let window = video_subsystem
+        .window(
+            "CHIP-8 Interpreter",
+            WIDTH_LO_RES as u32 * scale,
+            HEIGHT_LO_RES as u32 * scale,
+        )
+        .position_centered()
+        .resizable()
+        .build()
+        .unwrap();
+
+    let mut canvas = window.into_canvas().build().unwrap();
+
+    canvas.clear();
+    canvas.present();
+    canvas
 }
 
-impl Window {
-    pub fn new(sdl_context: &sdl2::Sdl) -> Self {
-        let video_subsystem = sdl_context.video().unwrap();
+pub struct Window<'a> {
+    canvas: Canvas<sdl2::video::Window>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    texture: Option<Texture<'a>>,
+    integer_scale: bool,
+}
 
-        let window = video_subsystem
-            .window("CHIP-8 Interpreter", SCREEN_WIDTH, SCREEN_HEIGHT)
-            .position_centered()
-            .opengl()
-            .build()
-            .unwrap();
+impl<'a> Window<'a> {
+    /// Draws on `canvas` with textures of `texture_creator`, which belongs
+    /// to the canvas.
+    pub fn new(
+        canvas: Canvas<sdl2::video::Window>,
+        texture_creator: &'a TextureCreator<WindowContext>,
+        integer_scale: bool,
+    ) -> Self {
+        Window {
+            canvas,
+            texture_creator,
+            texture: None,
+            integer_scale,
+        }
+    }
 
-        let mut canvas = window.into_canvas().build().unwrap();
+    /// Draws the framebuffer with the `overlay` lines on top of it.
+    pub fn draw(&mut self, framebuffer: &Framebuffer, overlay: &[String]) {
+        let width = framebuffer.width() as u32;
+        let height = framebuffer.height() as u32;
 
-        canvas.set_draw_color(COLOR_OFF);
-        canvas.clear();
-        canvas.present();
+        let outdated = self.texture.as_ref().is_none_or(|texture| {
+            let query = texture.query();
+            query.width != width || query.height != height
+        });
 
-        Window { canvas }
+        if outdated {
+            self.texture = Some(
+                self.texture_creator
+                    .create_texture_streaming(PixelFormatEnum::RGB24, width, height)
+                    .unwrap(),
+            );
+        }
+
+        let target = self.get_target(width, height);
+        let texture = self.texture.as_mut().unwrap();
+
+        let _ = texture.update(
+            None,
+            framebuffer.pixels(),
+            framebuffer.width() * BYTES_PER_PIXEL,
+        );
+
+        self.canvas.set_draw_color(framebuffer.background());
+        self.canvas.clear();
+        let _ = self.canvas.copy(texture, None, target);
+        self.draw_overlay(overlay);
+        self.canvas.present();
     }
 
-    pub fn draw(&mut self, memory: &mut Memory, hi_res: bool) {
-        let height = if hi_res { HEIGHT_HI_RES } else { HEIGHT_LO_RES };
-        let width = if hi_res { WIDTH_HI_RES } else { WIDTH_LO_RES };
-        let scale = if hi_res { SCALE_HI_RES } else { SCALE_LO_RES };
+    pub fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
 
-        for y in 0..height {
-            for x in 0..width {
-                let color = self.get_color(memory.read_vram(x, y));
-                let x = (x as u32) * scale;
-                let y = (y as u32) * scale;
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
 
-                self.canvas.set_draw_color(color);
+        let _ = window.set_fullscreen(fullscreen);
+    }
 
-                let _ = self
-                    .canvas
-                    .fill_rect(sdl2::rect::Rect::new(x as i32, y as i32, scale, scale));
-            }
-        }
+    /// Draws the menu `lines` on a black screen, scaled so that `rows`
+    /// lines fill its height. Lines wider than the window are cut off.
+    pub fn draw_menu(&mut self, lines: &[String], rows: usize) {
+        let output_height = self.canvas.output_size().map_or(0, |(_, height)| height);
+        let height = (rows.max(1) * (GLYPH_HEIGHT + GLYPH_SPACING * 2)) as u32;
+        let scale = (output_height / height).max(1);
 
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        self.draw_text(lines, scale as i32);
         self.canvas.present();
     }
 
-    fn get_color(&mut self, value: u8) -> sdl2::pixels::Color {
-        if value == 0 {
-            COLOR_OFF
-        } else {
-            COLOR_ON
+    /// Draws the lines in white on black boxes in the top left corner.
+    fn draw_overlay(&mut self, lines: &[String]) {
+        let output_height = self.canvas.output_size().map_or(0, |(_, height)| height);
+        self.draw_text(
+            lines,
+            (output_height / OVERLAY_PIXELS_PER_ROW).max(1) as i32,
+        );
+    }
+
+    fn draw_text(&mut self, lines: &[String], scale: i32) {
+        let line_height = (GLYPH_HEIGHT + GLYPH_SPACING * 2) as i32;
+
+        for (index, line) in lines.iter().enumerate() {
+            let top = index as i32 * line_height + GLYPH_SPACING as i32;
+            let left = GLYPH_SPACING as i32;
+            let width = text_width(line) as i32 + GLYPH_SPACING as i32 * 2;
+
+            let pixels: Vec<Rect> = text_pixels(line)
+                .into_iter()
+                .map(|(x, y)| {
+                    Rect::new(
+                        (left + x as i32) * scale,
+                        (top + y as i32) * scale,
+                        scale as u32,
+                        scale as u32,
+                    )
+                })
+                .collect();
+
+            self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+            let _ = self.canvas.fill_rect(Rect::new(
+                (left - GLYPH_SPACING as i32) * scale,
+                (top - GLYPH_SPACING as i32) * scale,
+                (width * scale) as u32,
+                (line_height * scale) as u32,
+            ));
+            self.canvas.set_draw_color(Color::RGB(255, 255, 255));
+            let _ = self.canvas.fill_rects(&pixels);
+        }
+    }
+
+    /// Scales the display as large as possible while keeping its aspect
+    /// ratio and centers it, leaving letterbox bars on the remaining sides.
+    fn get_target(&self, width: u32, height: u32) -> Option<Rect> {
+        let (output_width, output_height) = self.canvas.output_size().ok()?;
+        let scale_x = output_width as f32 / width as f32;
+        let scale_y = output_height as f32 / height as f32;
+        let mut scale = scale_x.min(scale_y);
+
+        if self.integer_scale && scale >= 1.0 {
+            scale = scale.floor();
         }
+
+        let target_width = (width as f32 * scale) as u32;
+        let target_height = (height as f32 * scale) as u32;
+
+        Some(Rect::new(
+            ((output_width - target_width) / 2) as i32,
+            ((output_height - target_height) / 2) as i32,
+            target_width,
+            target_height,
+        ))
     }
 }